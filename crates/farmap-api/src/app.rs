@@ -5,11 +5,17 @@ use axum::{
     Json, Router,
 };
 use chrono::prelude::*;
-use chrono::{Days, Months, NaiveDate};
+use chrono::{Days, NaiveDate};
+use farmap::survival::Spells;
+use farmap::CohortPeriod;
+use farmap::Cohorts;
+use farmap::Fid;
 use farmap::SetWithCastData;
 use farmap::SetWithSpamEntries;
+use farmap::SurvivalCurves;
 use farmap::TryFromUserSet;
 use farmap::UserCollectionWithNativeUserValue as UserCollection;
+use farmap::UserFilter;
 use farmap::UserWithSpamData;
 use log::info;
 use log::trace;
@@ -24,10 +30,13 @@ pub fn build_app(users: Arc<UserCollection>) -> Router {
     let app = Router::new()
         .route("/", get(root))
         .route("/{fid}", get(fid))
+        .route("/users/{fid}", get(user))
         .route(
             "/spam_score_distributions/{year}/{month}",
             get(spam_score_distributions_for_cohort),
         )
+        .route("/cohorts/{period}", get(cohort_table))
+        .route("/survival_curves", get(survival_curves))
         .route(
             "/spam_score_distribution",
             get(current_spam_score_distribution),
@@ -52,6 +61,7 @@ pub fn build_app(users: Arc<UserCollection>) -> Router {
 
     let allow_token = std::env::var("ALLOW_TOKEN").ok();
     if let Some(allow_token) = allow_token {
+        #[allow(deprecated)]
         app.layer(ValidateRequestHeaderLayer::bearer(&allow_token))
     } else {
         app
@@ -62,18 +72,24 @@ async fn root() -> &'static str {
     "This is a server for farmap data."
 }
 
-async fn current_spam_score_distribution(State(users): State<Arc<UserCollection>>) -> Json<Value> {
+async fn current_spam_score_distribution(
+    Query(filters): Query<Filters>,
+    State(users): State<Arc<UserCollection>>,
+) -> Result<Json<Value>, StatusCode> {
     let users_ref: &UserCollection = &users;
-    let set = SetWithSpamEntries::new(users_ref).unwrap();
+    let mut set = SetWithSpamEntries::new(users_ref).ok_or(StatusCode::NO_CONTENT)?;
+    set.filter_by(&filters.user_filter()?)
+        .ok_or(StatusCode::NO_CONTENT)?;
     let spam_score_distribution = set.current_spam_score_distribution();
-    Json(json!(spam_score_distribution))
+    Ok(Json(json!(spam_score_distribution)))
 }
 
 async fn latest_moves(
     Query(filters): Query<Filters>,
     Query(moves_filter): Query<MovesFilter>,
     State(users): State<Arc<UserCollection>>,
-) -> Json<Value> {
+) -> Result<Json<Value>, StatusCode> {
+    let filter = filters.user_filter()?;
     //last week changes.
     let current_time = Local::now().date_naive();
     let comparison_time = if let Some(days) = moves_filter.days {
@@ -85,64 +101,65 @@ async fn latest_moves(
     let users_ref: &UserCollection = &users;
 
     if let Some(mut set) = SetWithSpamEntries::new(users_ref) {
-        if let Some(to_fid) = filters.to_fid {
-            set.filter(|user: &UserWithSpamData| u64::from(user.fid()) <= to_fid);
-        };
-        if let Some(from_fid) = filters.from_fid {
-            set.filter(|user: &UserWithSpamData| u64::from(user.fid()) >= from_fid);
-        };
+        set.filter_by(&filter).ok_or(StatusCode::NO_CONTENT)?;
 
         let result = set.spam_changes_with_fid_score_shift(comparison_time, Days::new(21));
-        Json(json!(result))
+        Ok(Json(json!(result)))
     } else {
-        Json(serde_json::Value::Null)
+        Ok(Json(serde_json::Value::Null))
     }
 }
 
+/// The latest spam score of the fid. With `profile=true` the response is the same as the response
+/// of [`user`], which also holds the latest profile of the fid.
 async fn fid(
     Path(fid): Path<u64>,
+    Query(query): Query<FidQuery>,
     State(users): State<Arc<UserCollection>>,
 ) -> Result<Json<Value>, StatusCode> {
+    if query.profile.unwrap_or_default() {
+        return user(Path(fid), State(users)).await;
+    }
     let user = users.user(fid as usize).ok_or(StatusCode::NOT_FOUND)?;
     let spam_user = UserWithSpamData::try_from(user).map_err(|_| StatusCode::NOT_FOUND)?;
     let score = spam_user.latest_spam_update().score();
     Ok(Json(json!(score as u8)))
 }
 
+/// The latest spam score of the fid along with its latest profile. The profile is null if no
+/// profile is stored for the fid.
+async fn user(
+    Path(fid): Path<u64>,
+    State(users): State<Arc<UserCollection>>,
+) -> Result<Json<Value>, StatusCode> {
+    let user = users.user(fid as usize).ok_or(StatusCode::NOT_FOUND)?;
+    let spam_user = UserWithSpamData::try_from(user).map_err(|_| StatusCode::NOT_FOUND)?;
+    let score = spam_user.latest_spam_update().score();
+    Ok(Json(json!({
+        "fid": fid,
+        "score": score as u8,
+        "profile": user.profile(),
+    })))
+}
+
 async fn spam_score_distributions_for_cohort(
     Path((year, month)): Path<(u64, u64)>,
     State(users): State<Arc<UserCollection>>,
 ) -> Result<Json<Value>, StatusCode> {
     let users_ref: &UserCollection = &users;
-    let set = SetWithSpamEntries::new(users_ref);
-
-    let mut set = if let Some(set) = set {
-        set
-    } else {
-        return Err(StatusCode::NO_CONTENT);
-    };
+    let set = SetWithSpamEntries::new(users_ref).ok_or(StatusCode::NO_CONTENT)?;
 
     let cohort_start_date =
-        if let Some(date) = NaiveDate::from_ymd_opt(year as i32, month as u32, 1) {
-            date
-        } else {
-            return Err(StatusCode::BAD_REQUEST);
-        };
+        NaiveDate::from_ymd_opt(year as i32, month as u32, 1).ok_or(StatusCode::BAD_REQUEST)?;
 
-    trace!(
-        "checking spam score distributions for cohort created at of before {cohort_start_date:?}"
-    );
+    trace!("checking spam score distributions for cohort of {cohort_start_date:?}");
 
-    let cohort_end_date = cohort_start_date
-        .checked_add_months(Months::new(1))
-        .unwrap();
-
-    set.filter(|user: &UserWithSpamData| {
-        let earliest_spam_date = user.earliest_spam_update().date();
-        earliest_spam_date <= cohort_end_date && earliest_spam_date >= cohort_start_date
-    });
+    let cohorts = Cohorts::by_first_label(&set, CohortPeriod::Month);
+    let Some(cohort) = cohorts.cohort(&cohort_start_date) else {
+        return Ok(Json(json!([])));
+    };
 
-    let result = set.monthly_spam_score_distributions();
+    let result = cohort.monthly_spam_score_distributions();
     let result = result
         .iter()
         .map(|dated_distribution| {
@@ -156,6 +173,41 @@ async fn spam_score_distributions_for_cohort(
     Ok(Json(json!(result)))
 }
 
+/// The cohort table of the users grouped by the period of their first spam label.
+async fn cohort_table(
+    Path(period): Path<String>,
+    Query(filters): Query<Filters>,
+    State(users): State<Arc<UserCollection>>,
+) -> Result<Json<Value>, StatusCode> {
+    let period: CohortPeriod = period.parse().map_err(|err| {
+        info!("bad cohort period: {err}");
+        StatusCode::BAD_REQUEST
+    })?;
+    let users_ref: &UserCollection = &users;
+    let mut set = SetWithSpamEntries::new(users_ref).ok_or(StatusCode::NO_CONTENT)?;
+    set.filter_by(&filters.user_filter()?)
+        .ok_or(StatusCode::NO_CONTENT)?;
+
+    let table = Cohorts::by_first_label(&set, period).table(period);
+    Ok(Json(json!(table)))
+}
+
+/// The survival curves of the spam scores. The `spells` parameter is `first` to follow each user
+/// until the first change of score, which is the default, or `all` to include every spell.
+async fn survival_curves(
+    Query(query): Query<SurvivalQuery>,
+    Query(filters): Query<Filters>,
+    State(users): State<Arc<UserCollection>>,
+) -> Result<Json<Value>, StatusCode> {
+    let users_ref: &UserCollection = &users;
+    let mut set = SetWithSpamEntries::new(users_ref).ok_or(StatusCode::NO_CONTENT)?;
+    set.filter_by(&filters.user_filter()?)
+        .ok_or(StatusCode::NO_CONTENT)?;
+
+    let curves = SurvivalCurves::new(&set, query.spells.unwrap_or_default());
+    Ok(Json(json!(curves)))
+}
+
 async fn monthly_spam_score_distributions(
     State(users): State<Arc<UserCollection>>,
 ) -> Result<Json<Value>, StatusCode> {
@@ -180,12 +232,8 @@ async fn weekly_spam_score_distributions(
 ) -> Result<Json<Value>, StatusCode> {
     let users_ref: &UserCollection = &users;
     let mut set = SetWithSpamEntries::new(users_ref).ok_or(StatusCode::NO_CONTENT)?;
-    if let Some(to_fid) = filters.to_fid {
-        set.filter(|user: &UserWithSpamData| u64::from(user.fid()) <= to_fid);
-    };
-    if let Some(from_fid) = filters.from_fid {
-        set.filter(|user: &UserWithSpamData| u64::from(user.fid()) <= from_fid);
-    };
+    set.filter_by(&filters.user_filter()?)
+        .ok_or(StatusCode::NO_CONTENT)?;
 
     let result = set.weekly_spam_score_distributions();
 
@@ -195,20 +243,16 @@ async fn weekly_spam_score_distributions(
 async fn weekly_spam_score_counts(
     Query(filters): Query<Filters>,
     State(users): State<Arc<UserCollection>>,
-) -> Json<Value> {
+) -> Result<Json<Value>, StatusCode> {
+    let filter = filters.user_filter()?;
     let users_ref: &UserCollection = &users;
     if let Some(mut set) = SetWithSpamEntries::new(users_ref) {
-        if let Some(to_fid) = filters.to_fid {
-            set.filter(|user: &UserWithSpamData| u64::from(user.fid()) <= to_fid);
-        };
-        if let Some(from_fid) = filters.from_fid {
-            set.filter(|user: &UserWithSpamData| u64::from(user.fid()) >= from_fid);
-        };
+        set.filter_by(&filter).ok_or(StatusCode::NO_CONTENT)?;
 
         let counts = set.weekly_spam_score_counts();
-        Json(json!(counts))
+        Ok(Json(json!(counts)))
     } else {
-        Json(serde_json::Value::Null)
+        Ok(Json(serde_json::Value::Null))
     }
 }
 
@@ -229,24 +273,36 @@ async fn casts_for_moved(
     info!("checking with begin date {begin_date}");
     info!("checking with current time {current_time}");
 
-    set.filter(|user: &UserWithSpamData| {
-        user.spam_score_at_date(begin_date).is_some_and(|u| {
-            u == (from as usize)
-                .try_into()
-                .expect("already checked spam_score range")
+    // no user had the score at the begin date.
+    if set
+        .filter(|user: &UserWithSpamData| {
+            user.spam_score_at_date(begin_date).is_some_and(|u| {
+                u == (from as usize)
+                    .try_into()
+                    .expect("already checked spam_score range")
+            })
         })
-    });
+        .is_none()
+    {
+        return Ok(Json(json!([0, 0.0])));
+    }
 
     let set_size = set.user_count();
 
     info!("set size after begin_date filtering is {set_size}");
 
-    set.filter(|user: &UserWithSpamData| {
-        user.latest_spam_update().score()
-            == (to as usize)
-                .try_into()
-                .expect("already checked spam score range")
-    });
+    // no user moved to the score.
+    if set
+        .filter(|user: &UserWithSpamData| {
+            user.latest_spam_update().score()
+                == (to as usize)
+                    .try_into()
+                    .expect("already checked spam score range")
+        })
+        .is_none()
+    {
+        return Ok(Json(json!([set_size, 0.0])));
+    }
 
     info!("set size is {set_size}");
 
@@ -259,13 +315,44 @@ async fn casts_for_moved(
     Ok(Json(json!([set_size, average_total_casts])))
 }
 
+#[derive(Deserialize)]
+struct FidQuery {
+    profile: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct SurvivalQuery {
+    spells: Option<Spells>,
+}
+
 #[derive(Deserialize)]
 struct MovesFilter {
     days: Option<u64>,
 }
 
+/// The filters of a request. `filter` is a [`UserFilter`] expression or json and is combined with
+/// the inclusive fid range of `from_fid` and `to_fid`.
 #[derive(Deserialize)]
 struct Filters {
     from_fid: Option<u64>,
     to_fid: Option<u64>,
+    filter: Option<String>,
+}
+
+impl Filters {
+    /// The filter of the request. Returns a bad request status if the filter does not parse.
+    fn user_filter(&self) -> Result<UserFilter, StatusCode> {
+        let fid_range = UserFilter::FidRange {
+            from: self.from_fid.map(Fid::from),
+            to: self.to_fid.map(Fid::from),
+        };
+        let Some(expression) = &self.filter else {
+            return Ok(fid_range);
+        };
+        let filter = expression.parse::<UserFilter>().map_err(|err| {
+            info!("bad filter {expression:?}: {err}");
+            StatusCode::BAD_REQUEST
+        })?;
+        Ok(fid_range.and(filter))
+    }
 }
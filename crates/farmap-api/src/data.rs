@@ -1,22 +1,37 @@
 use axum::http::{HeaderMap, HeaderValue};
 use chrono::prelude::*;
 use chrono::Days;
+use farmap::fetch::github_parser::into_fidded_user_value_iter_from_commit;
 use farmap::fetch::github_parser::parse_commit_hash_body;
-use farmap::fetch::pinata_parser::cast_meta_from_pinata_response;
 use farmap::fetch::GithubFetcher;
 use farmap::fetch::ImporterError;
 use farmap::fetch::PinataFetcher;
+use farmap::fetch::WieldFetcher;
+use farmap::spam_score::CommitHash;
 use farmap::spam_score::DatedSpamUpdate;
+use farmap::Cast;
+use farmap::CastRemove;
+use farmap::CastType;
+use farmap::CollectionDiff;
+use farmap::CollisionPolicy;
+use farmap::Dated;
 use farmap::Fidded;
+use farmap::FollowCount;
+use farmap::HasTag;
+use farmap::Label;
+use farmap::Reaction;
 use farmap::SetWithSpamEntries;
+use farmap::Source;
+use farmap::Sourced;
 use farmap::SpamScore;
+use farmap::UnprocessedUserLine;
 use farmap::UserCollectionWithNativeUserValue as UserCollection;
 use futures::stream::{self, StreamExt};
 use futures::TryStreamExt;
 use itertools::iproduct;
 use itertools::Itertools;
 use log::trace;
-use log::{error, info};
+use log::{error, info, warn};
 use std::cell::Cell;
 use std::fs::File;
 use std::path::Path;
@@ -26,6 +41,7 @@ use std::{collections::HashSet, io::Write};
 pub async fn get_data() -> UserCollection {
     let local_data_dir = PathBuf::from("./data/auto-import/".to_string());
     let users_db_path = PathBuf::from("./data/auto-import/user-db.json".to_string());
+    let import_diff_path = PathBuf::from("./data/auto-import/import-diff.json".to_string());
     let names_data_path = PathBuf::from("./data/app_data/names".to_string());
     let names_data_dir = names_data_path.parent().unwrap();
     let readwrite_to_filesystem: Cell<bool> = Cell::new(true);
@@ -37,6 +53,9 @@ pub async fn get_data() -> UserCollection {
         UserCollection::default()
     };
     info!("finished...");
+    // The diff needs a copy of the collection from before the import, which doubles the memory
+    // that the import needs, so it is only made on request.
+    let local_users = import_diff_enabled().then(|| users.clone());
 
     if readwrite_to_filesystem.get()
         && !std::fs::exists(names_data_dir).unwrap_or_else(|_| {
@@ -74,16 +93,55 @@ pub async fn get_data() -> UserCollection {
         info!("There are no spam users in the dataset!")
     }
 
-    import_pinata_data(&mut users).await;
+    let fetch_list = pinata_fetch_list(&users);
+    import_pinata_data(&mut users, &fetch_list).await;
+    import_wield_data(&mut users, &fetch_list).await;
+
+    if let Some(days) = cast_retention_days() {
+        let cutoff = Local::now().date_naive() - Days::new(days);
+        let removed = users.retain_user_values(|cast: &Dated<CastType>| cast.date() >= cutoff)
+            + users.retain_user_values(|cast: &Dated<Cast>| cast.date() >= cutoff)
+            + users.retain_user_values(|remove: &Dated<CastRemove>| remove.date() >= cutoff);
+        info!("removed {removed} cast records older than {days} days");
+    }
+
+    let import_diff = local_users.map(|local_users| local_users.diff(&users));
+    if let Some(import_diff) = &import_diff {
+        info!(
+            "import added {} fids, removed {} fids and changed {} fids",
+            import_diff.added_fids().len(),
+            import_diff.removed_fids().len(),
+            import_diff.changed().len()
+        );
+    }
 
     if readwrite_to_filesystem.get() {
         save_to_db(&users, &users_db_path)
             .unwrap_or_else(|_| handle_rw_error(&readwrite_to_filesystem));
+        if let Some(import_diff) = &import_diff {
+            save_import_diff(import_diff, &import_diff_path)
+                .unwrap_or_else(|_| handle_rw_error(&readwrite_to_filesystem));
+        }
     };
 
     users
 }
 
+/// The number of days cast records are kept, set with the CAST_RETENTION_DAYS environment variable.
+/// All cast records are kept if the variable is not set.
+fn cast_retention_days() -> Option<u64> {
+    let days = std::env::var("CAST_RETENTION_DAYS").ok()?;
+    days.parse()
+        .inspect_err(|_| warn!("CAST_RETENTION_DAYS is not a number of days: {days}"))
+        .ok()
+}
+
+/// Whether the changes of the import are saved, set with the SAVE_IMPORT_DIFF environment variable.
+/// The changes are not saved if the variable is not set.
+fn import_diff_enabled() -> bool {
+    std::env::var("SAVE_IMPORT_DIFF").is_ok_and(|x| !x.is_empty() && x != "0" && x != "false")
+}
+
 pub fn create_from_db(db: &Path) -> Result<UserCollection, Box<dyn std::error::Error>> {
     let users = serde_json::from_str(&std::fs::read_to_string(db)?)?;
     Ok(users)
@@ -99,20 +157,29 @@ pub fn save_to_db(
     Ok(())
 }
 
-pub async fn import_pinata_data(users: &mut UserCollection) {
-    let fetch_list = pinata_fetch_list(&*users);
+/// Save the changes of the latest import, overwriting the changes of the previous import.
+pub fn save_import_diff(
+    diff: &CollectionDiff,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::create(path)?;
+    let json_text = serde_json::to_string(diff)?;
+    file.write_all(json_text.as_bytes())?;
+    Ok(())
+}
 
+pub async fn import_pinata_data(users: &mut UserCollection, fetch_list: &HashSet<u64>) {
     let pinata_fetcher = PinataFetcher::default();
     info!("fetching cast data for {} fids", fetch_list.len());
 
     let fres = fetch_list
         .iter()
-        .map(|x| async {
-            if let Ok(response) = pinata_fetcher.casts_by_fid(*x).await {
-                cast_meta_from_pinata_response(response).await.ok()
-            } else {
-                None
-            }
+        .map(|fid| async {
+            pinata_fetcher
+                .fetch_casts_for_fid(*fid)
+                .await
+                .ok()
+                .map(|parsed| (*fid, parsed))
         })
         .collect::<Vec<_>>();
 
@@ -122,28 +189,287 @@ pub async fn import_pinata_data(users: &mut UserCollection) {
         .flatten()
         .collect();
 
-    for fidded_cast_metas in results {
-        if fidded_cast_metas.is_empty() {
+    let source = users.register_source(Source::new("pinata/casts").with_fetcher("PinataFetcher"));
+    for (fid, parsed) in results {
+        if !parsed.unsupported.is_empty() {
+            info!(
+                "skipped {} unsupported cast messages for fid {fid}",
+                parsed.unsupported.len()
+            );
+        }
+
+        let Some(user) = users.user_mut(fid) else {
             continue;
         };
-
-        let fid = fidded_cast_metas
-            .first()
-            .expect("cannot be empty this point")
-            .fid();
-        let cast_metas = fidded_cast_metas
+        let casts: Vec<Dated<Cast>> = parsed.casts.into_iter().map(|x| x.unfid()).collect();
+        // The fetched casts replace the cast type records that older versions stored.
+        let migrated = user.remove_cast_types_on_dates(casts.iter().map(|x| x.date()));
+        if migrated > 0 {
+            trace!("replaced {migrated} cast type records of fid {fid}");
+        }
+        // Casts are compared by date and content, so records that were stored without a timestamp
+        // are not added again.
+        let stored_casts: HashSet<(NaiveDate, &Cast)> = user
+            .user_values_of_kind::<Dated<Cast>>()
+            .iter()
+            .map(|x| (x.date(), x.as_inner()))
+            .collect();
+        let new_casts: Vec<_> = casts
+            .into_iter()
+            .filter(|x| !stored_casts.contains(&(x.date(), x.as_inner())))
+            .collect();
+        let stored_removes: HashSet<(NaiveDate, &CastRemove)> = user
+            .user_values_of_kind::<Dated<CastRemove>>()
+            .iter()
+            .map(|x| (x.date(), x.as_inner()))
+            .collect();
+        let new_removes: Vec<_> = parsed
+            .removes
             .into_iter()
             .map(|x| x.unfid())
+            .filter(|x| !stored_removes.contains(&(x.date(), x.as_inner())))
+            .collect();
+        for cast in new_casts {
+            user.add_sourced_user_value(Sourced::from((cast, source)));
+        }
+        for remove in new_removes {
+            user.add_sourced_user_value(Sourced::from((remove, source)));
+        }
+        trace!("adding cast records to fid {fid}");
+    }
+
+    import_pinata_profiles(users, &pinata_fetcher, fetch_list).await;
+    import_pinata_reactions(users, &pinata_fetcher, fetch_list).await;
+    import_pinata_links(users, &pinata_fetcher, fetch_list).await;
+}
+
+/// Fetch the followers of the fids from wield. The fetch is skipped if the WIELD_API_KEY
+/// environment variable is not set.
+pub async fn import_wield_data(users: &mut UserCollection, fetch_list: &HashSet<u64>) {
+    let Ok(wield_fetcher) = WieldFetcher::default().set_api_key_from_env_var("WIELD_API_KEY")
+    else {
+        info!("WIELD_API_KEY is not set, skipping wield data");
+        return;
+    };
+    let Ok(wield_fetcher) = wield_fetcher
+        .build()
+        .inspect_err(|err| error!("could not build the wield fetcher: {err}"))
+    else {
+        return;
+    };
+
+    import_wield_followers(users, &wield_fetcher, fetch_list).await;
+}
+
+/// Fetch the followers of the fids along with the profile and the follow counts of every follower.
+/// Each fetch is stored in the follow graph of the fetched fid. For the followers that are in the
+/// collection, a profile is stored unless it is the same as their current profile, and the follow
+/// counts are stored unless the same counts are already stored for the same date.
+async fn import_wield_followers(
+    users: &mut UserCollection,
+    wield_fetcher: &WieldFetcher,
+    fetch_list: &HashSet<u64>,
+) {
+    info!(
+        "fetching followers with profiles and follow counts for {} fids",
+        fetch_list.len()
+    );
+
+    let fres = fetch_list
+        .iter()
+        .map(|fid| async move {
+            wield_fetcher
+                .fetch_followers_with_details(*fid)
+                .await
+                .ok()
+                .map(|followers| (*fid, followers))
+        })
+        .collect::<Vec<_>>();
+
+    let results: Vec<_> = futures::future::join_all(fres)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let today = Utc::now().date_naive();
+    let source = Source::new("wield/followers").with_fetcher("WieldFetcher");
+    let source_id = users.register_source(source.clone());
+    for (fid, followers) in results {
+        let follower_fids = followers
+            .profiles
+            .iter()
+            .map(|profile| profile.fid())
             .collect_vec();
+        let (_, added) =
+            users.add_follower_fetch_from_source(source.clone(), fid, follower_fids, today);
+        trace!("adding {added} follower edges to fid {fid}");
+
+        for profile in followers.profiles {
+            let (fid, profile) = profile.untag();
+            if let Some(user) = users.user_mut(fid) {
+                if user.profile().as_ref().map(Dated::as_inner) != Some(profile.as_inner()) {
+                    user.add_sourced_user_value(Sourced::from((profile, source_id)));
+                    trace!("adding profile to fid {fid}");
+                }
+            }
+        }
+
+        for follow_count in followers.follow_counts {
+            let (fid, follow_count) = follow_count.untag();
+            if let Some(user) = users.user_mut(fid) {
+                if !user
+                    .user_values_of_kind::<Dated<FollowCount>>()
+                    .contains(&follow_count)
+                {
+                    user.add_sourced_user_value(Sourced::from((follow_count, source_id)));
+                    trace!("adding follow count to fid {fid}");
+                }
+            }
+        }
+    }
+}
+
+/// Fetch the followers and the followed fids of the fids. Each follower fetch is stored in the
+/// follow graph of the fetched fid, which adds the fid to the collection if it is not in it yet.
+/// The follower and following counts are stored as well, unless the same count is already stored
+/// for the same date.
+async fn import_pinata_links(
+    users: &mut UserCollection,
+    pinata_fetcher: &PinataFetcher,
+    fetch_list: &HashSet<u64>,
+) {
+    info!("fetching links for {} fids", fetch_list.len());
+
+    let fres = fetch_list
+        .iter()
+        .map(|fid| async move {
+            let followers = pinata_fetcher.fetch_followers_for_fid(*fid).await.ok()?;
+            let following = pinata_fetcher.fetch_following_for_fid(*fid).await.ok()?;
+            Some((*fid, followers, following))
+        })
+        .collect::<Vec<_>>();
+
+    let results: Vec<_> = futures::future::join_all(fres)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
 
+    let today = Utc::now().date_naive();
+    let source = Source::new("pinata/links").with_fetcher("PinataFetcher");
+    let source_id = users.register_source(source.clone());
+    for (fid, followers, following) in results {
+        let follow_count = FollowCount::default()
+            .with_followers(followers.len() as u64)
+            .with_following(following.len() as u64);
+        let follow_count = Dated::from(today, follow_count);
+
+        let (_, added) =
+            users.add_follower_fetch_from_source(source.clone(), fid, followers, today);
+        trace!("adding {added} follower edges to fid {fid}");
+
+        let user = users
+            .user_mut(fid)
+            .expect("the follower fetch adds the fetched fid");
+        if !user
+            .user_values_of_kind::<Dated<FollowCount>>()
+            .contains(&follow_count)
+        {
+            user.add_sourced_user_value(Sourced::from((follow_count, source_id)));
+            trace!("adding follow count to fid {fid}");
+        }
+    }
+}
+
+/// Fetch the reactions of the fids. Reactions that are already stored are skipped, since every
+/// fetch returns the full reaction history.
+async fn import_pinata_reactions(
+    users: &mut UserCollection,
+    pinata_fetcher: &PinataFetcher,
+    fetch_list: &HashSet<u64>,
+) {
+    info!("fetching reactions for {} fids", fetch_list.len());
+
+    let fres = fetch_list
+        .iter()
+        .map(|fid| async move {
+            pinata_fetcher
+                .fetch_reactions_for_fid(*fid)
+                .await
+                .ok()
+                .map(|reactions| (*fid, reactions))
+        })
+        .collect::<Vec<_>>();
+
+    let results: Vec<_> = futures::future::join_all(fres)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let source =
+        users.register_source(Source::new("pinata/reactions").with_fetcher("PinataFetcher"));
+    for (fid, reactions) in results {
         if let Some(user) = users.user_mut(fid) {
-            for value in cast_metas {
-                user.add_user_value(value);
+            let stored: HashSet<Dated<Reaction>> = user
+                .user_values_of_kind::<Dated<Reaction>>()
+                .iter()
+                .cloned()
+                .collect();
+            for reaction in reactions.into_iter().filter(|x| !stored.contains(x)) {
+                user.add_sourced_user_value(Sourced::from((reaction, source)));
             }
-            trace!("adding cast records to fid {fid}");
-        } else {
-            continue;
-        };
+            trace!("adding reactions to fid {fid}");
+        }
+    }
+}
+
+/// Fetch the profiles of the fids that have no stored profile. Stored profiles are kept up to date
+/// by the wield follower fetches, so fids are not fetched again on every start.
+async fn import_pinata_profiles(
+    users: &mut UserCollection,
+    pinata_fetcher: &PinataFetcher,
+    fetch_list: &HashSet<u64>,
+) {
+    let fetch_list: Vec<u64> = fetch_list
+        .iter()
+        .copied()
+        .filter(|fid| {
+            users
+                .user(*fid)
+                .is_some_and(|user| user.profile().is_none())
+        })
+        .collect();
+    info!("fetching profiles for {} fids", fetch_list.len());
+
+    let fres = fetch_list
+        .iter()
+        .map(|fid| async move {
+            pinata_fetcher
+                .fetch_profile_for_fid(*fid)
+                .await
+                .ok()
+                .flatten()
+                .map(|profile| (*fid, profile))
+        })
+        .collect::<Vec<_>>();
+
+    let profiles: Vec<_> = futures::future::join_all(fres)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let source =
+        users.register_source(Source::new("pinata/user-data").with_fetcher("PinataFetcher"));
+    for (fid, profile) in profiles {
+        if let Some(user) = users.user_mut(fid) {
+            if user.profile().as_ref() != Some(&profile) {
+                user.add_sourced_user_value(Sourced::from((profile, source)));
+                trace!("adding profile to fid {fid}");
+            }
+        }
     }
 }
 
@@ -197,26 +523,61 @@ pub async fn import_github_data(
             error!("full error message : {err}");
         })?;
 
-    let api_names_set = HashSet::from_iter(api_names.iter().map(|x| x.to_string()));
-    let missing_names = api_names_set.difference(&local_names);
-    let missing_names_count = missing_names.clone().count();
+    // the api lists the newest commit first. Import the oldest commit first so that a compacted
+    // label change keeps the commit that introduced it.
+    let missing_names = api_names
+        .iter()
+        .rev()
+        .filter(|name| !local_names.contains(*name))
+        .collect_vec();
+    let missing_names_count = missing_names.len();
     trace!("There are {missing_names_count} missing names");
 
-    let new_bodies = stream::iter(missing_names)
+    let new_bodies = stream::iter(missing_names.iter())
         .then(|name| importer.fetch_commit_hash_body(name))
         .try_collect::<Vec<_>>()
         .await?;
 
-    for body in new_bodies {
-        let user_lines = parse_commit_hash_body(&body);
-        let dated_spam_updates = user_lines
+    for (name, body) in missing_names.into_iter().zip(new_bodies) {
+        let (user_lines, label_lines): (Vec<_>, Vec<_>) = parse_commit_hash_body(&body)
             .0
             .into_iter()
-            .flat_map(Fidded::<DatedSpamUpdate>::try_from)
+            .partition(UnprocessedUserLine::is_warpcast_spam);
+        let dated_spam_updates = match CommitHash::try_from(name.as_str()) {
+            Ok(commit) => into_fidded_user_value_iter_from_commit(user_lines, commit).collect_vec(),
+            Err(_) => {
+                warn!("{name} is not a commit hash, importing updates without a source commit");
+                user_lines
+                    .into_iter()
+                    .flat_map(Fidded::<DatedSpamUpdate>::try_from)
+                    .collect_vec()
+            }
+        };
+        let source = Source::new("warpcast/labels")
+            .with_commit(name.as_str())
+            .with_fetcher("GithubFetcher");
+        let labels = label_lines
+            .into_iter()
+            .flat_map(Fidded::<Dated<Label>>::try_from)
             .collect_vec();
-        users.add_user_value_iter(dated_spam_updates);
+        let (_, report) = users.try_add_user_value_iter_from_source(
+            source.clone(),
+            dated_spam_updates,
+            CollisionPolicy::KeepLast,
+        );
+        users.add_user_value_iter_from_source(source, labels);
+        if !report.is_empty() {
+            warn!(
+                "{} conflicting spam updates for {} fids, keeping the latest fetched update",
+                report.collisions().len(),
+                report.fids().len()
+            );
+        }
     }
 
+    let removed_updates = users.compact_spam_updates();
+    info!("removed {removed_updates} redundant spam updates");
+
     let updated_local_names = api_names;
 
     trace!("updated local names: {updated_local_names:?}");
@@ -265,21 +626,21 @@ fn pinata_fetch_list(users: &UserCollection) -> HashSet<u64> {
     let fill_rates = iproduct!(spam_scores, spam_scores)
         .map(|(from, to)| {
             let mut subset = SetWithSpamEntries::new(users).expect("no users with spam data");
-            subset.filter(|user| {
-                user.spam_score_at_date(previous_date)
-                    .map(|x| x == from)
-                    .unwrap_or(false)
-            });
-            subset.filter(|user| {
-                user.spam_score_at_date(previous_date)
-                    .map(|x| x == to)
-                    .unwrap_or(false)
-            });
-            (
-                subset.user_count() as f32 / users.user_count() as f32,
-                from,
-                to,
-            )
+            let user_count = subset
+                .filter(|user| {
+                    user.spam_score_at_date(previous_date)
+                        .map(|x| x == from)
+                        .unwrap_or(false)
+                })
+                .and_then(|_| {
+                    subset.filter(|user| {
+                        user.spam_score_at_date(previous_date)
+                            .map(|x| x == to)
+                            .unwrap_or(false)
+                    })
+                })
+                .map_or(0, |_| subset.user_count());
+            (user_count as f32 / users.user_count() as f32, from, to)
         })
         .collect::<Vec<_>>();
 
@@ -296,16 +657,22 @@ fn pinata_fetch_list(users: &UserCollection) -> HashSet<u64> {
     let add_fids_rate_for_from_two_pair =
         |result_fids: &mut HashSet<u64>, from: SpamScore, to: SpamScore| {
             if let Some(mut subset) = SetWithSpamEntries::new(users) {
-                subset.filter(|user| {
-                    user.spam_score_at_date(previous_date)
-                        .map(|x| x == from)
-                        .unwrap_or(false)
-                });
-                subset.filter(|user| {
-                    user.spam_score_at_date(previous_date)
-                        .map(|x| x == to)
-                        .unwrap_or(false)
-                });
+                let matched = subset
+                    .filter(|user| {
+                        user.spam_score_at_date(previous_date)
+                            .map(|x| x == from)
+                            .unwrap_or(false)
+                    })
+                    .and_then(|_| {
+                        subset.filter(|user| {
+                            user.spam_score_at_date(previous_date)
+                                .map(|x| x == to)
+                                .unwrap_or(false)
+                        })
+                    });
+                if matched.is_none() {
+                    return;
+                }
 
                 subset.into_iter().map(|x| x.fid()).for_each(|x| {
                     result_fids.insert(x.into());
@@ -80,6 +80,55 @@ async fn test_fid_endpoint() {
         .expect("Failed to send request");
 
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .get(format!("http://{addr}/100?profile=true"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(json["score"].as_u64(), Some(2));
+    assert_eq!(json["profile"]["username"].as_str(), Some("alice"));
+}
+
+#[tokio::test]
+async fn test_user_endpoint() {
+    let (addr, _handle) = spawn_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("http://{addr}/users/100"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(json["score"].as_u64(), Some(2));
+    assert_eq!(json["fid"].as_u64(), Some(100));
+    assert_eq!(json["profile"]["username"].as_str(), Some("alice"));
+    assert_eq!(json["profile"]["date"].as_str(), Some("2025-05-01"));
+
+    let response = client
+        .get(format!("http://{addr}/users/200"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(json["score"].as_u64(), Some(0));
+    assert!(json["profile"].is_null());
+
+    let response = client
+        .get(format!("http://{addr}/users/999999"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
@@ -345,3 +394,124 @@ async fn test_spam_score_distributions_for_cohort_invalid_date() {
 
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn test_cohort_table() {
+    let (addr, _handle) = spawn_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("http://{addr}/cohorts/month"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(json["period"], "month");
+    let rows = json["rows"].as_array().expect("rows should be an array");
+    assert!(!rows.is_empty());
+    for row in rows {
+        assert!(row["cohort"].is_string());
+        assert!(row["user_count"].as_u64().unwrap() > 0);
+        assert!(!row["cells"].as_array().unwrap().is_empty());
+    }
+
+    let response = client
+        .get(format!("http://{addr}/cohorts/day"))
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_survival_curves() {
+    let (addr, _handle) = spawn_test_server().await;
+    let client = reqwest::Client::new();
+
+    for spells in ["first", "all"] {
+        let response = client
+            .get(format!("http://{addr}/survival_curves"))
+            .query(&[("spells", spells)])
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let json: Value = response.json().await.expect("Failed to parse JSON");
+        assert_eq!(json["spells"], spells);
+        let curves = json["curves"]
+            .as_array()
+            .expect("curves should be an array");
+        assert!(!curves.is_empty());
+        for curve in curves {
+            let points = curve["points"].as_array().unwrap();
+            let survival: Vec<f64> = points
+                .iter()
+                .map(|point| point["survival"].as_f64().unwrap())
+                .collect();
+            assert!(survival.windows(2).all(|x| x[0] >= x[1]));
+        }
+    }
+
+    let response = client
+        .get(format!("http://{addr}/survival_curves"))
+        .query(&[("spells", "some")])
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_filter_expression() {
+    let (addr, _handle) = spawn_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("http://{addr}/spam_score_distribution"))
+        .query(&[("filter", "score = 2 and fid in 100..=300")])
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(json["nonspam"].as_f64(), Some(1.0));
+
+    let response = client
+        .get(format!("http://{addr}/weekly_spam_scores_counts"))
+        .query(&[("filter", "score(2025-01-01) = 7")])
+        .send()
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_filter_without_matches() {
+    let (addr, _handle) = spawn_test_server().await;
+    let client = reqwest::Client::new();
+
+    for endpoint in [
+        "spam_score_distribution",
+        "weekly_spam_scores",
+        "weekly_spam_scores_counts",
+        "latest_moves",
+        "cohorts/month",
+        "survival_curves",
+    ] {
+        let response = client
+            .get(format!("http://{addr}/{endpoint}"))
+            .query(&[("filter", "none")])
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT, "{endpoint}");
+    }
+}
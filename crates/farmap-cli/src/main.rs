@@ -1,17 +1,26 @@
 use chrono::Days;
 use chrono::NaiveDate;
+use clap::error::ErrorKind;
+use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
 use farmap::fetch::local_spam_label_importer;
 use farmap::fid_score_shift::ShiftSource;
 use farmap::fid_score_shift::ShiftTarget;
 use farmap::spam_score::DatedSpamUpdate;
+use farmap::CohortPeriod;
+use farmap::Cohorts;
+use farmap::CollisionPolicy;
+use farmap::CollisionReport;
+use farmap::Dated;
 use farmap::Fidded;
+use farmap::Label;
 use farmap::SetWithSpamEntries;
+use farmap::Source;
 use farmap::SpamScore;
+use farmap::UnprocessedUserLine;
 use farmap::UserCollectionWithNativeUserValue as UserCollection;
-use farmap::UserStoreWithNativeUserValue as User;
-use farmap::UserWithSpamData;
+use farmap::UserFilter;
 use farmap::UsersSubset;
 use itertools::Itertools;
 use simple_log::log::info;
@@ -30,11 +39,11 @@ struct Args {
 
     /// Only include users with earliest spam score at or after this date.
     #[arg(short, long, default_value = None)]
-    after_date: Option<String>,
+    after_date: Option<NaiveDate>,
 
     /// Only include users with earliest spam score at or before this date.
     #[arg(short,long, default_value = None)]
-    before_date: Option<String>,
+    before_date: Option<NaiveDate>,
 
     /// Only include users with a particular most recent spam score.
     #[arg(short,long, default_value = None)]
@@ -45,6 +54,12 @@ struct Args {
     #[arg(short,long,default_value = None , number_of_values=2, value_names = &["STRING", "NUMBER"])]
     spam_score_at_date: Option<Vec<String>>,
 
+    /// Only include users that match a filter expression, for example
+    /// "fid in 1..=1000 and not score(2025-01-01) = 0". The expression can also be json. It is
+    /// combined with the other filters.
+    #[arg(long, default_value = None)]
+    filter: Option<UserFilter>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -78,10 +93,27 @@ enum Commands {
 
     /// Print all fids that are not filtered out.
     AllFids,
+
+    /// Group the users by the period of their first spam label and print the spam score
+    /// distribution of each group at the end of every period since.
+    Cohorts {
+        /// The period to group and measure by: week, month or quarter.
+        #[arg(long, default_value = "month")]
+        period: CohortPeriod,
+
+        /// Print the cohort table as json.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() {
     let args = Args::parse();
+    let filter = user_filter(&args).unwrap_or_else(|err| {
+        Args::command()
+            .error(ErrorKind::ValueValidation, err)
+            .exit()
+    });
     let (dir_path, file_path) = if let Some(p) = args.path {
         let dir_path = if p.is_file() {
             p.parent().unwrap().to_str().unwrap().to_owned()
@@ -125,65 +157,8 @@ fn main() {
         import_data_from_dir(&dir_path)
     };
 
-    // If after_date is some, create a subset by that filter.
-    // If after_date is none, create a set with all users
-    let mut set = args.after_date.map_or_else(
-        || UsersSubset::from(&users),
-        |after_date| {
-            UsersSubset::from_filter(&users, |user: &User| {
-                UserWithSpamData::try_from(user)
-                    .map(|user| {
-                        user.earliest_spam_update().date()
-                            >= NaiveDate::parse_from_str(&after_date, "%Y-%m-%d").unwrap()
-                    })
-                    .unwrap_or(false)
-            })
-        },
-    );
-
-    // Filter on before_date if that input was provided.
-    if let Some(unparsed_before_date) = args.before_date {
-        if let Ok(parsed_before_date) = NaiveDate::parse_from_str(&unparsed_before_date, "%Y-%m-%d")
-        {
-            set.filter(|user| {
-                UserWithSpamData::try_from(user)
-                    .map(|user| user.earliest_spam_update().date() <= parsed_before_date)
-                    .unwrap_or(false)
-            })
-        }
-    }
-
-    if let Some(score) = args.current_spam_score {
-        let expected_spam_score = SpamScore::try_from(score).unwrap();
-        set.filter(|user: &User| {
-            UserWithSpamData::try_from(user)
-                .map(|user| user.latest_spam_update().score() == expected_spam_score)
-                .unwrap_or(false)
-        })
-    }
-
-    if let Some(spam_score_at_date) = args.spam_score_at_date {
-        let date = spam_score_at_date
-            .first()
-            .and_then(|date_str| NaiveDate::parse_from_str(date_str, "%Y-%m-%d").ok());
-        let score = spam_score_at_date
-            .get(1)
-            .and_then(|score| score.parse::<usize>().ok())
-            .and_then(|score| SpamScore::try_from(score).ok());
-        if let Some(date) = date {
-            if let Some(score) = score {
-                set.filter(|user: &User| {
-                    UserWithSpamData::try_from(user)
-                        .map(|user| {
-                            user.spam_score_at_date(date)
-                                .map(|sc| sc == score)
-                                .unwrap_or(false)
-                        })
-                        .unwrap_or(false)
-                })
-            }
-        }
-    }
+    let mut set = UsersSubset::from(&users);
+    set.filter_by(&filter);
 
     match args.command {
         Some(Commands::ChangeMatrix { from_date, to_date }) => {
@@ -204,10 +179,9 @@ fn main() {
             } else {
                 chrono::Local::now().naive_local().date()
             };
-            set.filter(|user: &User| {
-                UserWithSpamData::try_from(user)
-                    .map(|user| user.earliest_spam_update().date() <= analysis_date)
-                    .unwrap_or(false)
+            set.filter_by(&UserFilter::FirstLabelDate {
+                from: None,
+                to: Some(analysis_date),
             });
             print_spam_score_distribution(set.clone(), analysis_date);
         }
@@ -219,6 +193,7 @@ fn main() {
         }
 
         Some(Commands::Fid { fid }) => {
+            print_fid_profile(&set, fid);
             if let Ok(spam_set) = SetWithSpamEntries::try_from(&set) {
                 print_fid_history(&spam_set, &fid);
             } else {
@@ -228,7 +203,47 @@ fn main() {
         Some(Commands::AllFids) => {
             print_all(&set);
         }
+        Some(Commands::Cohorts { period, json }) => {
+            print_cohort_table(&set, period, json);
+        }
+    }
+}
+
+/// The filter of all the filter arguments combined. Returns an error message if a spam score or a
+/// date of a score filter does not parse.
+fn user_filter(args: &Args) -> Result<UserFilter, String> {
+    let parse_score = |score: &str| {
+        score
+            .parse::<usize>()
+            .ok()
+            .and_then(|score| SpamScore::try_from(score).ok())
+            .ok_or_else(|| format!("invalid spam score {score:?}, expected 0, 1 or 2"))
+    };
+    let mut filter = UserFilter::all();
+    if args.after_date.is_some() || args.before_date.is_some() {
+        filter = filter.and(UserFilter::FirstLabelDate {
+            from: args.after_date,
+            to: args.before_date,
+        });
+    }
+
+    if let Some(score) = args.current_spam_score {
+        let score = SpamScore::try_from(score)
+            .map_err(|_| format!("invalid spam score {score}, expected 0, 1 or 2"))?;
+        filter = filter.and(UserFilter::CurrentScore(score));
+    }
+
+    for date_and_score in args.spam_score_at_date.iter().flat_map(|x| x.chunks(2)) {
+        let date = NaiveDate::parse_from_str(&date_and_score[0], "%Y-%m-%d")
+            .map_err(|err| format!("invalid date {:?}: {err}", date_and_score[0]))?;
+        let score = parse_score(&date_and_score[1])?;
+        filter = filter.and(UserFilter::ScoreAtDate { date, score });
+    }
+
+    if let Some(expression) = &args.filter {
+        filter = filter.and(expression.clone());
     }
+    Ok(filter)
 }
 
 fn print_all(set: &UsersSubset) {
@@ -237,6 +252,35 @@ fn print_all(set: &UsersSubset) {
     }
 }
 
+fn print_fid_profile(set: &UsersSubset, fid: usize) {
+    let Some(profile) = set.user(fid).and_then(|user| user.profile()) else {
+        println!("no profile data for user in data set");
+        return;
+    };
+    println!("Profile for {fid} as of {}", profile.date());
+    println!("------");
+    let fields = [
+        ("username", profile.username().map(str::to_string)),
+        ("display name", profile.display_name().map(str::to_string)),
+        ("bio", profile.bio().map(str::to_string)),
+        ("pfp", profile.pfp_url().map(str::to_string)),
+        (
+            "custody address",
+            profile.custody_address().map(str::to_string),
+        ),
+        (
+            "registered at",
+            profile.registered_at().map(|x| x.to_string()),
+        ),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            println!("{name}: {value}");
+        }
+    }
+    println!();
+}
+
 fn print_fid_history(set: &SetWithSpamEntries, fid: &usize) {
     if let Some(user) = set.fid(*fid) {
         println!("Spam record history for {fid}");
@@ -290,6 +334,39 @@ fn print_spam_score_distribution(set: UsersSubset, date: NaiveDate) {
     }
 }
 
+fn print_cohort_table(set: &UsersSubset, period: CohortPeriod, json: bool) {
+    let Ok(spam_set) = SetWithSpamEntries::try_from(set) else {
+        println!("no spam data in set");
+        return;
+    };
+    let table = Cohorts::by_first_label(&spam_set, period).table(period);
+    if json {
+        println!("{}", serde_json::to_string(&table).unwrap());
+        return;
+    }
+
+    for row in table.rows() {
+        println!(
+            "Cohort of {period} {}, user count {}",
+            row.cohort(),
+            row.user_count()
+        );
+        for cell in row.cells() {
+            if let Some(distribution) = cell.distribution() {
+                println!(
+                    " {}: 0: {:.2}% 1: {:.2}% 2: {:.2}%",
+                    cell.date(),
+                    distribution.spam() * 100.0,
+                    distribution.maybe_spam() * 100.0,
+                    distribution.non_spam() * 100.0
+                );
+            } else {
+                println!(" {}: no spam scores", cell.date());
+            }
+        }
+    }
+}
+
 fn import_data_from_dir(data_dir: &str) -> UserCollection {
     let results =
         local_spam_label_importer::import_data_from_dir_with_collected_res(data_dir).unwrap();
@@ -298,12 +375,30 @@ fn import_data_from_dir(data_dir: &str) -> UserCollection {
         warn!("non-fatal error on import: {error:?}")
     }
 
-    let user_lines: Vec<Fidded<DatedSpamUpdate>> =
-        oks.into_iter().map(|x| x.try_into().unwrap()).collect_vec();
-    let mut collection = UserCollection::default();
-    collection.add_user_value_iter(user_lines);
+    collection_from_lines(oks, local_source(data_dir))
+}
 
-    collection
+fn local_source(path: &str) -> Source {
+    Source::new("local")
+        .with_file_path(path)
+        .with_fetcher("local_spam_label_importer")
+}
+
+fn warn_on_collisions(report: &CollisionReport<DatedSpamUpdate>) {
+    for collision in report.collisions() {
+        let existing = collision.existing().score();
+        let incoming = collision.incoming().score();
+        let outcome = match report.policy() {
+            CollisionPolicy::KeepFirst => format!("kept {existing:?}, dropped {incoming:?}"),
+            CollisionPolicy::KeepLast => format!("kept {incoming:?}, dropped {existing:?}"),
+            CollisionPolicy::Reject => format!("dropped both {existing:?} and {incoming:?}"),
+        };
+        warn!(
+            "conflicting spam scores for fid {} at {}: {outcome}",
+            collision.fid(),
+            collision.date(),
+        )
+    }
 }
 
 fn import_data_from_file(data_path: &str) -> UserCollection {
@@ -313,10 +408,30 @@ fn import_data_from_file(data_path: &str) -> UserCollection {
     for error in errors {
         warn!("non-fatal error on import: {error:?}")
     }
-    let user_lines: Vec<Fidded<DatedSpamUpdate>> =
-        oks.into_iter().map(|x| x.try_into().unwrap()).collect_vec();
+    collection_from_lines(oks, local_source(data_path))
+}
+
+/// Spam label lines are added as spam updates and the lines of other label streams as labels.
+fn collection_from_lines(lines: Vec<UnprocessedUserLine>, source: Source) -> UserCollection {
+    let (spam_lines, label_lines): (Vec<_>, Vec<_>) = lines
+        .into_iter()
+        .partition(UnprocessedUserLine::is_warpcast_spam);
+    let user_lines: Vec<Fidded<DatedSpamUpdate>> = spam_lines
+        .into_iter()
+        .map(|x| x.try_into().unwrap())
+        .collect_vec();
+    let labels: Vec<Fidded<Dated<Label>>> = label_lines
+        .into_iter()
+        .flat_map(Fidded::<Dated<Label>>::try_from)
+        .collect_vec();
     let mut collection = UserCollection::default();
-    collection.add_user_value_iter(user_lines);
+    let (_, report) = collection.try_add_user_value_iter_from_source(
+        source.clone(),
+        user_lines,
+        CollisionPolicy::KeepLast,
+    );
+    warn_on_collisions(&report);
+    collection.add_user_value_iter_from_source(source, labels);
 
     collection
 }
@@ -345,6 +460,29 @@ pub mod tests {
             .stdout("1\n");
     }
 
+    #[test]
+    fn test_quarterly_cohorts_on_dummy_data() {
+        let current_dir = env::current_dir().unwrap();
+        let path_arg = format!("-p{}{}", current_dir.to_str().unwrap(), "/data/dummy-data/");
+        Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg(path_arg)
+            .arg("cohorts")
+            .arg("--period=quarter")
+            .assert()
+            .stdout(concat!(
+                "Cohort of quarter 2024-01-01, user count 1\n",
+                " 2024-03-31: 0: 0.00% 1: 100.00% 2: 0.00%\n",
+                " 2024-06-30: 0: 0.00% 1: 100.00% 2: 0.00%\n",
+                " 2024-09-30: 0: 0.00% 1: 100.00% 2: 0.00%\n",
+                " 2024-12-31: 0: 0.00% 1: 100.00% 2: 0.00%\n",
+                " 2025-01-23: 0: 100.00% 1: 0.00% 2: 0.00%\n",
+                "Cohort of quarter 2025-01-01, user count 1\n",
+                " 2025-01-23: 0: 0.00% 1: 0.00% 2: 100.00%\n",
+            ));
+    }
+
     #[test]
     fn test_distribution_on_dummy_data() {
         let current_dir = env::current_dir().unwrap();
@@ -423,4 +561,29 @@ pub mod tests {
             .assert()
             .stdout("2\n");
     }
+
+    #[test]
+    fn test_filter_expression_on_dummy_data() {
+        let current_dir = env::current_dir().unwrap();
+        let path_arg = format!("-p{}{}", current_dir.to_str().unwrap(), "/data/dummy-data/");
+        Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg(path_arg.clone())
+            .arg("--filter")
+            .arg("score(2024-01-01) = 1 or fid >= 3")
+            .arg("all-fids")
+            .assert()
+            .stdout("1\n");
+
+        Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg(path_arg)
+            .arg("--filter")
+            .arg(r#"{"current_score":"Two"}"#)
+            .arg("all-fids")
+            .assert()
+            .stdout("2\n");
+    }
 }
@@ -1,28 +1,36 @@
 use chrono::NaiveDate;
 use criterion::{criterion_group, criterion_main, Criterion};
-use farmap::{UserCollection, UsersSubset};
+use farmap::fetch::local_spam_label_importer;
+use farmap::{SetWithSpamEntries, UserCollectionWithNativeUserValue, UserSet};
+
+fn create_collection(file_path: &str) -> UserCollectionWithNativeUserValue {
+    let updates = local_spam_label_importer::import_data_from_file(file_path).unwrap();
+    let mut collection = UserCollectionWithNativeUserValue::default();
+    collection.add_user_value_iter(updates);
+    collection
+}
 
 fn bench_spam_scores(c: &mut Criterion) {
     let home_dir = std::env::var("HOME").unwrap();
     let file_path = home_dir + "/.local/share/farmap/spam_2025-01-21.jsonl";
-    let collection = UserCollection::create_from_file_and_collect_non_fatal_errors(&file_path)
-        .unwrap()
-        .0;
+    let collection = create_collection(&file_path);
 
     c.bench_function("create collection", |b| {
-        b.iter(|| UserCollection::create_from_file_and_collect_non_fatal_errors(&file_path))
+        b.iter(|| create_collection(&file_path))
     });
 
-    c.bench_function("create set", |b| b.iter(|| UsersSubset::from(&collection)));
-    let set = UsersSubset::from(&collection);
+    c.bench_function("create set", |b| {
+        b.iter(|| SetWithSpamEntries::new(&collection))
+    });
+    let set = SetWithSpamEntries::new(&collection).unwrap();
     run_benchmarks_on_set(c, &set, "full set");
 
-    let filtered_set = set.filtered(|user| user.fid() < 10_000);
+    let filtered_set = set.filtered(|user| u64::from(user.fid()) < 10_000).unwrap();
 
     run_benchmarks_on_set(c, &filtered_set, "fid < 10_000");
 }
 
-fn run_benchmarks_on_set(c: &mut Criterion, set: &UsersSubset, name: &str) {
+fn run_benchmarks_on_set<'a>(c: &mut Criterion, set: &'a SetWithSpamEntries<'a>, name: &str) {
     let mut group = c.benchmark_group(name);
 
     group.bench_function("current_spam_score_count", |b| {
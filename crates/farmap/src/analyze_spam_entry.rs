@@ -1,4 +1,10 @@
 use crate::fid_score_shift::ShiftSource;
+#[cfg(feature = "rayon")]
+use crate::spam_score::par_spam_score_counts_at_dates;
+use crate::spam_score::spam_score_at_date;
+#[cfg(not(feature = "rayon"))]
+use crate::spam_score::spam_score_counts_at_dates;
+use crate::spam_score::CommitHash;
 use crate::spam_score::DatedSpamScoreDistribution;
 use crate::spam_score::DatedSpamUpdate;
 use crate::time_utils::TimeIterator;
@@ -16,9 +22,9 @@ use chrono::Days;
 use chrono::Duration;
 use chrono::NaiveDate;
 use itertools::Itertools;
-use std::collections::hash_set::IntoIter as HashSetIntoIter;
 use std::collections::HashMap;
-use std::collections::HashSet;
+use std::ops::RangeBounds;
+use std::vec::IntoIter as VecIntoIter;
 use thiserror::Error;
 
 /// A set of [UserWithSpamData]
@@ -34,6 +40,9 @@ impl<'a> SetWithSpamEntries<'a> {
     /// Returns None when a [UserCollection] doesn't have [User]s that contains at least one
     /// [SpamUpdate](crate::spam_score::DatedSpamUpdate).
     pub fn new(collection: &'a UserCollectionWithNativeUserValue) -> Option<Self> {
+        #[cfg(feature = "rayon")]
+        let set = UsersSubset::par_from_filter(collection, |user| user.has::<DatedSpamUpdate>());
+        #[cfg(not(feature = "rayon"))]
         let set = UsersSubset::from_filter(collection, |user| user.has::<DatedSpamUpdate>());
         if set.user_count() == 0 {
             None
@@ -53,48 +62,89 @@ impl<'a> SetWithSpamEntries<'a> {
     where
         F: Fn(&UserWithSpamData) -> bool,
     {
-        let new_map: HashMap<Fid, &UserStoreWithNativeUserValue> = self
-            .set
-            .clone()
-            .into_map()
-            .values()
-            .map(|user| UserWithSpamData::try_from(*user).expect("should not be able to fail"))
-            .filter(filter)
-            .map(|user| (user.fid(), user.user()))
-            .collect();
-        if new_map.is_empty() {
+        let set = self.set.filtered(|user| {
+            filter(&UserWithSpamData::try_from(user).expect("should not be able to fail"))
+        });
+        if set.user_count() == 0 {
             None
         } else {
-            let new_subset = UsersSubset::from(new_map);
-            SetWithSpamEntries::try_from(new_subset).ok()
+            Some(Self {
+                earliest_spam_score_date: earliest_spam_score_date(set.iter()),
+                latest_spam_score_date: latest_spam_score_date(set.iter()),
+                set,
+            })
         }
     }
 
     /// Since the set should contain users with spam entries, this method returns none (and does not
     /// filter) if the filter would result in an empty set.
+    #[must_use = "the set is left unfiltered if no user matches the filter"]
     pub fn filter<F>(&mut self, filter: F) -> Option<()>
     where
         F: Fn(&UserWithSpamData) -> bool,
     {
-        if self.filtered(&filter).is_none() {
-            None
-        } else {
-            let set = std::mem::take(&mut self.set);
-            let new_set: HashMap<Fid, &UserStoreWithNativeUserValue> = set
-                .into_map()
-                .values()
-                .map(|user| UserWithSpamData::try_from(*user).expect("should not be able to fail"))
-                .filter(filter)
-                .map(|user| (user.fid(), user.user()))
-                .collect();
+        *self = self.filtered(filter)?;
+        Some(())
+    }
+
+    /// Create a new set with the users with a fid in the range. Returns None if no user in the set
+    /// is in the range.
+    pub fn fid_range(&self, range: impl RangeBounds<Fid>) -> Option<Self> {
+        SetWithSpamEntries::try_from(self.set.fid_range(range)).ok()
+    }
+
+    /// Restrict the set to the users with a fid in the range. Like [`Self::filter`], this method
+    /// returns None (and does not filter) if no user in the set is in the range.
+    #[must_use = "the set is left unfiltered if no user is in the range"]
+    pub fn filter_fid_range(&mut self, range: impl RangeBounds<Fid>) -> Option<()> {
+        *self = self.fid_range(range)?;
+        Some(())
+    }
 
-            self.set = UsersSubset::from(new_set);
-            self.earliest_spam_score_date = earliest_spam_score_date(self.set.iter());
-            self.latest_spam_score_date = latest_spam_score_date(self.set.iter());
-            Some(())
+    /// The users that are in either set. Like the set operations of [`UsersSubset`], this assumes
+    /// that both sets are sets of the same collection.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            set: self.set.union(&other.set),
+            earliest_spam_score_date: self
+                .earliest_spam_score_date
+                .min(other.earliest_spam_score_date),
+            latest_spam_score_date: self
+                .latest_spam_score_date
+                .max(other.latest_spam_score_date),
         }
     }
 
+    /// The users that are in both sets. Returns None if no user is in both sets.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        SetWithSpamEntries::try_from(self.set.intersection(&other.set)).ok()
+    }
+
+    /// The users that are in this set but not in the other set. Returns None if every user is in
+    /// the other set.
+    pub fn difference(&self, other: &Self) -> Option<Self> {
+        SetWithSpamEntries::try_from(self.set.difference(&other.set)).ok()
+    }
+
+    /// The users that are in exactly one of the sets. Returns None if the sets have the same users.
+    pub fn symmetric_difference(&self, other: &Self) -> Option<Self> {
+        SetWithSpamEntries::try_from(self.set.symmetric_difference(&other.set)).ok()
+    }
+
+    /// The date of the earliest spam update of any user in the set.
+    pub fn earliest_spam_score_date(&self) -> NaiveDate {
+        self.earliest_spam_score_date
+    }
+
+    /// The date of the latest spam update of any user in the set.
+    pub fn latest_spam_score_date(&self) -> NaiveDate {
+        self.latest_spam_score_date
+    }
+
+    pub(crate) fn subset(&self) -> &UsersSubset<'a> {
+        &self.set
+    }
+
     /// Returns a [UserWithSpamData] if it is in the set. Otherwise returns None.
     pub fn fid(&'a self, fid: usize) -> Option<UserWithSpamData<'a>> {
         if let Some(user) = self.set.user(fid) {
@@ -104,6 +154,18 @@ impl<'a> SetWithSpamEntries<'a> {
         }
     }
 
+    /// The spam score at the date of the user with the fid, if the user is in the set.
+    pub(crate) fn spam_score_of(&self, fid: Fid, date: NaiveDate) -> Option<SpamScore> {
+        self.set
+            .user(fid)
+            .and_then(|user| spam_score_at_date(user.user_values_of_kind(), date))
+    }
+
+    /// The most recent spam score of the user with the fid, if the user is in the set.
+    pub(crate) fn current_spam_score_of(&self, fid: Fid) -> Option<SpamScore> {
+        self.spam_score_of(fid, self.latest_spam_score_date)
+    }
+
     /// Returns the current [SpamScoreDistribution]. The current spam score for each user is taken
     /// to be its most recent spam score.
     pub fn current_spam_score_distribution(&self) -> SpamScoreDistribution {
@@ -165,11 +227,9 @@ impl<'a> SetWithSpamEntries<'a> {
         };
 
         Some(
-            self.set
-                .iter()
-                .flat_map(|user| {
-                    spam_score_at_date(&user.user_values_of_kind::<DatedSpamUpdate>(), date)
-                })
+            self.map_users(|user| spam_score_at_date(user_spam_updates(user), date))
+                .into_iter()
+                .flatten()
                 .fold(
                     DatedSpamScoreCount::default_with_date(date),
                     |mut acc, user| {
@@ -180,6 +240,47 @@ impl<'a> SetWithSpamEntries<'a> {
         )
     }
 
+    /// The number of users that moved from each spam score to each spam score between every pair of
+    /// consecutive dates, indexed by window, then by the score at the start of the window and then
+    /// by the score at the end of the window. Users without a spam score at the start of a window
+    /// are left out of that window. The dates must be sorted in ascending order.
+    pub(crate) fn spam_score_transition_counts(&self, dates: &[NaiveDate]) -> Vec<[[u64; 3]; 3]> {
+        debug_assert!(dates.is_sorted());
+        let empty = || vec![[[0; 3]; 3]; dates.len().saturating_sub(1)];
+        let add_user = |mut counts: Vec<[[u64; 3]; 3]>, user: &UserStoreWithNativeUserValue| {
+            let updates = user_spam_updates(user);
+            let scores = dates.iter().map(|date| spam_score_at_date(updates, *date));
+            for (window, (from, to)) in scores.tuple_windows().enumerate() {
+                if let (Some(from), Some(to)) = (from, to) {
+                    counts[window][from as usize][to as usize] += 1;
+                }
+            }
+            counts
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::iter::ParallelIterator;
+            self.set
+                .par_iter()
+                .fold(empty, add_user)
+                .reduce(empty, |mut counts, other| {
+                    for (window, other) in counts.iter_mut().zip(other) {
+                        for (row, other) in window.iter_mut().zip(other) {
+                            for (count, other) in row.iter_mut().zip(other) {
+                                *count += other;
+                            }
+                        }
+                    }
+                    counts
+                })
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.set.iter().fold(empty(), add_user)
+        }
+    }
+
     /// The changes in spam scores that have happened from a date and a number of days from that
     /// date.
     pub fn spam_changes_with_fid_score_shift(
@@ -191,48 +292,63 @@ impl<'a> SetWithSpamEntries<'a> {
             .checked_add_days(days)
             .unwrap_or(NaiveDate::MAX);
 
-        let users_with_source = self
-            .set
-            .iter()
-            .filter(|x| end_date >= user_earliest_spam_score_date(x))
-            .map(|x| {
-                let user_spam_updates = user_spam_updates(x);
-                let user_source = spam_score_at_date(&user_spam_updates, initial_date)
-                    .map(|spam_update| spam_update.into())
-                    .unwrap_or(ShiftSource::New);
+        let users_with_source = self.map_users(|x| {
+            if end_date < user_earliest_spam_score_date(x) {
+                return None;
+            }
+            let user_spam_updates = user_spam_updates(x);
+            let user_source = spam_score_at_date(user_spam_updates, initial_date)
+                .map(|spam_update| spam_update.into())
+                .unwrap_or(ShiftSource::New);
 
-                let user_target = spam_score_at_date(&user_spam_updates, end_date)
-                    .map(|spam_update| spam_update.into())
-                    .expect("should always have spam_score_at_end");
+            let user_target = spam_score_at_date(user_spam_updates, end_date)
+                .map(|spam_update| spam_update.into())
+                .expect("should always have spam_score_at_end");
 
-                FidScoreShift::new(user_source, user_target, 1)
-            })
-            .collect_vec();
+            Some(FidScoreShift::new(user_source, user_target, 1))
+        });
 
-        let shift: Vec<FidScoreShift> = users_with_source.iter().fold(
-            vec![
-                TryInto::<FidScoreShift>::try_into(0).unwrap(),
-                TryInto::<FidScoreShift>::try_into(1).unwrap(),
-                TryInto::<FidScoreShift>::try_into(2).unwrap(),
-                TryInto::<FidScoreShift>::try_into(3).unwrap(),
-                TryInto::<FidScoreShift>::try_into(4).unwrap(),
-                TryInto::<FidScoreShift>::try_into(5).unwrap(),
-                TryInto::<FidScoreShift>::try_into(6).unwrap(),
-                TryInto::<FidScoreShift>::try_into(7).unwrap(),
-                TryInto::<FidScoreShift>::try_into(8).unwrap(),
-                TryInto::<FidScoreShift>::try_into(9).unwrap(),
-                TryInto::<FidScoreShift>::try_into(10).unwrap(),
-                TryInto::<FidScoreShift>::try_into(11).unwrap(),
-            ],
-            |mut acc, shift| {
-                let shift = *shift;
-                let index: usize = shift.try_into().unwrap();
-                acc[index].increment();
-                acc
-            },
-        );
+        fid_score_shift_counts(users_with_source.into_iter().flatten())
+    }
 
-        shift.into_iter().filter(|x| x.count() != 0).collect_vec()
+    /// The label changes of the users in the set grouped by the commit that introduced them. A
+    /// label change is the first spam update of a user or an update with a different score than the
+    /// previous update of the user. Changes from updates without a source commit are left out.
+    pub fn label_changes_by_commit(&self) -> HashMap<CommitHash, Vec<FidScoreShift>> {
+        let mut shifts: HashMap<CommitHash, Vec<FidScoreShift>> = HashMap::new();
+        for (_, commit, shift) in self.label_changes() {
+            shifts.entry(commit).or_default().push(shift);
+        }
+        shifts
+            .into_iter()
+            .map(|(commit, shifts)| (commit, fid_score_shift_counts(shifts)))
+            .collect()
+    }
+
+    /// The fids of the users in the set whose label was changed by the commit.
+    pub fn fids_changed_by_commit(&self, commit: CommitHash) -> Vec<Fid> {
+        self.label_changes()
+            .filter(|(_, change_commit, _)| *change_commit == commit)
+            .map(|(fid, _, _)| fid)
+            .dedup()
+            .collect()
+    }
+
+    fn label_changes(&self) -> impl Iterator<Item = (Fid, CommitHash, FidScoreShift)> + '_ {
+        self.set.iter().flat_map(|user| {
+            let mut previous: Option<SpamScore> = None;
+            user_spam_updates(user).iter().filter_map(move |update| {
+                let source = previous.map(ShiftSource::from).unwrap_or(ShiftSource::New);
+                let changed = previous != Some(update.score());
+                previous = Some(update.score());
+                let commit = update.source_commit().filter(|_| changed)?;
+                Some((
+                    user.fid(),
+                    commit,
+                    FidScoreShift::new(source, update.score().into(), 1),
+                ))
+            })
+        })
     }
 
     /// Returns the spam score count for a set at a weekly cadence. The first value is at the
@@ -241,47 +357,87 @@ impl<'a> SetWithSpamEntries<'a> {
     pub fn weekly_spam_score_counts(&self) -> Vec<DatedSpamScoreCount> {
         let mut date = self.earliest_spam_score_date;
         let end_date = self.latest_spam_score_date;
-        let mut result: Vec<DatedSpamScoreCount> = Vec::new();
+        let mut dates: Vec<NaiveDate> = Vec::new();
         while date <= end_date {
-            result.push(self.spam_score_count_at_date(date).unwrap());
+            dates.push(date);
             date += Duration::days(7);
         }
 
         // always include the last date.
         if date < end_date {
-            // since end date is a valid date the unwrap should never trigger.
-            result.push(self.spam_score_count_at_date(end_date).unwrap());
+            dates.push(end_date);
         };
 
-        result
+        self.spam_score_counts_at_dates(&dates)
     }
 
     pub fn weekly_spam_score_distributions(&self) -> Vec<DatedSpamScoreDistribution> {
-        TimeIterator::new()
+        let dates = TimeIterator::new()
             .with_weekly_cadence()
             .with_start_date(self.earliest_spam_score_date)
             .with_end_date(self.latest_spam_score_date)
             .build()
-            .map(|date| {
-                let distribution = (*self.spam_score_count_at_date(date).unwrap().as_inner())
-                    .try_into()
-                    .unwrap();
-                let dated_distribution: DatedSpamScoreDistribution = (distribution, date).into();
-                dated_distribution
-            })
-            .collect()
+            .collect_vec();
+        self.spam_score_distributions_at_dates(&dates)
     }
 
     pub fn monthly_spam_score_distributions(&self) -> Vec<DatedSpamScoreDistribution> {
-        TimeIterator::new()
+        let dates = TimeIterator::new()
             .with_monthly_cadence()
             .with_start_date(self.earliest_spam_score_date)
             .with_end_date(self.latest_spam_score_date)
             .build()
-            .map(|date| {
-                let distribution = (*self.spam_score_count_at_date(date).unwrap().as_inner())
-                    .try_into()
-                    .unwrap();
+            .collect_vec();
+        self.spam_score_distributions_at_dates(&dates)
+    }
+
+    /// The spam score count at each date, computed in a single sweep over the spam updates in the
+    /// set. The dates must be sorted in ascending order and not be prior to the earliest spam score
+    /// date in the set.
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) fn spam_score_counts_at_dates(
+        &self,
+        dates: &[NaiveDate],
+    ) -> Vec<DatedSpamScoreCount> {
+        spam_score_counts_at_dates(self.set.iter().map(user_spam_updates), dates)
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn spam_score_counts_at_dates(
+        &self,
+        dates: &[NaiveDate],
+    ) -> Vec<DatedSpamScoreCount> {
+        use rayon::iter::ParallelIterator;
+        par_spam_score_counts_at_dates(self.set.par_iter().map(user_spam_updates), dates)
+    }
+
+    /// Map every user in the set, in fid order. With the `rayon` feature the users are mapped in
+    /// parallel.
+    fn map_users<R, F>(&self, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(&UserStoreWithNativeUserValue) -> R + Sync + Send,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::iter::ParallelIterator;
+            self.set.par_iter().map(f).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.set.iter().map(f).collect()
+        }
+    }
+
+    fn spam_score_distributions_at_dates(
+        &self,
+        dates: &[NaiveDate],
+    ) -> Vec<DatedSpamScoreDistribution> {
+        self.spam_score_counts_at_dates(dates)
+            .into_iter()
+            .map(|count| {
+                let date = count.date();
+                let distribution = count.into_inner().try_into().unwrap();
                 let dated_distribution: DatedSpamScoreDistribution = (distribution, date).into();
                 dated_distribution
             })
@@ -299,12 +455,7 @@ impl<'a> TryFrom<UsersSubset<'a>> for SetWithSpamEntries<'a> {
 impl<'a> TryFrom<&UsersSubset<'a>> for SetWithSpamEntries<'a> {
     type Error = EmptySetError;
     fn try_from(value: &UsersSubset<'a>) -> Result<Self, Self::Error> {
-        let new_subset = value.filtered(|user| {
-            user.all_user_values()
-                .flat_map(|user_value| user_value.specify_ref::<DatedSpamUpdate>())
-                .count()
-                > 0
-        });
+        let new_subset = value.filtered(|user| user.has::<DatedSpamUpdate>());
 
         if new_subset.user_count() == 0 {
             return Err(EmptySetError);
@@ -325,8 +476,9 @@ impl<'a> TryFrom<&UsersSubset<'a>> for SetWithSpamEntries<'a> {
     }
 }
 
+/// Iterates over the users of a [`SetWithSpamEntries`] in ascending fid order.
 pub struct SetWithSpamEntriesIter<'a> {
-    iter: HashSetIntoIter<UserWithSpamData<'a>>,
+    iter: VecIntoIter<UserWithSpamData<'a>>,
 }
 
 impl<'a> Iterator for SetWithSpamEntriesIter<'a> {
@@ -341,7 +493,7 @@ impl<'a> IntoIterator for SetWithSpamEntries<'a> {
     type Item = UserWithSpamData<'a>;
     type IntoIter = SetWithSpamEntriesIter<'a>;
     fn into_iter(self) -> Self::IntoIter {
-        let iter: HashSet<_> = self
+        let iter: Vec<_> = self
             .set
             .into_iter()
             .map(|x| {
@@ -374,8 +526,7 @@ where
     I: Iterator<Item = &'a UserStoreWithNativeUserValue>,
 {
     iterator
-        .flat_map(|user| user.all_user_values()) // flatten to remove users with no user_values
-        .flat_map(|x| x.specify_ref::<DatedSpamUpdate>())
+        .flat_map(|user| user_spam_updates(user).first())
         .map(|x| x.date())
         .min()
         .expect("internal error - SetWithSpamEntry should always have earliest spam score date")
@@ -386,17 +537,29 @@ where
     I: Iterator<Item = &'a UserStoreWithNativeUserValue>,
 {
     iterator
-        .flat_map(|user| user.all_user_values()) // flatten to remove users with no user_values
-        .flat_map(|x| x.specify_ref::<DatedSpamUpdate>())
+        .flat_map(|user| user_spam_updates(user).last())
         .map(|x| x.date())
         .max()
         .expect("internal error - SetWithSpamEntry should always have earliest spam score date")
 }
 
-fn user_spam_updates(user: &UserStoreWithNativeUserValue) -> Vec<&DatedSpamUpdate> {
-    user.all_user_values()
-        .flat_map(|x| x.specify_ref::<DatedSpamUpdate>())
-        .collect()
+/// The count of each kind of shift, leaving out kinds with a zero count. Each shift adds its count
+/// to its kind.
+fn fid_score_shift_counts(shifts: impl IntoIterator<Item = FidScoreShift>) -> Vec<FidScoreShift> {
+    let mut counts = (0..12)
+        .map(|index| FidScoreShift::try_from(index).unwrap())
+        .collect_vec();
+    for shift in shifts {
+        let index: usize = shift.try_into().unwrap();
+        for _ in 0..shift.count() {
+            counts[index].increment();
+        }
+    }
+    counts.into_iter().filter(|x| x.count() != 0).collect_vec()
+}
+
+fn user_spam_updates(user: &UserStoreWithNativeUserValue) -> &[DatedSpamUpdate] {
+    user.user_values_of_kind::<DatedSpamUpdate>()
 }
 
 fn user_earliest_spam_score_date(user: &UserStoreWithNativeUserValue) -> NaiveDate {
@@ -410,14 +573,6 @@ fn user_latest_spam_score_date(user: &UserStoreWithNativeUserValue) -> NaiveDate
     latest_spam_score_date(user_iter)
 }
 
-fn spam_score_at_date(updates: &Vec<&DatedSpamUpdate>, date: NaiveDate) -> Option<SpamScore> {
-    updates
-        .iter()
-        .filter(|x| x.date() <= date)
-        .max_by_key(|x| x.date())
-        .map(|update| update.score())
-}
-
 #[cfg(test)]
 mod tests {
 
@@ -434,7 +589,9 @@ mod tests {
 
     use super::*;
 
-    fn create_set(collection: &UserCollectionWithNativeUserValue) -> Option<SetWithSpamEntries> {
+    fn create_set(
+        collection: &UserCollectionWithNativeUserValue,
+    ) -> Option<SetWithSpamEntries<'_>> {
         SetWithSpamEntries::new(collection)
     }
 
@@ -621,6 +778,133 @@ mod tests {
         }
     }
 
+    mod set_operations {
+        use super::*;
+        use crate::time_utils::date;
+        use crate::Fidded;
+        use crate::UserFilter;
+
+        fn collection() -> UserCollectionWithNativeUserValue {
+            let mut collection = UserCollectionWithNativeUserValue::default();
+            collection.add_user_value_iter(
+                [
+                    (1_u64, "2025-01-01", SpamScore::Zero),
+                    (1, "2025-01-10", SpamScore::Two),
+                    (2, "2025-01-01", SpamScore::Zero),
+                    (3, "2025-01-01", SpamScore::Two),
+                    (3, "2025-01-10", SpamScore::Zero),
+                ]
+                .map(|(fid, date_str, score)| {
+                    Fidded::from((DatedSpamUpdate::from(date(date_str), score), Fid::from(fid)))
+                }),
+            );
+            collection
+        }
+
+        fn fids(set: &SetWithSpamEntries) -> Vec<u64> {
+            set.clone()
+                .into_iter()
+                .map(|user| user.fid().into())
+                .collect()
+        }
+
+        #[test]
+        fn test_spam_on_one_date_but_not_another() {
+            let collection = collection();
+            let set = SetWithSpamEntries::new(&collection).unwrap();
+            let spam_on = |date_str: &str| {
+                set.filtered_by(
+                    &format!("score({date_str}) = 0")
+                        .parse::<UserFilter>()
+                        .unwrap(),
+                )
+                .unwrap()
+            };
+            let spam_on_a = spam_on("2025-01-02");
+            let spam_on_b = spam_on("2025-01-11");
+
+            assert_eq!(fids(&spam_on_a.difference(&spam_on_b).unwrap()), [1]);
+            assert_eq!(fids(&spam_on_a.intersection(&spam_on_b).unwrap()), [2]);
+            assert_eq!(
+                fids(&spam_on_a.symmetric_difference(&spam_on_b).unwrap()),
+                [1, 3]
+            );
+            assert!(spam_on_a.difference(&spam_on_a).is_none());
+            assert_eq!(spam_on_a.union(&spam_on_b), set);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    mod parallel {
+        use super::*;
+        use crate::spam_score::spam_score_counts_at_dates;
+        use crate::time_utils::date;
+        use crate::Fidded;
+
+        fn collection() -> UserCollectionWithNativeUserValue {
+            let start = date("2024-01-01");
+            let mut collection = UserCollectionWithNativeUserValue::default();
+            collection.add_user_value_iter((0..1000_u64).flat_map(|fid| {
+                let first = start + Days::new(fid * 7 % 90);
+                let second = start + Days::new(100 + fid * 13 % 200);
+                [
+                    (first, SpamScore::try_from(fid as usize % 3).unwrap()),
+                    (second, SpamScore::try_from(fid as usize / 3 % 3).unwrap()),
+                ]
+                .map(|(date, score)| {
+                    Fidded::from((DatedSpamUpdate::from(date, score), Fid::from(fid)))
+                })
+            }));
+            collection
+        }
+
+        #[test]
+        fn test_counts_match_the_sequential_sweep() {
+            let collection = collection();
+            let set = SetWithSpamEntries::new(&collection).unwrap();
+            let dates = TimeIterator::new()
+                .with_weekly_cadence()
+                .with_start_date(set.earliest_spam_score_date)
+                .with_end_date(set.latest_spam_score_date)
+                .build()
+                .collect_vec();
+            let expected =
+                spam_score_counts_at_dates(collection.iter().map(user_spam_updates), &dates);
+            assert_eq!(set.spam_score_counts_at_dates(&dates), expected);
+
+            for count in expected {
+                assert_eq!(set.spam_score_count_at_date(count.date()), Some(count));
+            }
+        }
+
+        #[test]
+        fn test_par_from_filter_matches_from_filter() {
+            let collection = collection();
+            let filter = |user: &UserStoreWithNativeUserValue| u64::from(user.fid()) % 3 == 0;
+            assert_eq!(
+                UsersSubset::par_from_filter(&collection, filter),
+                UsersSubset::from_filter(&collection, filter)
+            );
+        }
+
+        #[test]
+        fn test_transition_counts_match_the_sequential_count() {
+            let collection = collection();
+            let set = SetWithSpamEntries::new(&collection).unwrap();
+            let dates = [date("2024-01-01"), date("2024-03-01"), date("2024-09-01")];
+            let mut expected = vec![[[0; 3]; 3]; 2];
+            for user in collection.iter() {
+                let scores = dates.map(|date| spam_score_at_date(user_spam_updates(user), date));
+                for window in 0..2 {
+                    if let (Some(from), Some(to)) = (scores[window], scores[window + 1]) {
+                        expected[window][from as usize][to as usize] += 1;
+                    }
+                }
+            }
+            assert_eq!(set.spam_score_transition_counts(&dates), expected);
+        }
+    }
+
     mod filtered {
         use super::*;
         enum FilterValidity {
@@ -666,6 +950,28 @@ mod tests {
         }
     }
 
+    mod fid_range {
+        use super::*;
+
+        #[test]
+        fn test_fid_range() {
+            let collection = basic_m_user_test_collection_with_n_spam_updates(5, 2);
+            let mut set = create_set(&collection).unwrap();
+            assert_eq!(
+                set.fid_range(Fid::from(1_u64)..=Fid::from(2_u64))
+                    .unwrap()
+                    .user_count(),
+                2
+            );
+            assert!(set.fid_range(Fid::from(10_u64)..).is_none());
+            assert!(set.filter_fid_range(Fid::from(10_u64)..).is_none());
+            assert_eq!(set.user_count(), 5);
+            assert!(set.filter_fid_range(Fid::from(3_u64)..).is_some());
+            let fids: Vec<u64> = set.into_iter().map(|user| user.fid().into()).collect();
+            assert_eq!(fids, [3, 4]);
+        }
+    }
+
     mod current_spam_counts {
         use super::*;
 
@@ -767,6 +1073,42 @@ mod tests {
         }
     }
 
+    mod series {
+        use super::*;
+        use crate::user_collection::tests::dummy_data;
+
+        #[track_caller]
+        fn check_series_matches_pointwise_counts(set: &SetWithSpamEntries) {
+            let weekly = set.weekly_spam_score_counts();
+            assert!(!weekly.is_empty());
+            for count in weekly {
+                assert_eq!(Some(count), set.spam_score_count_at_date(count.date()));
+            }
+
+            for distribution in set.monthly_spam_score_distributions() {
+                let expected: SpamScoreDistribution = set
+                    .spam_score_count_at_date(distribution.date())
+                    .unwrap()
+                    .into_inner()
+                    .try_into()
+                    .unwrap();
+                assert_eq!(*distribution.as_inner(), expected);
+            }
+        }
+
+        #[test]
+        fn test_series_on_dummy_data() {
+            let users = dummy_data();
+            check_series_matches_pointwise_counts(&create_set(&users).unwrap());
+        }
+
+        #[test]
+        fn test_series_on_users_with_many_updates() {
+            let collection = basic_m_user_test_collection_with_n_spam_updates(40, 60);
+            check_series_matches_pointwise_counts(&create_set(&collection).unwrap());
+        }
+    }
+
     mod earliest_date {
         use super::filter::*;
         use super::*;
@@ -911,14 +1253,14 @@ mod tests {
             .try_add_user_value(DatedSpamUpdate::from(earlier_date, SpamScore::Zero))
             .is_ok());
 
-        let spam_updates = user.user_values_of_kind::<DatedSpamUpdate>();
+        let spam_updates = user_spam_updates(&user);
 
         let earliest_spam_score_date = user_earliest_spam_score_date(&user);
         let latest_spam_score_date = user_latest_spam_score_date(&user);
 
         let earliest_spam_score =
-            spam_score_at_date(&spam_updates, earliest_spam_score_date).unwrap();
-        let latest_spam_score = spam_score_at_date(&spam_updates, latest_spam_score_date).unwrap();
+            spam_score_at_date(spam_updates, earliest_spam_score_date).unwrap();
+        let latest_spam_score = spam_score_at_date(spam_updates, latest_spam_score_date).unwrap();
 
         assert_eq!(
             (earliest_spam_score, earliest_spam_score_date),
@@ -930,4 +1272,62 @@ mod tests {
             (SpamScore::One, date)
         );
     }
+
+    mod label_changes_by_commit {
+        use super::*;
+        use crate::fid_score_shift::ShiftTarget;
+        use crate::spam_score::SpamUpdate;
+        use crate::time_utils::date;
+        use crate::Fidded;
+
+        fn commit(prefix: &str) -> CommitHash {
+            CommitHash::try_from(prefix.repeat(20)).unwrap()
+        }
+
+        fn update(
+            fid: u64,
+            score: SpamScore,
+            date_str: &str,
+            prefix: &str,
+        ) -> Fidded<DatedSpamUpdate> {
+            let update = SpamUpdate::from(score).with_source_commit(commit(prefix));
+            Fidded::from((
+                DatedSpamUpdate::from(date(date_str), update),
+                Fid::from(fid),
+            ))
+        }
+
+        #[test]
+        fn test_label_changes_are_grouped_by_introducing_commit() {
+            let mut collection = UserCollectionWithNativeUserValue::default();
+            collection.add_user_value_iter([
+                update(1, SpamScore::One, "2025-01-01", "aa"),
+                update(1, SpamScore::One, "2025-01-05", "bb"),
+                update(1, SpamScore::Zero, "2025-01-09", "cc"),
+                update(2, SpamScore::Two, "2025-01-05", "bb"),
+            ]);
+            collection.add_user_value_iter([Fidded::from((
+                DatedSpamUpdate::from(date("2025-01-10"), SpamScore::One),
+                Fid::from(2_u64),
+            ))]);
+            let set = create_set(&collection).unwrap();
+            let changes = set.label_changes_by_commit();
+
+            assert_eq!(changes.len(), 3);
+            assert_eq!(
+                changes[&commit("aa")],
+                [FidScoreShift::new(ShiftSource::New, ShiftTarget::One, 1)]
+            );
+            assert_eq!(
+                changes[&commit("cc")],
+                [FidScoreShift::new(ShiftSource::One, ShiftTarget::Zero, 1)]
+            );
+            assert_eq!(
+                changes[&commit("bb")],
+                [FidScoreShift::new(ShiftSource::New, ShiftTarget::Two, 1)]
+            );
+            assert_eq!(set.fids_changed_by_commit(commit("bb")), [Fid::from(2_u64)]);
+            assert_eq!(set.fids_changed_by_commit(commit("cc")), [Fid::from(1_u64)]);
+        }
+    }
 }
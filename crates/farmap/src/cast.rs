@@ -0,0 +1,319 @@
+use crate::cast_type::CastType;
+use crate::core::UserValueContainer;
+use crate::dated::Dated;
+use crate::native_user_value::AnyNativeUserValue;
+use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
+use crate::Fid;
+use crate::NativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// The id of a cast, which is the fid of the author and the hash of the cast.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct CastId {
+    fid: Fid,
+    hash: String,
+}
+
+impl CastId {
+    pub fn new(fid: impl Into<Fid>, hash: impl Into<String>) -> Self {
+        Self {
+            fid: fid.into(),
+            hash: hash.into(),
+        }
+    }
+
+    pub fn fid(&self) -> Fid {
+        self.fid
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+/// What a cast replies to. A reply to a cast has a cast id as parent while a cast in a channel has
+/// the url of the channel as parent.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CastParent {
+    Cast(CastId),
+    Url(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Embed {
+    Url(String),
+    Cast(CastId),
+}
+
+/// A cast added by the user that stores the value.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Cast {
+    cast_type: CastType,
+    hash: Option<String>,
+    parent: Option<CastParent>,
+    mentions: Vec<Fid>,
+    embeds: Vec<Embed>,
+}
+
+impl Cast {
+    pub fn new(cast_type: CastType) -> Self {
+        Self {
+            cast_type,
+            hash: None,
+            parent: None,
+            mentions: Vec::new(),
+            embeds: Vec::new(),
+        }
+    }
+
+    pub fn with_hash(mut self, hash: impl Into<String>) -> Self {
+        self.hash = Some(hash.into());
+        self
+    }
+
+    pub fn with_parent(mut self, parent: CastParent) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn with_mentions(mut self, mentions: impl IntoIterator<Item = impl Into<Fid>>) -> Self {
+        self.mentions = mentions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_embeds(mut self, embeds: impl IntoIterator<Item = Embed>) -> Self {
+        self.embeds = embeds.into_iter().collect();
+        self
+    }
+
+    pub fn cast_type(&self) -> CastType {
+        self.cast_type
+    }
+
+    pub fn hash(&self) -> Option<&str> {
+        self.hash.as_deref()
+    }
+
+    pub fn parent(&self) -> Option<&CastParent> {
+        self.parent.as_ref()
+    }
+
+    /// The cast that this cast replies to, if it is a reply.
+    pub fn parent_cast(&self) -> Option<&CastId> {
+        match &self.parent {
+            Some(CastParent::Cast(id)) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The parent url of the cast, which is the channel url for casts in a channel.
+    pub fn parent_url(&self) -> Option<&str> {
+        match &self.parent {
+            Some(CastParent::Url(url)) => Some(url),
+            _ => None,
+        }
+    }
+
+    pub fn is_reply(&self) -> bool {
+        self.parent_cast().is_some()
+    }
+
+    pub fn mentions(&self) -> &[Fid] {
+        &self.mentions
+    }
+
+    pub fn embeds(&self) -> &[Embed] {
+        &self.embeds
+    }
+}
+
+impl From<CastType> for Cast {
+    fn from(value: CastType) -> Self {
+        Self::new(value)
+    }
+}
+
+/// The removal of an earlier cast by the user that stores the value.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct CastRemove {
+    target_hash: String,
+}
+
+impl CastRemove {
+    pub fn new(target_hash: impl Into<String>) -> Self {
+        Self {
+            target_hash: target_hash.into(),
+        }
+    }
+
+    /// The hash of the removed cast.
+    pub fn target_hash(&self) -> &str {
+        &self.target_hash
+    }
+}
+
+impl NativeUserValueSeal for Dated<Cast> {}
+
+impl NativeUserValue for Dated<Cast> {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedCast(self.clone())
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedCast(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedCast(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedCast(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_casts
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_casts
+    }
+}
+
+impl NativeUserValueSeal for Dated<CastRemove> {}
+
+impl NativeUserValue for Dated<CastRemove> {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedCastRemove(self.clone())
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedCastRemove(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedCastRemove(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedCastRemove(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_cast_removes
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_cast_removes
+    }
+}
+
+impl UserStoreWithNativeUserValue {
+    /// Returns true if the user has either full cast records or cast type records.
+    pub(crate) fn has_cast_data(&self) -> bool {
+        self.has::<Dated<Cast>>() || self.has::<Dated<CastType>>()
+    }
+
+    /// The number of casts of the user, counted from the full cast records and the cast type
+    /// records. See [`Self::remove_cast_types_on_dates`] for how a cast is kept from being stored
+    /// as both.
+    pub(crate) fn cast_count(&self) -> usize {
+        self.user_values_of_kind::<Dated<Cast>>().len()
+            + self.user_values_of_kind::<Dated<CastType>>().len()
+    }
+
+    /// Remove the cast type records on the dates. Older versions only stored cast type records, so
+    /// the cast type records of a date are removed once the full cast records of that date are
+    /// imported. Returns the number of removed records.
+    pub fn remove_cast_types_on_dates(
+        &mut self,
+        dates: impl IntoIterator<Item = NaiveDate>,
+    ) -> usize {
+        let dates: HashSet<NaiveDate> = dates.into_iter().collect();
+        self.retain_user_values(|x: &Dated<CastType>| !dates.contains(&x.date()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_utils::date;
+
+    #[test]
+    fn test_reply_and_channel_parents() {
+        let reply =
+            Cast::new(CastType::CAST).with_parent(CastParent::Cast(CastId::new(2_u64, "0xab")));
+        assert!(reply.is_reply());
+        assert_eq!(reply.parent_cast().unwrap().fid(), Fid::from(2_u64));
+        assert_eq!(reply.parent_url(), None);
+
+        let channel_cast = Cast::new(CastType::LONG_CAST).with_parent(CastParent::Url(
+            "https://warpcast.com/~/channel/rust".to_string(),
+        ));
+        assert!(!channel_cast.is_reply());
+        assert_eq!(
+            channel_cast.parent_url(),
+            Some("https://warpcast.com/~/channel/rust")
+        );
+    }
+
+    #[test]
+    fn test_cast_data_counts_both_kinds() {
+        let mut user = UserStoreWithNativeUserValue::new(1_u64);
+        assert!(!user.has_cast_data());
+        user.add_user_value(Dated::<CastType>::from(date("2025-01-01"), CastType::CAST));
+        user.add_user_value(Dated::<Cast>::from(
+            date("2025-01-02"),
+            Cast::new(CastType::CAST).with_mentions([3_u64]),
+        ));
+        assert!(user.has_cast_data());
+        assert_eq!(user.cast_count(), 2);
+    }
+
+    #[test]
+    fn test_remove_cast_types_on_dates() {
+        let mut user = UserStoreWithNativeUserValue::new(1_u64);
+        for day in ["2025-01-01", "2025-01-01", "2025-01-02"] {
+            user.add_user_value(Dated::<CastType>::from(date(day), CastType::CAST));
+        }
+
+        assert_eq!(user.remove_cast_types_on_dates([date("2025-01-01")]), 2);
+        let dates: Vec<NaiveDate> = user
+            .user_values_of_kind::<Dated<CastType>>()
+            .iter()
+            .map(|x| x.date())
+            .collect();
+        assert_eq!(dates, [date("2025-01-02")]);
+    }
+
+    #[test]
+    fn test_dated_cast_round_trip() {
+        let cast = Dated::<Cast>::from(
+            date("2025-01-01"),
+            Cast::new(CastType::CAST).with_hash("0x12345").with_embeds([
+                Embed::Url("https://example.com".to_string()),
+                Embed::Cast(CastId::new(5_u64, "0x99")),
+            ]),
+        );
+        let json = serde_json::to_string(&cast.as_any_user_value()).unwrap();
+        let deserialized: AnyNativeUserValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.specify::<Dated<Cast>>(), Some(cast));
+    }
+}
@@ -1,6 +1,8 @@
+use crate::core::UserValueContainer;
 use crate::dated::Dated;
 use crate::native_user_value::AnyNativeUserValue;
 use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
 use crate::NativeUserValue;
 use serde::Deserialize;
 use serde::Serialize;
@@ -8,8 +10,11 @@ use thiserror::Error;
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone, Hash)]
 #[non_exhaustive]
+#[allow(non_camel_case_types)]
 pub enum CastType {
     CAST,
+    LONG_CAST,
+    TEN_K_CAST,
 }
 
 impl TryFrom<&str> for CastType {
@@ -17,6 +22,8 @@ impl TryFrom<&str> for CastType {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "CAST" => Ok(Self::CAST),
+            "LONG_CAST" => Ok(Self::LONG_CAST),
+            "TEN_K_CAST" => Ok(Self::TEN_K_CAST),
             _ => Err(InvalidCastInputError::InvalidInput),
         }
     }
@@ -52,4 +59,12 @@ impl NativeUserValue for Dated<CastType> {
             _ => None,
         }
     }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_cast_types
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_cast_types
+    }
 }
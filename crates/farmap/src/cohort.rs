@@ -0,0 +1,381 @@
+//! Cohort analysis of users with spam data.
+//!
+//! A cohort is a group of users that share a key, usually the period in which they got their first
+//! spam label. [`Cohorts`] groups a [`SetWithSpamEntries`] into cohorts and [`Cohorts::table`]
+//! follows the spam scores of every cohort period by period in a [`CohortTable`].
+use crate::SetWithSpamEntries;
+use crate::SpamScoreCount;
+use crate::SpamScoreDistribution;
+use crate::UserWithSpamData;
+use crate::UsersSubset;
+use chrono::Datelike;
+use chrono::Days;
+use chrono::Months;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The length of a period of a cohort analysis. Weeks start on Monday and quarters start in
+/// January, April, July and October.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CohortPeriod {
+    Week,
+    Month,
+    Quarter,
+}
+
+impl CohortPeriod {
+    /// The first day of the period that contains the date.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            Self::Month => date.with_day(1).expect("every month has a first day"),
+            Self::Quarter => {
+                let month = date.month0() / 3 * 3 + 1;
+                NaiveDate::from_ymd_opt(date.year(), month, 1)
+                    .expect("every quarter has a first day")
+            }
+        }
+    }
+
+    /// The first day of the period after the period that contains the date.
+    pub fn next_start(self, date: NaiveDate) -> NaiveDate {
+        let start = self.start_of(date);
+        match self {
+            Self::Week => start + Days::new(7),
+            Self::Month => start + Months::new(1),
+            Self::Quarter => start + Months::new(3),
+        }
+    }
+
+    /// The last day of the period that contains the date.
+    pub fn end_of(self, date: NaiveDate) -> NaiveDate {
+        self.next_start(date) - Days::new(1)
+    }
+}
+
+impl Display for CohortPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Week => write!(f, "week"),
+            Self::Month => write!(f, "month"),
+            Self::Quarter => write!(f, "quarter"),
+        }
+    }
+}
+
+impl FromStr for CohortPeriod {
+    type Err = CohortPeriodParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "week" | "weekly" => Ok(Self::Week),
+            "month" | "monthly" => Ok(Self::Month),
+            "quarter" | "quarterly" => Ok(Self::Quarter),
+            _ => Err(CohortPeriodParseError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+#[error("{0:?} is not a cohort period, expected week, month or quarter")]
+pub struct CohortPeriodParseError(String);
+
+/// The users of a [`SetWithSpamEntries`] grouped into cohorts by a key.
+///
+/// Every cohort is a non-empty [`SetWithSpamEntries`] of the same collection as the set that the
+/// cohorts are created from. The cohorts are ordered by key.
+#[derive(Debug, Clone)]
+pub struct Cohorts<'a, K = NaiveDate> {
+    cohorts: BTreeMap<K, SetWithSpamEntries<'a>>,
+    latest_spam_score_date: NaiveDate,
+}
+
+impl<'a> Cohorts<'a> {
+    /// Group the users by the start of the period of their first spam label.
+    pub fn by_first_label(set: &SetWithSpamEntries<'a>, period: CohortPeriod) -> Self {
+        Self::by_key(set, |user| {
+            Some(period.start_of(user.earliest_spam_update().date()))
+        })
+    }
+}
+
+impl<'a, K: Ord> Cohorts<'a, K> {
+    /// Group the users by the key. Users for which the key returns None are not in any cohort.
+    pub fn by_key<F>(set: &SetWithSpamEntries<'a>, key: F) -> Self
+    where
+        F: Fn(&UserWithSpamData<'a>) -> Option<K>,
+    {
+        let subset = set.subset();
+        let empty = subset.filtered(|_| false);
+        let mut subsets: BTreeMap<K, UsersSubset<'a>> = BTreeMap::new();
+        for user in subset.iter() {
+            let user = UserWithSpamData::try_from(user)
+                .expect("SetWithSpamEntries should only contain users that have spam data");
+            if let Some(key) = key(&user) {
+                subsets
+                    .entry(key)
+                    .or_insert_with(|| empty.clone())
+                    .add_user(user);
+            }
+        }
+
+        let cohorts = subsets
+            .into_iter()
+            .map(|(key, subset)| {
+                let cohort = SetWithSpamEntries::try_from(subset)
+                    .expect("a cohort has at least one user with spam data");
+                (key, cohort)
+            })
+            .collect();
+
+        Self {
+            cohorts,
+            latest_spam_score_date: set.latest_spam_score_date(),
+        }
+    }
+
+    pub fn cohort(&self, key: &K) -> Option<&SetWithSpamEntries<'a>> {
+        self.cohorts.get(key)
+    }
+
+    /// The cohorts in ascending key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &SetWithSpamEntries<'a>)> {
+        self.cohorts.iter()
+    }
+
+    /// The number of cohorts.
+    pub fn len(&self) -> usize {
+        self.cohorts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cohorts.is_empty()
+    }
+
+    /// The spam scores of every cohort at the end of each period.
+    ///
+    /// The periods of a cohort start with the period of the earliest spam label in the cohort and
+    /// end with the period of the latest spam label in the set that the cohorts were created from,
+    /// so all rows end at the same period. The last period is measured at the latest spam label
+    /// date rather than at the end of the period.
+    pub fn table(&self, period: CohortPeriod) -> CohortTable<K>
+    where
+        K: Clone,
+    {
+        let rows = self
+            .cohorts
+            .iter()
+            .map(|(key, cohort)| {
+                let mut dates = Vec::new();
+                let mut start = period.start_of(cohort.earliest_spam_score_date());
+                while start <= self.latest_spam_score_date {
+                    dates.push(period.end_of(start).min(self.latest_spam_score_date));
+                    start = period.next_start(start);
+                }
+
+                let cells = cohort
+                    .spam_score_counts_at_dates(&dates)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, count)| CohortCell {
+                        period: index,
+                        date: count.date(),
+                        count: *count.as_inner(),
+                        distribution: SpamScoreDistribution::try_from(*count.as_inner()).ok(),
+                    })
+                    .collect();
+
+                CohortRow {
+                    cohort: key.clone(),
+                    user_count: cohort.user_count(),
+                    cells,
+                }
+            })
+            .collect();
+
+        CohortTable { period, rows }
+    }
+}
+
+/// The spam scores of cohorts period by period, with one row per cohort in ascending key order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CohortTable<K = NaiveDate> {
+    period: CohortPeriod,
+    rows: Vec<CohortRow<K>>,
+}
+
+impl<K> CohortTable<K> {
+    pub fn period(&self) -> CohortPeriod {
+        self.period
+    }
+
+    pub fn rows(&self) -> &[CohortRow<K>] {
+        &self.rows
+    }
+
+    pub fn row(&self, cohort: &K) -> Option<&CohortRow<K>>
+    where
+        K: PartialEq,
+    {
+        self.rows.iter().find(|row| row.cohort == *cohort)
+    }
+}
+
+/// The spam scores of one cohort period by period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CohortRow<K = NaiveDate> {
+    cohort: K,
+    user_count: usize,
+    cells: Vec<CohortCell>,
+}
+
+impl<K> CohortRow<K> {
+    pub fn cohort(&self) -> &K {
+        &self.cohort
+    }
+
+    pub fn user_count(&self) -> usize {
+        self.user_count
+    }
+
+    pub fn cells(&self) -> &[CohortCell] {
+        &self.cells
+    }
+}
+
+/// The spam scores of a cohort at the end of a period.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CohortCell {
+    period: usize,
+    date: NaiveDate,
+    count: SpamScoreCount,
+    distribution: Option<SpamScoreDistribution>,
+}
+
+impl CohortCell {
+    /// The number of periods since the first period of the cohort.
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    /// The date that the spam scores are counted at.
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn count(&self) -> SpamScoreCount {
+        self.count
+    }
+
+    /// The distribution of the spam scores. None if no user in the cohort has a spam score at the
+    /// date.
+    pub fn distribution(&self) -> Option<SpamScoreDistribution> {
+        self.distribution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam_score::DatedSpamUpdate;
+    use crate::time_utils::date;
+    use crate::user_collection::tests::dummy_data;
+    use crate::Fid;
+    use crate::SpamScore;
+    use crate::UserCollectionWithNativeUserValue;
+
+    fn fids(set: &SetWithSpamEntries) -> Vec<u64> {
+        set.subset().iter().map(|user| user.fid().into()).collect()
+    }
+
+    #[test]
+    fn test_period_boundaries() {
+        let day = date("2025-02-13");
+        assert_eq!(CohortPeriod::Week.start_of(day), date("2025-02-10"));
+        assert_eq!(CohortPeriod::Week.end_of(day), date("2025-02-16"));
+        assert_eq!(CohortPeriod::Month.start_of(day), date("2025-02-01"));
+        assert_eq!(CohortPeriod::Month.end_of(day), date("2025-02-28"));
+        assert_eq!(CohortPeriod::Quarter.start_of(day), date("2025-01-01"));
+        assert_eq!(CohortPeriod::Quarter.next_start(day), date("2025-04-01"));
+        assert_eq!(
+            CohortPeriod::Quarter.end_of(date("2024-12-31")),
+            date("2024-12-31")
+        );
+        assert_eq!("Monthly".parse(), Ok(CohortPeriod::Month));
+        assert!("daily".parse::<CohortPeriod>().is_err());
+    }
+
+    #[test]
+    fn test_cohorts_by_first_label() {
+        let collection = dummy_data();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let cohorts = Cohorts::by_first_label(&set, CohortPeriod::Month);
+        assert_eq!(cohorts.len(), 2);
+        assert_eq!(fids(cohorts.cohort(&date("2024-01-01")).unwrap()), [1]);
+        assert_eq!(fids(cohorts.cohort(&date("2025-01-01")).unwrap()), [2]);
+        assert!(cohorts.cohort(&date("2024-02-01")).is_none());
+    }
+
+    #[test]
+    fn test_cohorts_by_custom_key() {
+        let collection = dummy_data();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let cohorts = Cohorts::by_key(&set, |user| {
+            (user.fid() != Fid::from(2_u64)).then_some(user.latest_spam_update().score() as u8)
+        });
+        assert_eq!(cohorts.len(), 1);
+        assert_eq!(fids(cohorts.cohort(&(SpamScore::Zero as u8)).unwrap()), [1]);
+    }
+
+    #[test]
+    fn test_cohort_table() {
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        let updates = [
+            (1_u64, "2025-01-03", SpamScore::One),
+            (1, "2025-02-10", SpamScore::Two),
+            (2, "2025-01-20", SpamScore::Zero),
+            (3, "2025-02-05", SpamScore::Two),
+            (3, "2025-03-15", SpamScore::Zero),
+        ];
+        collection.add_user_value_iter(updates.map(|(fid, day, score)| {
+            crate::Fidded::from((DatedSpamUpdate::from(date(day), score), Fid::from(fid)))
+        }));
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let table = Cohorts::by_first_label(&set, CohortPeriod::Month).table(CohortPeriod::Month);
+
+        assert_eq!(table.rows().len(), 2);
+        let january = table.row(&date("2025-01-01")).unwrap();
+        assert_eq!(january.user_count(), 2);
+        let counts: Vec<(NaiveDate, [u64; 3])> = january
+            .cells()
+            .iter()
+            .map(|cell| (cell.date(), cell.count().into()))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (date("2025-01-31"), [1, 1, 0]),
+                (date("2025-02-28"), [1, 0, 1]),
+                (date("2025-03-15"), [1, 0, 1]),
+            ]
+        );
+
+        let february = table.row(&date("2025-02-01")).unwrap();
+        assert_eq!(february.cells().len(), 2);
+        assert_eq!(february.cells()[1].period(), 1);
+        assert_eq!(
+            february.cells()[1].distribution().unwrap().spam(),
+            1.0,
+            "fid 3 is spam at the latest date"
+        );
+
+        let json = serde_json::to_string(&table).unwrap();
+        assert_eq!(serde_json::from_str::<CohortTable>(&json).unwrap(), table);
+    }
+}
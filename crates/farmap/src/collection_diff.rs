@@ -0,0 +1,302 @@
+use crate::AnyNativeUserValue;
+use crate::AnyNativeUserValueRef;
+use crate::Fid;
+use crate::UserCollectionWithNativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem::{discriminant, Discriminant};
+
+/// The changes between an older and a newer [`UserCollectionWithNativeUserValue`]. Fids are sorted
+/// in ascending order.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CollectionDiff {
+    added_fids: Vec<Fid>,
+    removed_fids: Vec<Fid>,
+    changed: Vec<UserDiff>,
+}
+
+impl CollectionDiff {
+    /// Fids that only exist in the newer collection.
+    pub fn added_fids(&self) -> &[Fid] {
+        &self.added_fids
+    }
+
+    /// Fids that only exist in the older collection.
+    pub fn removed_fids(&self) -> &[Fid] {
+        &self.removed_fids
+    }
+
+    /// The users that exist in both collections but have different values.
+    pub fn changed(&self) -> &[UserDiff] {
+        &self.changed
+    }
+
+    /// Returns true if the collections hold the same users with the same values.
+    pub fn is_empty(&self) -> bool {
+        self.added_fids.is_empty() && self.removed_fids.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The changed values of a user that exists in both collections. Values that occur several times
+/// are counted, so a value that is stored twice in the newer collection and once in the older
+/// collection is added once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserDiff {
+    fid: Fid,
+    added: Vec<AnyNativeUserValue>,
+    removed: Vec<AnyNativeUserValue>,
+}
+
+impl UserDiff {
+    pub fn fid(&self) -> Fid {
+        self.fid
+    }
+
+    /// Values that are only stored in the newer collection.
+    pub fn added(&self) -> &[AnyNativeUserValue] {
+        &self.added
+    }
+
+    /// Values that are only stored in the older collection.
+    pub fn removed(&self) -> &[AnyNativeUserValue] {
+        &self.removed
+    }
+
+    fn new(
+        older: &UserStoreWithNativeUserValue,
+        newer: &UserStoreWithNativeUserValue,
+    ) -> Option<Self> {
+        if older == newer {
+            return None;
+        }
+
+        let mut diff = Self {
+            fid: newer.fid(),
+            added: Vec::new(),
+            removed: Vec::new(),
+        };
+        for (_, older, newer) in values_by_kind(older, newer) {
+            diff.add_changes_of_kind(&older, &newer);
+        }
+
+        (!diff.added.is_empty() || !diff.removed.is_empty()).then_some(diff)
+    }
+
+    /// Compare the values of one kind. The values are kept in the same order in both users, and
+    /// an import mostly adds values, so values of a kind that are equal are skipped and only the
+    /// values after the common prefix are counted. Only changed values are cloned.
+    fn add_changes_of_kind(
+        &mut self,
+        older: &[AnyNativeUserValueRef],
+        newer: &[AnyNativeUserValueRef],
+    ) {
+        if older == newer {
+            return;
+        }
+
+        let common = older.iter().zip(newer).take_while(|(x, y)| x == y).count();
+        let (older, newer) = (&older[common..], &newer[common..]);
+        let mut counts: HashMap<AnyNativeUserValueRef, isize> = HashMap::new();
+        for value in older {
+            *counts.entry(*value).or_default() -= 1;
+        }
+        for value in newer {
+            *counts.entry(*value).or_default() += 1;
+        }
+
+        self.added.extend(
+            newer
+                .iter()
+                .filter(|value| take_count(&mut counts, value, 1))
+                .map(|value| (*value).into()),
+        );
+        self.removed.extend(
+            older
+                .iter()
+                .filter(|value| take_count(&mut counts, value, -1))
+                .map(|value| (*value).into()),
+        );
+    }
+}
+
+/// The kind along with the older and the newer values of the kind.
+type KindValues<'a> = (
+    Discriminant<AnyNativeUserValueRef<'a>>,
+    Vec<AnyNativeUserValueRef<'a>>,
+    Vec<AnyNativeUserValueRef<'a>>,
+);
+
+/// The values of both users grouped by kind, see
+/// [`UserStore::all_user_values`](crate::UserStore::all_user_values). The kinds of the older user
+/// come first. Kinds that neither user stores are left out.
+fn values_by_kind<'a>(
+    older: &'a UserStoreWithNativeUserValue,
+    newer: &'a UserStoreWithNativeUserValue,
+) -> Vec<KindValues<'a>> {
+    let mut kinds: Vec<KindValues> = Vec::new();
+    let values = older
+        .all_user_values()
+        .map(|value| (value, false))
+        .chain(newer.all_user_values().map(|value| (value, true)));
+    for (value, is_newer) in values {
+        let kind = discriminant(&value);
+        let index = match kinds.iter().position(|(x, _, _)| *x == kind) {
+            Some(index) => index,
+            None => {
+                kinds.push((kind, Vec::new(), Vec::new()));
+                kinds.len() - 1
+            }
+        };
+        let (_, older, newer) = &mut kinds[index];
+        if is_newer {
+            newer.push(value);
+        } else {
+            older.push(value);
+        }
+    }
+    kinds
+}
+
+/// Moves the count of the value one step towards zero if it has the sign of the direction.
+fn take_count<S: Eq + Hash>(counts: &mut HashMap<S, isize>, value: &S, direction: isize) -> bool {
+    let count = counts.get_mut(value).expect("every value is counted");
+    if count.signum() == direction {
+        *count -= direction;
+        true
+    } else {
+        false
+    }
+}
+
+impl UserCollectionWithNativeUserValue {
+    /// The changes from this collection to a newer collection.
+    pub fn diff(&self, newer: &UserCollectionWithNativeUserValue) -> CollectionDiff {
+        let added_fids: Vec<Fid> = newer
+            .iter()
+            .map(|user| user.fid())
+            .filter(|fid| self.user(*fid).is_none())
+            .collect();
+        let removed_fids: Vec<Fid> = self
+            .iter()
+            .map(|user| user.fid())
+            .filter(|fid| newer.user(*fid).is_none())
+            .collect();
+        let changed: Vec<UserDiff> = self
+            .iter()
+            .flat_map(|older| {
+                newer
+                    .user(older.fid())
+                    .and_then(|newer| UserDiff::new(older, newer))
+            })
+            .collect();
+
+        CollectionDiff {
+            added_fids,
+            removed_fids,
+            changed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam_score::DatedSpamUpdate;
+    use crate::time_utils::date;
+    use crate::user_collection::tests::dummy_data;
+    use crate::Fidded;
+    use crate::NativeUserValue;
+    use crate::SpamScore;
+
+    fn spam_update(fid: u64, score: SpamScore, day: &str) -> Fidded<DatedSpamUpdate> {
+        Fidded::from((DatedSpamUpdate::from(date(day), score), Fid::from(fid)))
+    }
+
+    #[test]
+    fn test_diff_with_itself_is_empty() {
+        let collection = dummy_data();
+        assert!(collection.diff(&collection).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut older = UserCollectionWithNativeUserValue::default();
+        older.add_user_value_iter([
+            spam_update(1, SpamScore::One, "2025-01-01"),
+            spam_update(2, SpamScore::One, "2025-01-01"),
+            spam_update(3, SpamScore::Two, "2025-01-01"),
+        ]);
+        let mut newer = UserCollectionWithNativeUserValue::default();
+        newer.add_user_value_iter([
+            spam_update(4, SpamScore::Zero, "2025-01-02"),
+            spam_update(2, SpamScore::One, "2025-01-01"),
+            spam_update(2, SpamScore::Zero, "2025-01-02"),
+            spam_update(3, SpamScore::Two, "2025-01-01"),
+        ]);
+
+        let diff = older.diff(&newer);
+        assert_eq!(diff.added_fids(), [Fid::from(4_u64)]);
+        assert_eq!(diff.removed_fids(), [Fid::from(1_u64)]);
+        assert_eq!(diff.changed().len(), 1);
+        let user_diff = &diff.changed()[0];
+        assert_eq!(user_diff.fid(), Fid::from(2_u64));
+        assert_eq!(
+            user_diff.added(),
+            [spam_update(2, SpamScore::Zero, "2025-01-02")
+                .unfid()
+                .into_any_user_value()]
+        );
+        assert!(user_diff.removed().is_empty());
+
+        let json = serde_json::to_string(&diff).unwrap();
+        assert_eq!(serde_json::from_str::<CollectionDiff>(&json).unwrap(), diff);
+    }
+
+    #[test]
+    fn test_diff_after_common_values() {
+        let mut older = UserCollectionWithNativeUserValue::default();
+        older.add_user_value_iter([
+            spam_update(1, SpamScore::One, "2025-01-01"),
+            spam_update(1, SpamScore::Zero, "2025-01-02"),
+            spam_update(1, SpamScore::Two, "2025-01-03"),
+        ]);
+        let mut newer = UserCollectionWithNativeUserValue::default();
+        newer.add_user_value_iter([
+            spam_update(1, SpamScore::One, "2025-01-01"),
+            spam_update(1, SpamScore::Two, "2025-01-03"),
+            spam_update(1, SpamScore::Two, "2025-01-04"),
+        ]);
+
+        let diff = older.diff(&newer);
+        let user_diff = &diff.changed()[0];
+        let any = |day| {
+            spam_update(1, SpamScore::Two, day)
+                .unfid()
+                .into_any_user_value()
+        };
+        assert_eq!(user_diff.added(), [any("2025-01-04")]);
+        assert_eq!(
+            user_diff.removed(),
+            [spam_update(1, SpamScore::Zero, "2025-01-02")
+                .unfid()
+                .into_any_user_value()]
+        );
+    }
+
+    #[test]
+    fn test_diff_of_values_that_are_not_spam_updates() {
+        let mut older = UserCollectionWithNativeUserValue::default();
+        older.add_user_value_iter([spam_update(1, SpamScore::One, "2025-01-01")]);
+        let mut newer = older.clone();
+        newer.add_user_value_iter([Fidded::from((SpamScore::Two, Fid::from(1_u64)))]);
+
+        let diff = older.diff(&newer);
+        assert_eq!(
+            diff.changed()[0].added(),
+            [SpamScore::Two.into_any_user_value()]
+        );
+        assert!(diff.changed()[0].removed().is_empty());
+    }
+}
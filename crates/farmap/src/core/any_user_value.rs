@@ -1,8 +1,17 @@
 use super::user_value::UserValue;
+use super::UserValueStorage;
 
 /// A representation that can store different kinds of  [`UserValue`]s.
 /// It can only be converted into one [`UserValue`] at the time, a that is carried out via the specify methods.
+///
+/// The Storage is where a [`UserStore`](super::UserStore) keeps the values, see [`UserValueStorage`].
 pub trait AnyUserValue: Sized {
+    type Storage: UserValueStorage<Self>;
+
+    /// A borrowed value of any of the kinds, as returned by
+    /// [`UserValueStorage::any_value_refs`]. Converting it into the AnyUserValue clones the value.
+    type Ref<'a>: Copy + Into<Self>;
+
     fn specify<S: UserValue<Self>>(self) -> Option<S>;
     fn specify_ref<S: UserValue<Self>>(&self) -> Option<&S>;
 }
@@ -0,0 +1,104 @@
+use super::Fid;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Decides which value is stored when an incoming [`UserValue`](super::UserValue) collides with
+/// a value that is already stored for the same user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum CollisionPolicy {
+    /// Neither value is stored. The stored value is removed and the incoming value is discarded.
+    /// Later values in the same ingestion that collide with a rejected value are rejected too.
+    Reject,
+    /// The stored value is kept and the incoming value is discarded.
+    #[default]
+    KeepFirst,
+    /// The stored value is removed and replaced by the incoming value.
+    KeepLast,
+}
+
+/// A single collision between a stored value and an incoming value for a fid.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct UserValueCollision<S> {
+    fid: Fid,
+    existing: S,
+    incoming: S,
+}
+
+impl<S> UserValueCollision<S> {
+    pub(crate) fn new(fid: Fid, existing: S, incoming: S) -> Self {
+        Self {
+            fid,
+            existing,
+            incoming,
+        }
+    }
+
+    pub fn fid(&self) -> Fid {
+        self.fid
+    }
+
+    /// The value that was stored before the collision.
+    pub fn existing(&self) -> &S {
+        &self.existing
+    }
+
+    /// The value that collided with the stored value.
+    pub fn incoming(&self) -> &S {
+        &self.incoming
+    }
+}
+
+/// The result of a bulk ingestion with collision checks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollisionReport<S> {
+    policy: CollisionPolicy,
+    added: usize,
+    collisions: Vec<UserValueCollision<S>>,
+}
+
+impl<S> CollisionReport<S> {
+    pub(crate) fn new(policy: CollisionPolicy) -> Self {
+        Self {
+            policy,
+            added: 0,
+            collisions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn record_added(&mut self, count: usize) {
+        self.added += count;
+    }
+
+    pub(crate) fn record_collision(&mut self, collision: UserValueCollision<S>) {
+        self.collisions.push(collision);
+    }
+
+    /// The policy that was used for the ingestion.
+    pub fn policy(&self) -> CollisionPolicy {
+        self.policy
+    }
+
+    /// The number of values that were stored without colliding with another value and were not
+    /// taken out again by a later value.
+    pub fn added_count(&self) -> usize {
+        self.added
+    }
+
+    /// Returns true if no value collided.
+    pub fn is_empty(&self) -> bool {
+        self.collisions.is_empty()
+    }
+
+    pub fn collisions(&self) -> &[UserValueCollision<S>] {
+        &self.collisions
+    }
+
+    pub fn into_collisions(self) -> Vec<UserValueCollision<S>> {
+        self.collisions
+    }
+
+    /// All the fids that had at least one collision, in the order they were first seen.
+    pub fn fids(&self) -> Vec<Fid> {
+        self.collisions.iter().map(|x| x.fid).unique().collect()
+    }
+}
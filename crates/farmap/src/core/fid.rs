@@ -2,7 +2,7 @@ use std::fmt::Debug;
 use std::num::TryFromIntError;
 
 use serde::{Deserialize, Serialize};
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
 pub struct Fid(u64);
 
 impl std::fmt::Display for Fid {
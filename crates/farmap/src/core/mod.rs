@@ -1,20 +1,35 @@
 mod any_user_value;
 mod collidable;
+mod collision_report;
 mod fid;
 mod has_tag;
+mod source;
 mod user_collection;
 mod user_collection_error;
 mod user_error;
 mod user_store;
 mod user_value;
+mod user_value_container;
+mod user_value_storage;
 
 pub use any_user_value::AnyUserValue;
 pub use collidable::Collidable;
+pub use collision_report::CollisionPolicy;
+pub use collision_report::CollisionReport;
+pub use collision_report::UserValueCollision;
 pub use fid::Fid;
 pub use has_tag::HasTag;
-#[expect(unused)]
+pub use source::Source;
+pub use source::SourceId;
+pub use source::SourceLocation;
+pub use source::Sourced;
 pub use user_collection::UserCollection;
 pub use user_collection_error::CollectionError;
 pub use user_error::UserError;
 pub use user_store::UserStore;
 pub use user_value::UserValue;
+pub use user_value_container::UserValueContainer;
+pub use user_value_storage::UserValueStorage;
+
+#[cfg(test)]
+pub(crate) use user_collection::tests;
@@ -0,0 +1,151 @@
+use super::HasTag;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Where a [`UserValue`](super::UserValue) came from.
+///
+/// A source is created with a dataset name and the remaining fields are set with the builder
+/// methods. The import time defaults to the time the source was created.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Source {
+    dataset: String,
+    location: Option<SourceLocation>,
+    fetcher: Option<String>,
+    imported_at: NaiveDateTime,
+}
+
+/// The location within a dataset that a [`Source`] refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SourceLocation {
+    FilePath(PathBuf),
+    Commit(String),
+}
+
+impl Source {
+    pub fn new(dataset: impl Into<String>) -> Self {
+        Self {
+            dataset: dataset.into(),
+            location: None,
+            fetcher: None,
+            imported_at: Utc::now().naive_utc(),
+        }
+    }
+
+    pub fn with_file_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.location = Some(SourceLocation::FilePath(path.into()));
+        self
+    }
+
+    pub fn with_commit(mut self, commit: impl Into<String>) -> Self {
+        self.location = Some(SourceLocation::Commit(commit.into()));
+        self
+    }
+
+    /// The name of the fetcher or importer that produced the values.
+    pub fn with_fetcher(mut self, fetcher: impl Into<String>) -> Self {
+        self.fetcher = Some(fetcher.into());
+        self
+    }
+
+    pub fn with_import_time(mut self, imported_at: NaiveDateTime) -> Self {
+        self.imported_at = imported_at;
+        self
+    }
+
+    pub fn dataset(&self) -> &str {
+        &self.dataset
+    }
+
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+
+    pub fn fetcher(&self) -> Option<&str> {
+        self.fetcher.as_deref()
+    }
+
+    pub fn imported_at(&self) -> NaiveDateTime {
+        self.imported_at
+    }
+
+    /// The id of the source. The id only depends on the fields of the source, so equal sources
+    /// have the same id in every collection and in every run of the program.
+    pub fn id(&self) -> SourceId {
+        // FNV-1a over the serialized source, which is stable between runs unlike the std hasher.
+        let bytes = serde_json::to_vec(self).expect("source is always serializable");
+        let hash = bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+        SourceId(hash)
+    }
+}
+
+/// A compact reference to a [`Source`], which is stored with every sourced value. The
+/// [`UserCollection`](super::UserCollection) keeps the [`Source`] for each id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SourceId(u64);
+
+/// A value tagged with the id of the [`Source`] it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sourced<T> {
+    inner: T,
+    source: SourceId,
+}
+
+impl<T> Sourced<T> {
+    pub fn source(&self) -> SourceId {
+        self.source
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    pub fn as_inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> From<(T, SourceId)> for Sourced<T> {
+    fn from(value: (T, SourceId)) -> Self {
+        Self {
+            inner: value.0,
+            source: value.1,
+        }
+    }
+}
+
+impl<T> HasTag<SourceId, T> for Sourced<T> {
+    fn tag(&self) -> SourceId {
+        self.source
+    }
+
+    fn untag(self) -> (SourceId, T) {
+        (self.source, self.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn import_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_id_is_stable_and_depends_on_fields() {
+        let source = Source::new("labels")
+            .with_commit("abc")
+            .with_import_time(import_time());
+        assert_eq!(source.id(), source.clone().id());
+        assert_ne!(source.id(), source.clone().with_commit("abd").id());
+        assert_ne!(source.id(), source.with_fetcher("GithubFetcher").id());
+    }
+}
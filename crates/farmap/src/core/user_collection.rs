@@ -1,11 +1,19 @@
 use super::user_value::UserValue;
 use super::AnyUserValue;
 use super::CollectionError;
+use super::Collidable;
+use super::CollisionPolicy;
+use super::CollisionReport;
 use super::Fid;
 use super::HasTag;
+use super::Source;
+use super::SourceId;
 use super::UserStore;
-use std::collections::hash_map::Entry::Vacant;
+use super::UserValueCollision;
+use std::collections::btree_map::Entry::Vacant;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::ops::RangeBounds;
 
 /// A representation of one or several users.
 ///
@@ -13,37 +21,243 @@ use std::collections::HashMap;
 ///
 /// The type parameter T refers to the [`AnyUserValue`] that all the users in the collection
 /// implement. All users in the collection must be generic over the same [`AnyUserValue`].
+///
+/// The users are ordered by fid. Iteration is always in ascending fid order.
+///
+/// The collection also keeps the [`Source`] of every [`SourceId`] that the values of its users are
+/// tagged with.
 #[derive(Debug, PartialEq, Clone)]
 pub struct UserCollection<T: AnyUserValue> {
-    map: HashMap<Fid, UserStore<T>>,
+    map: BTreeMap<Fid, UserStore<T>>,
+    sources: BTreeMap<SourceId, Source>,
 }
 
 impl<T: AnyUserValue> Default for UserCollection<T> {
     fn default() -> Self {
         Self {
-            map: HashMap::new(),
+            map: BTreeMap::new(),
+            sources: BTreeMap::new(),
         }
     }
 }
 
-#[expect(unused)]
 impl<T: AnyUserValue> UserCollection<T> {
-    pub fn add_user_value_iter<S: UserValue<T>, F: IntoIterator<Item = impl HasTag<Fid, S>>>(
-        &mut self,
-        values: F,
-    ) -> Option<Vec<CollectionError>> {
-        let mut errors: Option<Vec<CollectionError>> = None;
+    /// Add every value to the user with the fid it is tagged with. Users that do not exist yet
+    /// are created. This method does not check for collisions.
+    pub fn add_user_value_iter<S, F>(&mut self, values: F)
+    where
+        S: UserValue<T>,
+        F: IntoIterator<Item: HasTag<Fid, S>>,
+    {
         for value in values {
-            if let Some(user) = self.user_mut(value.tag()) {
-                user.add_user_value(value.untag().1)
+            let (fid, value) = value.untag();
+            if let Some(user) = self.user_mut(fid) {
+                user.add_user_value(value);
             } else {
-                let fid = value.tag();
-                let mut user = UserStore::from(fid);
-                user.add_user_value(value.untag().1);
+                let mut user = UserStore::new(fid);
+                user.add_user_value(value);
                 self.add_user(user).expect("new user cannot collide");
             }
         }
-        errors
+    }
+
+    /// Add every value to the user with the fid it is tagged with, checking each value for
+    /// collisions with the values that are already stored. The policy decides which value is
+    /// stored when two values collide. Every collision is recorded in the returned
+    /// [`CollisionReport`].
+    ///
+    /// Values are checked in order, so an incoming value is compared against the values that
+    /// remain after the earlier values in the iterator have been handled. A user that does not
+    /// exist yet is only created when one of its values is stored.
+    pub fn try_add_user_value_iter<S, F>(
+        &mut self,
+        values: F,
+        policy: CollisionPolicy,
+    ) -> CollisionReport<S>
+    where
+        S: UserValue<T> + Collidable + Clone,
+        F: IntoIterator<Item: HasTag<Fid, S>>,
+    {
+        let values = values.into_iter().map(|value| {
+            let (fid, value) = value.untag();
+            (fid, value, None)
+        });
+        self.try_add_user_values_with_sources(values, policy)
+    }
+
+    /// Add every value to the user with the fid it is tagged with and record the source of every
+    /// value. Users that do not exist yet are created. This method does not check for collisions.
+    /// Returns the id of the source.
+    pub fn add_user_value_iter_from_source<S, F>(&mut self, source: Source, values: F) -> SourceId
+    where
+        S: UserValue<T>,
+        F: IntoIterator<Item: HasTag<Fid, S>>,
+    {
+        let source = self.register_source(source);
+        for value in values {
+            let (fid, value) = value.untag();
+            self.add_user_value_with_source(fid, value, Some(source));
+        }
+        source
+    }
+
+    /// Like [`Self::try_add_user_value_iter`], but the source of every value is recorded. Returns
+    /// the id of the source along with the report.
+    pub fn try_add_user_value_iter_from_source<S, F>(
+        &mut self,
+        source: Source,
+        values: F,
+        policy: CollisionPolicy,
+    ) -> (SourceId, CollisionReport<S>)
+    where
+        S: UserValue<T> + Collidable + Clone,
+        F: IntoIterator<Item: HasTag<Fid, S>>,
+    {
+        let source = self.register_source(source);
+        let values = values.into_iter().map(|value| {
+            let (fid, value) = value.untag();
+            (fid, value, Some(source))
+        });
+        (
+            source,
+            self.try_add_user_values_with_sources(values, policy),
+        )
+    }
+
+    pub(crate) fn try_add_user_values_with_sources<S>(
+        &mut self,
+        values: impl IntoIterator<Item = (Fid, S, Option<SourceId>)>,
+        policy: CollisionPolicy,
+    ) -> CollisionReport<S>
+    where
+        S: UserValue<T> + Collidable + Clone,
+    {
+        let mut report = CollisionReport::new(policy);
+        // The values that have been rejected so far, so that a rejection sticks for the rest of
+        // the batch instead of letting a later value take the place of the rejected values.
+        let mut rejected: HashMap<Fid, Vec<S>> = HashMap::new();
+        // The values of the batch that are still stored, as a later value can take them out again.
+        // When the first value is kept nothing is taken out, so the values are only counted.
+        let mut added: HashMap<Fid, Vec<S>> = HashMap::new();
+        let mut added_count = 0;
+        let mut created: Vec<Fid> = Vec::new();
+        for (fid, value, source) in values {
+            let mut existing: Vec<S> = match (policy, self.map.get_mut(&fid)) {
+                (_, None) => Vec::new(),
+                (CollisionPolicy::KeepFirst, Some(user)) => user
+                    .user_values_of_kind::<S>()
+                    .iter()
+                    .filter(|x| Collidable::is_collision(*x, &value))
+                    .cloned()
+                    .collect(),
+                (_, Some(user)) => user.take_collisions(&value),
+            };
+            if policy == CollisionPolicy::Reject {
+                existing.extend(
+                    rejected
+                        .get(&fid)
+                        .into_iter()
+                        .flatten()
+                        .filter(|x| Collidable::is_collision(*x, &value))
+                        .cloned(),
+                );
+            }
+
+            if existing.is_empty() {
+                if policy == CollisionPolicy::KeepFirst {
+                    added_count += 1;
+                } else {
+                    added.entry(fid).or_default().push(value.clone());
+                }
+                if !self.map.contains_key(&fid) {
+                    created.push(fid);
+                }
+                self.add_user_value_with_source(fid, value, source);
+                continue;
+            }
+
+            for existing in &existing {
+                report.record_collision(UserValueCollision::new(
+                    fid,
+                    existing.clone(),
+                    value.clone(),
+                ));
+            }
+            if let Some(added) = added.get_mut(&fid) {
+                added.retain(|x| !Collidable::is_collision(x, &value));
+            }
+
+            match policy {
+                CollisionPolicy::KeepFirst => {}
+                CollisionPolicy::KeepLast => self.add_user_value_with_source(fid, value, source),
+                CollisionPolicy::Reject => {
+                    let rejected = rejected.entry(fid).or_default();
+                    rejected.extend(existing);
+                    rejected.push(value);
+                }
+            }
+        }
+
+        // A user that was created for values that were rejected afterwards is removed again.
+        for fid in created {
+            if self.map.get(&fid).is_some_and(|user| user.is_empty()) {
+                self.map.remove(&fid);
+            }
+        }
+        report.record_added(added_count + added.values().map(Vec::len).sum::<usize>());
+        report
+    }
+
+    /// Add the value to the user with the fid and create the user if it does not exist yet. This
+    /// method does not check for collisions.
+    fn add_user_value_with_source<S: UserValue<T>>(
+        &mut self,
+        fid: Fid,
+        value: S,
+        source: Option<SourceId>,
+    ) {
+        self.map
+            .entry(fid)
+            .or_insert_with(|| UserStore::new(fid))
+            .add_user_value_with_source(value, source);
+    }
+
+    /// Add the source to the sources of the collection and return its id. Values can then be
+    /// tagged with the id, see [`Sourced`](super::Sourced).
+    pub fn register_source(&mut self, source: Source) -> SourceId {
+        let id = source.id();
+        self.sources.insert(id, source);
+        id
+    }
+
+    pub fn source(&self, id: SourceId) -> Option<&Source> {
+        self.sources.get(&id)
+    }
+
+    /// All the registered sources, ordered by id.
+    pub fn sources(&self) -> impl Iterator<Item = (SourceId, &Source)> {
+        self.sources.iter().map(|(id, source)| (*id, source))
+    }
+
+    /// Every value of type S that came from the source, along with the fid of its user.
+    pub fn user_values_from_source<'a, S: UserValue<T> + 'a>(
+        &'a self,
+        source: SourceId,
+    ) -> impl Iterator<Item = (Fid, &'a S)> {
+        self.iter().flat_map(move |user| {
+            user.user_values_from_source::<S>(source)
+                .map(move |value| (user.fid(), value))
+        })
+    }
+
+    /// Remove every value of every kind that came from the source, along with the source itself.
+    /// Users are kept even if they have no values left, see [`Self::remove_empty_users`]. Returns
+    /// the number of removed values.
+    pub fn remove_user_values_from_source(&mut self, source: SourceId) -> usize {
+        self.sources.remove(&source);
+        self.iter_mut()
+            .map(|user| user.remove_user_values_from_source(source))
+            .sum()
     }
 
     pub fn add_user(&mut self, user: UserStore<T>) -> Result<(), CollectionError> {
@@ -55,7 +269,400 @@ impl<T: AnyUserValue> UserCollection<T> {
         }
     }
 
-    pub fn user_mut(&mut self, fid: Fid) -> Option<&mut UserStore<T>> {
+    pub fn user(&self, fid: impl Into<Fid>) -> Option<&UserStore<T>> {
+        let fid: Fid = fid.into();
+        self.map.get(&fid)
+    }
+
+    pub fn user_mut(&mut self, fid: impl Into<Fid>) -> Option<&mut UserStore<T>> {
+        let fid: Fid = fid.into();
         self.map.get_mut(&fid)
     }
+
+    pub(crate) fn user_mut_unchecked(&mut self, fid: impl Into<Fid>) -> &mut UserStore<T> {
+        let fid: Fid = fid.into();
+        self.map
+            .get_mut(&fid)
+            .expect("fid {fid} should exist in collection")
+    }
+
+    pub fn user_count(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Remove all the [`UserValue`]s of type S from every user. Users are kept even if they have
+    /// no values left, see [`Self::remove_empty_users`]. Returns the number of removed values.
+    pub fn remove_user_values_of_kind<S: UserValue<T>>(&mut self) -> usize {
+        self.iter_mut()
+            .map(|user| user.remove_user_values_of_kind::<S>().len())
+            .sum()
+    }
+
+    /// Keep only the [`UserValue`]s of type S for which the predicate returns true, for every
+    /// user. Values of other kinds are left untouched. Returns the number of removed values.
+    pub fn retain_user_values<S: UserValue<T>>(
+        &mut self,
+        mut predicate: impl FnMut(&S) -> bool,
+    ) -> usize {
+        self.iter_mut()
+            .map(|user| user.retain_user_values(&mut predicate))
+            .sum()
+    }
+
+    /// Remove the users that have no values of any kind. Returns the number of removed users.
+    pub fn remove_empty_users(&mut self) -> usize {
+        let count = self.user_count();
+        self.map.retain(|_, user| !user.is_empty());
+        count - self.user_count()
+    }
+
+    /// Applies a filter to the user data. Use with caution since the data is removed from the
+    /// struct. For most situations it is preferred to create a subset of the data.
+    pub fn apply_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&UserStore<T>) -> bool,
+    {
+        self.map.retain(|_, user| filter(user));
+    }
+
+    /// All users in ascending fid order.
+    pub fn iter(&self) -> impl Iterator<Item = &UserStore<T>> {
+        self.map.values()
+    }
+
+    /// The users with a fid in the range, in ascending fid order.
+    pub fn range(&self, range: impl RangeBounds<Fid>) -> impl Iterator<Item = &UserStore<T>> {
+        self.map.range(range).map(|(_, user)| user)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut UserStore<T>> {
+        self.map.values_mut()
+    }
+
+    pub fn data(&self) -> &BTreeMap<Fid, UserStore<T>> {
+        &self.map
+    }
+}
+
+impl<T: AnyUserValue> From<BTreeMap<Fid, UserStore<T>>> for UserCollection<T> {
+    fn from(value: BTreeMap<Fid, UserStore<T>>) -> Self {
+        Self {
+            map: value,
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: AnyUserValue> From<HashMap<Fid, UserStore<T>>> for UserCollection<T> {
+    fn from(value: HashMap<Fid, UserStore<T>>) -> Self {
+        Self::from(value.into_iter().collect::<BTreeMap<_, _>>())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::core::UserValueContainer;
+    use crate::Fidded;
+    use crate::UserValueStorage;
+
+    /// A minimal [`AnyUserValue`] that is not native to the crate.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ExternalUserValue {
+        Note(Note),
+        Rank(Rank),
+    }
+
+    /// A borrowed [`ExternalUserValue`].
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ExternalUserValueRef<'a> {
+        Note(&'a Note),
+        Rank(&'a Rank),
+    }
+
+    impl From<ExternalUserValueRef<'_>> for ExternalUserValue {
+        fn from(value: ExternalUserValueRef<'_>) -> Self {
+            match value {
+                ExternalUserValueRef::Note(x) => x.as_any(),
+                ExternalUserValueRef::Rank(x) => x.as_any(),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Note(pub String);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Rank(pub u32);
+
+    #[derive(Debug, Clone, PartialEq, Default)]
+    pub struct ExternalUserValueStorage {
+        notes: UserValueContainer<Note>,
+        ranks: UserValueContainer<Rank>,
+    }
+
+    impl UserValueStorage<ExternalUserValue> for ExternalUserValueStorage {
+        fn insert(&mut self, value: ExternalUserValue, source: Option<SourceId>) {
+            match value {
+                ExternalUserValue::Note(x) => self.notes.push(x, source),
+                ExternalUserValue::Rank(x) => self.ranks.push(x, source),
+            }
+        }
+
+        fn any_value_refs(
+            &self,
+        ) -> impl Iterator<Item = (ExternalUserValueRef<'_>, Option<SourceId>)> + '_ {
+            self.notes
+                .iter()
+                .map(|(x, source)| (ExternalUserValueRef::Note(x), source))
+                .chain(
+                    self.ranks
+                        .iter()
+                        .map(|(x, source)| (ExternalUserValueRef::Rank(x), source)),
+                )
+        }
+    }
+
+    impl AnyUserValue for ExternalUserValue {
+        type Storage = ExternalUserValueStorage;
+        type Ref<'a> = ExternalUserValueRef<'a>;
+
+        fn specify<S: UserValue<Self>>(self) -> Option<S> {
+            S::from_any(self)
+        }
+
+        fn specify_ref<S: UserValue<Self>>(&self) -> Option<&S> {
+            S::from_any_ref(self)
+        }
+    }
+
+    impl UserValue<ExternalUserValue> for Note {
+        fn into_any(self) -> ExternalUserValue {
+            ExternalUserValue::Note(self)
+        }
+
+        fn as_any(&self) -> ExternalUserValue {
+            ExternalUserValue::Note(self.clone())
+        }
+
+        fn from_any(list: ExternalUserValue) -> Option<Self> {
+            match list {
+                ExternalUserValue::Note(x) => Some(x),
+                _ => None,
+            }
+        }
+
+        fn from_any_ref(list: &ExternalUserValue) -> Option<&Self> {
+            match list {
+                ExternalUserValue::Note(x) => Some(x),
+                _ => None,
+            }
+        }
+
+        fn stored(storage: &ExternalUserValueStorage) -> &UserValueContainer<Self> {
+            &storage.notes
+        }
+
+        fn stored_mut(storage: &mut ExternalUserValueStorage) -> &mut UserValueContainer<Self> {
+            &mut storage.notes
+        }
+    }
+
+    impl UserValue<ExternalUserValue> for Rank {
+        fn into_any(self) -> ExternalUserValue {
+            ExternalUserValue::Rank(self)
+        }
+
+        fn as_any(&self) -> ExternalUserValue {
+            ExternalUserValue::Rank(*self)
+        }
+
+        fn from_any(list: ExternalUserValue) -> Option<Self> {
+            match list {
+                ExternalUserValue::Rank(x) => Some(x),
+                _ => None,
+            }
+        }
+
+        fn from_any_ref(list: &ExternalUserValue) -> Option<&Self> {
+            match list {
+                ExternalUserValue::Rank(x) => Some(x),
+                _ => None,
+            }
+        }
+
+        fn stored(storage: &ExternalUserValueStorage) -> &UserValueContainer<Self> {
+            &storage.ranks
+        }
+
+        fn stored_mut(storage: &mut ExternalUserValueStorage) -> &mut UserValueContainer<Self> {
+            &mut storage.ranks
+        }
+    }
+
+    impl Collidable for Rank {
+        fn is_collision(&self, other: &Self) -> bool {
+            self != other
+        }
+    }
+
+    pub fn external_collection() -> UserCollection<ExternalUserValue> {
+        let mut collection = UserCollection::default();
+        collection.add_user_value_iter([
+            Fidded::from((Rank(1), Fid::from(1_u64))),
+            Fidded::from((Rank(2), Fid::from(2_u64))),
+            Fidded::from((Rank(3), Fid::from(3_u64))),
+        ]);
+        collection
+            .add_user_value_iter([Fidded::from((Note("hello".to_string()), Fid::from(2_u64)))]);
+        collection
+    }
+
+    #[test]
+    fn test_lookup_on_external_user_values() {
+        let collection = external_collection();
+        assert_eq!(collection.user_count(), 3);
+        let user = collection.user(2_u64).unwrap();
+        assert_eq!(user.user_values_of_kind::<Rank>().first(), Some(&Rank(2)));
+        assert_eq!(
+            user.user_values_of_kind::<Note>().first(),
+            Some(&Note("hello".to_string()))
+        );
+        assert!(collection.user(4_u64).is_none());
+    }
+
+    #[test]
+    fn test_apply_filter_on_external_user_values() {
+        let mut collection = external_collection();
+        collection.apply_filter(|user| user.has::<Note>());
+        assert_eq!(collection.user_count(), 1);
+        assert!(collection.user(2_u64).is_some());
+    }
+
+    #[test]
+    fn test_remove_user_values_of_kind() {
+        let mut collection = external_collection();
+        assert_eq!(collection.remove_user_values_of_kind::<Rank>(), 3);
+        assert!(collection.iter().all(|user| !user.has::<Rank>()));
+        assert!(collection.user(2_u64).unwrap().has::<Note>());
+        assert_eq!(collection.remove_empty_users(), 2);
+        assert_eq!(collection.user_count(), 1);
+    }
+
+    #[test]
+    fn test_retain_user_values() {
+        let mut collection = external_collection();
+        assert_eq!(collection.retain_user_values(|rank: &Rank| rank.0 >= 2), 1);
+        assert!(collection.user(1_u64).unwrap().is_empty());
+        assert_eq!(
+            collection
+                .user(3_u64)
+                .unwrap()
+                .user_values_of_kind::<Rank>(),
+            [Rank(3)]
+        );
+        assert_eq!(collection.remove_empty_users(), 1);
+    }
+
+    mod sources {
+        use super::*;
+        use crate::core::Source;
+        use crate::core::Sourced;
+        use crate::CollisionPolicy;
+
+        fn ranks_from(
+            collection: &mut UserCollection<ExternalUserValue>,
+            dataset: &str,
+            ranks: &[(u64, u32)],
+        ) -> SourceId {
+            collection.add_user_value_iter_from_source(
+                Source::new(dataset),
+                ranks
+                    .iter()
+                    .map(|(fid, rank)| Fidded::from((Rank(*rank), Fid::from(*fid)))),
+            )
+        }
+
+        #[test]
+        fn test_values_are_tagged_with_their_source() {
+            let mut collection = external_collection();
+            let first = ranks_from(&mut collection, "first", &[(1, 10), (4, 40)]);
+            let second = ranks_from(&mut collection, "second", &[(1, 11)]);
+
+            assert_eq!(collection.source(first).unwrap().dataset(), "first");
+            assert_eq!(collection.sources().count(), 2);
+            assert_eq!(
+                collection
+                    .user_values_from_source::<Rank>(first)
+                    .collect::<Vec<_>>(),
+                [(Fid::from(1_u64), &Rank(10)), (Fid::from(4_u64), &Rank(40))]
+            );
+            let user = collection.user(1_u64).unwrap();
+            assert_eq!(
+                user.sourced_user_values_of_kind::<Rank>()
+                    .collect::<Vec<_>>(),
+                [
+                    (&Rank(1), None),
+                    (&Rank(10), Some(first)),
+                    (&Rank(11), Some(second))
+                ]
+            );
+            assert!(user.has_value_from_source(second));
+            assert!(!collection
+                .user(4_u64)
+                .unwrap()
+                .has_value_from_source(second));
+        }
+
+        #[test]
+        fn test_remove_user_values_from_source() {
+            let mut collection = external_collection();
+            let source = ranks_from(&mut collection, "first", &[(1, 10), (4, 40)]);
+            assert_eq!(collection.remove_user_values_from_source(source), 2);
+            assert!(collection.source(source).is_none());
+            assert_eq!(collection, external_collection_with_empty_user(4));
+        }
+
+        #[test]
+        fn test_add_sourced_user_value() {
+            let source = Source::new("first").id();
+            let mut user = UserStore::<ExternalUserValue>::new(1_u64);
+            user.add_sourced_user_value(Sourced::from((Rank(1), source)));
+            assert_eq!(user.user_values_from_source::<Rank>(source).count(), 1);
+        }
+
+        #[test]
+        fn test_collisions_from_source() {
+            let mut collection = UserCollection::<ExternalUserValue>::default();
+            let values = [Fidded::from((Rank(1), Fid::from(1_u64)))];
+            collection.add_user_value_iter(values);
+            let (source, report) = collection.try_add_user_value_iter_from_source(
+                Source::new("first"),
+                [Fidded::from((Rank(2), Fid::from(1_u64)))],
+                CollisionPolicy::KeepLast,
+            );
+            assert_eq!(report.collisions().len(), 1);
+            assert_eq!(
+                collection
+                    .user_values_from_source::<Rank>(source)
+                    .collect::<Vec<_>>(),
+                [(Fid::from(1_u64), &Rank(2))]
+            );
+        }
+
+        fn external_collection_with_empty_user(fid: u64) -> UserCollection<ExternalUserValue> {
+            let mut collection = external_collection();
+            collection.add_user(UserStore::new(fid)).unwrap();
+            collection
+        }
+    }
+
+    #[test]
+    fn test_add_existing_user_is_err() {
+        let mut collection = external_collection();
+        assert_eq!(
+            collection.add_user(UserStore::new(1_u64)),
+            Err(CollectionError::DuplicateUserError)
+        );
+    }
 }
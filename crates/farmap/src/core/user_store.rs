@@ -1,31 +1,40 @@
 use super::AnyUserValue;
 use super::Collidable;
 use super::Fid;
+use super::HasTag;
+use super::SourceId;
 use super::UserError;
 use super::UserValue;
+use super::UserValueStorage;
 
 /// The main struct for storing user data.
 ///
 /// An instance of UserStore is always related to a collection of potential user values that it can store simultaneously. This is expressed through the AnyUserValue, which connects this Struct with [`UserValue`].
 /// This struct is generic over [`AnyUserValue`], which is that is the main data type that this struct store and what determines which kind [`UserValue`] can be stored. [`AnyUserValue`] is a type that can hold all the types that a UserStore can store.
 ///
+/// The values are kept in the [`UserValueStorage`] of the [`AnyUserValue`], with one container per
+/// kind of [`UserValue`]. Each value can be stored with the [`SourceId`] of the source it came from.
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct UserStore<T: AnyUserValue> {
     fid: Fid,
-    values: Vec<T>,
+    values: T::Storage,
 }
 
 impl<T: AnyUserValue> UserStore<T> {
+    /// Check if a User has at least one value of type S.
     pub fn has<S: UserValue<T>>(&self) -> bool {
-        self.values.iter().any(|x| S::from_any_ref(x).is_some())
+        !S::stored(&self.values).is_empty()
     }
 
+    /// Add a [`UserValue`] to a [`UserStore`]. Returns an error in case of Collision. This method
+    /// relies on Ss implementation of Collidable to determine collisions.
     pub fn try_add_user_value<S: UserValue<T> + Collidable>(
         &mut self,
         new: S,
     ) -> Result<(), UserError> {
         if self
             .user_values_of_kind::<S>()
+            .iter()
             .all(|x| !Collidable::is_collision(x, &new))
         {
             self.add_user_value(new);
@@ -35,29 +44,157 @@ impl<T: AnyUserValue> UserStore<T> {
         }
     }
 
+    /// Remove and return all the stored values of kind S that collide with `new`.
+    pub(crate) fn take_collisions<S: UserValue<T> + Collidable>(&mut self, new: &S) -> Vec<S> {
+        self.take_user_values_where(|x: &S| Collidable::is_collision(x, new))
+    }
+
+    /// Remove and return all the stored values of kind S for which the predicate is true. Values
+    /// of other kinds are left untouched.
+    pub(crate) fn take_user_values_where<S: UserValue<T>>(
+        &mut self,
+        predicate: impl Fn(&S) -> bool,
+    ) -> Vec<S> {
+        self.take_sourced_user_values_where(|x: &S, _| predicate(x))
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect()
+    }
+
+    /// Remove and return all the stored values of kind S, along with their sources, for which the
+    /// predicate is true.
+    pub(crate) fn take_sourced_user_values_where<S: UserValue<T>>(
+        &mut self,
+        predicate: impl FnMut(&S, Option<SourceId>) -> bool,
+    ) -> Vec<(S, Option<SourceId>)> {
+        S::stored_mut(&mut self.values).take_where(predicate)
+    }
+
+    /// Remove and return all the [`UserValue`]s of type S.
+    pub fn remove_user_values_of_kind<S: UserValue<T>>(&mut self) -> Vec<S> {
+        std::mem::take(S::stored_mut(&mut self.values))
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect()
+    }
+
+    /// Keep only the [`UserValue`]s of type S for which the predicate returns true. Values of
+    /// other kinds are left untouched. Returns the number of removed values.
+    pub fn retain_user_values<S: UserValue<T>>(
+        &mut self,
+        mut predicate: impl FnMut(&S) -> bool,
+    ) -> usize {
+        S::stored_mut(&mut self.values).retain(|x, _| predicate(x))
+    }
+
+    /// Remove every value of every kind that came from the source. Returns the number of removed
+    /// values.
+    pub fn remove_user_values_from_source(&mut self, source: SourceId) -> usize {
+        let values = std::mem::take(&mut self.values);
+        let mut removed = 0;
+        for (value, value_source) in values.any_values() {
+            if value_source == Some(source) {
+                removed += 1;
+            } else {
+                self.values.insert(value, value_source);
+            }
+        }
+        removed
+    }
+
+    /// Returns true if the user has no values of any kind.
+    pub fn is_empty(&self) -> bool {
+        self.values.any_value_refs().next().is_none()
+    }
+
+    /// Insert a new [`UserValue`]. This method does not check for collisions.
     pub fn add_user_value<S: UserValue<T>>(&mut self, new: S) {
-        self.values.push(new.into_any());
+        self.add_user_value_with_source(new, None);
     }
 
-    pub fn user_values_of_kind<'a, S: UserValue<T> + 'a>(&'a self) -> impl Iterator<Item = &'a S> {
-        self.values.iter().flat_map(|x| x.specify_ref::<S>())
+    /// Insert a new [`UserValue`] that is tagged with its source, such as a
+    /// [`Sourced`](super::Sourced) value. This method does not check for collisions.
+    pub fn add_sourced_user_value<S: UserValue<T>>(&mut self, new: impl HasTag<SourceId, S>) {
+        let (source, new) = new.untag();
+        self.add_user_value_with_source(new, Some(source));
+    }
+
+    pub(crate) fn add_user_value_with_source<S: UserValue<T>>(
+        &mut self,
+        new: S,
+        source: Option<SourceId>,
+    ) {
+        self.values.insert(new.into_any(), source);
+    }
+
+    /// Insert a value of any kind. This method does not check for collisions.
+    pub(crate) fn add_any_user_value(&mut self, new: T, source: Option<SourceId>) {
+        self.values.insert(new, source);
+    }
+
+    /// Get all the [`UserValue`]s of type S, in the order given by the [`UserValueStorage`]. If
+    /// there are no such values, the slice is empty.
+    pub fn user_values_of_kind<S: UserValue<T>>(&self) -> &[S] {
+        S::stored(&self.values).values()
+    }
+
+    /// Mutable access to the [`UserValue`]s of type S. A change must keep the order of the
+    /// [`UserValueStorage`].
+    pub(crate) fn user_values_of_kind_mut<S: UserValue<T>>(&mut self) -> &mut [S] {
+        S::stored_mut(&mut self.values).values_mut()
+    }
+
+    /// Get all the [`UserValue`]s of type S along with the id of the source they came from.
+    pub fn sourced_user_values_of_kind<'a, S: UserValue<T> + 'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (&'a S, Option<SourceId>)> {
+        S::stored(&self.values).iter()
+    }
+
+    /// Get the [`UserValue`]s of type S that came from the source.
+    pub fn user_values_from_source<'a, S: UserValue<T> + 'a>(
+        &'a self,
+        source: SourceId,
+    ) -> impl Iterator<Item = &'a S> {
+        self.sourced_user_values_of_kind::<S>()
+            .filter(move |(_, value_source)| *value_source == Some(source))
+            .map(|(value, _)| value)
+    }
+
+    /// Check if the user has at least one value of any kind from the source.
+    pub fn has_value_from_source(&self, source: SourceId) -> bool {
+        self.values
+            .any_value_refs()
+            .any(|(_, value_source)| value_source == Some(source))
+    }
+
+    pub fn new(fid: impl Into<Fid>) -> Self {
+        Self::from(fid.into())
     }
 
     pub(crate) fn from_generic_user_values(
         fid: impl Into<Fid>,
-        values: impl IntoIterator<Item = T>,
+        values: impl IntoIterator<Item = (T, Option<SourceId>)>,
     ) -> Self {
-        let fid = fid.into();
-        let values: Vec<T> = values.into_iter().collect();
-        Self { fid, values }
+        let mut user = Self::new(fid);
+        for (value, source) in values {
+            user.values.insert(value, source);
+        }
+        user
     }
 
     pub fn fid(&self) -> Fid {
         self.fid
     }
 
-    pub fn all_user_values(&self) -> impl Iterator<Item = &T> {
-        self.values.iter()
+    /// All the user values, grouped by kind.
+    pub fn all_user_values(&self) -> impl Iterator<Item = T::Ref<'_>> {
+        self.values.any_value_refs().map(|(value, _)| value)
+    }
+
+    /// All the user values along with the ids of their sources, grouped by kind.
+    pub fn all_sourced_user_values(&self) -> impl Iterator<Item = (T::Ref<'_>, Option<SourceId>)> {
+        self.values.any_value_refs()
     }
 }
 
@@ -65,7 +202,7 @@ impl<T: AnyUserValue> From<Fid> for UserStore<T> {
     fn from(value: Fid) -> Self {
         Self {
             fid: value,
-            values: Vec::new(),
+            values: T::Storage::default(),
         }
     }
 }
@@ -1,4 +1,5 @@
 use super::any_user_value::AnyUserValue;
+use super::UserValueContainer;
 
 /// A representation of a data point related to a user.
 ///
@@ -18,4 +19,10 @@ pub trait UserValue<T: AnyUserValue>: Sized {
     fn as_any(&self) -> T;
     fn from_any(list: T) -> Option<Self>;
     fn from_any_ref(list: &T) -> Option<&Self>;
+
+    /// The container of this kind of value in the storage of T.
+    fn stored(storage: &T::Storage) -> &UserValueContainer<Self>;
+
+    /// The mutable container of this kind of value in the storage of T.
+    fn stored_mut(storage: &mut T::Storage) -> &mut UserValueContainer<Self>;
 }
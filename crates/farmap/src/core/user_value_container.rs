@@ -0,0 +1,85 @@
+use super::SourceId;
+
+/// The container of one kind of [`UserValue`](super::UserValue) in a
+/// [`UserValueStorage`](super::UserValueStorage).
+///
+/// The container stores the [`SourceId`] of every value next to the value, and keeps the two in
+/// sync when values are inserted or removed. Values without a known source have no id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserValueContainer<S> {
+    values: Vec<S>,
+    sources: Vec<Option<SourceId>>,
+}
+
+impl<S> Default for UserValueContainer<S> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl<S> UserValueContainer<S> {
+    pub fn values(&self) -> &[S] {
+        &self.values
+    }
+
+    /// The values can be changed in place, but a change must keep the order that the
+    /// [`UserValueStorage`](super::UserValueStorage) relies on.
+    pub(crate) fn values_mut(&mut self) -> &mut [S] {
+        &mut self.values
+    }
+
+    /// The values along with the id of the source each value came from.
+    pub fn iter(&self) -> impl Iterator<Item = (&S, Option<SourceId>)> {
+        self.values.iter().zip(self.sources.iter().copied())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn push(&mut self, value: S, source: Option<SourceId>) {
+        self.values.push(value);
+        self.sources.push(source);
+    }
+
+    /// Insert a value at the index, shifting all values after it.
+    pub fn insert(&mut self, index: usize, value: S, source: Option<SourceId>) {
+        self.values.insert(index, value);
+        self.sources.insert(index, source);
+    }
+
+    /// Keep only the values for which the predicate returns true. Returns the number of removed
+    /// values.
+    pub fn retain(&mut self, mut predicate: impl FnMut(&S, Option<SourceId>) -> bool) -> usize {
+        self.take_where(|value, source| !predicate(value, source))
+            .len()
+    }
+
+    /// Remove and return the values for which the predicate returns true.
+    pub fn take_where(
+        &mut self,
+        mut predicate: impl FnMut(&S, Option<SourceId>) -> bool,
+    ) -> Vec<(S, Option<SourceId>)> {
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(self)
+            .into_iter()
+            .partition(|(value, source)| predicate(value, *source));
+        (self.values, self.sources) = kept.into_iter().unzip();
+        taken
+    }
+}
+
+impl<S> IntoIterator for UserValueContainer<S> {
+    type Item = (S, Option<SourceId>);
+    type IntoIter = std::iter::Zip<std::vec::IntoIter<S>, std::vec::IntoIter<Option<SourceId>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter().zip(self.sources)
+    }
+}
@@ -0,0 +1,25 @@
+use super::AnyUserValue;
+use super::SourceId;
+use std::fmt::Debug;
+
+/// The containers a [`UserStore`](super::UserStore) keeps its values in.
+///
+/// A storage holds one [`UserValueContainer`](super::UserValueContainer) per kind of
+/// [`UserValue`](super::UserValue) that the [`AnyUserValue`] can hold. Each
+/// [`UserValue`](super::UserValue) borrows its own container from the storage, which means that
+/// values of one kind can be read without looking at values of other kinds. The storage decides
+/// the order of the values in each container.
+pub trait UserValueStorage<T: AnyUserValue>: Default + Debug + Clone + PartialEq {
+    /// Store a value and the id of its source in the container of its kind.
+    fn insert(&mut self, value: T, source: Option<SourceId>);
+
+    /// All the stored values along with the ids of their sources, grouped by kind. Inserting the
+    /// values in this order into an empty storage recreates the storage.
+    fn any_value_refs(&self) -> impl Iterator<Item = (T::Ref<'_>, Option<SourceId>)> + '_;
+
+    /// Clones of the values of [`Self::any_value_refs`].
+    fn any_values(&self) -> impl Iterator<Item = (T, Option<SourceId>)> + '_ {
+        self.any_value_refs()
+            .map(|(value, source)| (value.into(), source))
+    }
+}
@@ -0,0 +1,201 @@
+use crate::core::UserValueContainer;
+use crate::native_user_value::AnyNativeUserValue;
+use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
+use crate::NativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+/// A value type defined outside of the crate that can be stored as a [`CustomUserValue`].
+///
+/// The type tag registers the type. It is stored with every value and must be unique among the
+/// custom value types that are stored in the same collection.
+pub trait CustomValue: Serialize + DeserializeOwned {
+    const TYPE_TAG: &'static str;
+}
+
+/// A [`NativeUserValue`] that holds a value of a [`CustomValue`] type as a type tag and a
+/// serialized payload. Custom values are serialized with the rest of the collection and can be
+/// queried with [`user_values_of_kind`](crate::UserStore::user_values_of_kind) or, typed, with
+/// [`UserStoreWithNativeUserValue::custom_values_of_kind`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct CustomUserValue {
+    type_tag: String,
+    payload: String,
+}
+
+#[derive(Error, Debug)]
+pub enum CustomValueError {
+    #[error("expected a custom value with type tag {expected}, found {found}")]
+    WrongType {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("could not serialize or deserialize the custom value payload")]
+    Payload(#[from] serde_json::Error),
+}
+
+impl CustomUserValue {
+    pub fn new<C: CustomValue>(value: &C) -> Result<Self, CustomValueError> {
+        Ok(Self {
+            type_tag: C::TYPE_TAG.to_string(),
+            payload: serde_json::to_string(value)?,
+        })
+    }
+
+    pub fn type_tag(&self) -> &str {
+        &self.type_tag
+    }
+
+    /// The value serialized as json.
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+
+    /// Returns true if the value has the type tag of C.
+    pub fn is<C: CustomValue>(&self) -> bool {
+        self.type_tag == C::TYPE_TAG
+    }
+
+    pub fn decode<C: CustomValue>(&self) -> Result<C, CustomValueError> {
+        if !self.is::<C>() {
+            return Err(CustomValueError::WrongType {
+                expected: C::TYPE_TAG,
+                found: self.type_tag.clone(),
+            });
+        }
+        Ok(serde_json::from_str(&self.payload)?)
+    }
+}
+
+impl NativeUserValueSeal for CustomUserValue {}
+
+impl NativeUserValue for CustomUserValue {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::Custom(self.clone())
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::Custom(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::Custom(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::Custom(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.custom_values
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.custom_values
+    }
+}
+
+impl UserStoreWithNativeUserValue {
+    /// Serialize the value and add it as a [`CustomUserValue`].
+    pub fn add_custom_value<C: CustomValue>(&mut self, value: &C) -> Result<(), CustomValueError> {
+        self.add_user_value(CustomUserValue::new(value)?);
+        Ok(())
+    }
+
+    /// All the custom values with the type tag of C, in insertion order. Returns an error if a
+    /// payload cannot be deserialized as C.
+    pub fn custom_values_of_kind<C: CustomValue>(&self) -> Result<Vec<C>, CustomValueError> {
+        self.user_values_of_kind::<CustomUserValue>()
+            .iter()
+            .filter(|value| value.is::<C>())
+            .map(CustomUserValue::decode)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fid;
+    use crate::Fidded;
+    use crate::UserCollectionWithNativeUserValue;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct ModerationNote {
+        note: String,
+    }
+
+    impl CustomValue for ModerationNote {
+        const TYPE_TAG: &'static str = "moderation_note";
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct ClassifierScore(f64);
+
+    impl CustomValue for ClassifierScore {
+        const TYPE_TAG: &'static str = "classifier_score";
+    }
+
+    fn note(note: &str) -> ModerationNote {
+        ModerationNote {
+            note: note.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_custom_values_are_queryable_by_kind() {
+        let mut user = UserStoreWithNativeUserValue::new(1_u64);
+        user.add_custom_value(&note("first")).unwrap();
+        user.add_custom_value(&ClassifierScore(0.25)).unwrap();
+        user.add_custom_value(&note("second")).unwrap();
+
+        assert_eq!(user.user_values_of_kind::<CustomUserValue>().len(), 3);
+        assert_eq!(
+            user.custom_values_of_kind::<ModerationNote>().unwrap(),
+            [note("first"), note("second")]
+        );
+        assert_eq!(
+            user.custom_values_of_kind::<ClassifierScore>().unwrap(),
+            [ClassifierScore(0.25)]
+        );
+    }
+
+    #[test]
+    fn test_decode_with_wrong_type_is_err() {
+        let value = CustomUserValue::new(&ClassifierScore(1.0)).unwrap();
+        assert!(matches!(
+            value.decode::<ModerationNote>(),
+            Err(CustomValueError::WrongType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_round_trip_through_collection_serde() {
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter([Fidded::from((
+            CustomUserValue::new(&note("spam ring")).unwrap(),
+            Fid::from(1_u64),
+        ))]);
+        let json = serde_json::to_string(&collection).unwrap();
+        let deserialized: UserCollectionWithNativeUserValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, collection);
+        assert_eq!(
+            deserialized
+                .user(1_u64)
+                .unwrap()
+                .custom_values_of_kind::<ModerationNote>()
+                .unwrap(),
+            [note("spam ring")]
+        );
+    }
+}
@@ -1,7 +1,11 @@
 //! create a dated version of any type.
 use std::ops::{Deref, DerefMut};
 
+use crate::core::UserValueCollision;
+use chrono::DateTime;
 use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
 
 #[derive(serde::Serialize, serde::Deserialize, PartialEq, Hash, Eq)]
 #[serde(bound(
@@ -12,6 +16,9 @@ pub struct Dated<T> {
     #[serde(flatten)]
     inner: T,
     date: NaiveDate,
+    /// The time of the value when it is known. The date is then the date of the timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<DateTime<Utc>>,
 }
 
 impl<T> Dated<T> {
@@ -19,6 +26,26 @@ impl<T> Dated<T> {
         self.date
     }
 
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.timestamp
+    }
+
+    /// The timestamp of the value, or the start of the date if the time is not known. Stored values
+    /// are ordered by this time, so values with a timestamp have a well defined order within a day.
+    pub fn date_time(&self) -> DateTime<Utc> {
+        self.timestamp
+            .unwrap_or_else(|| self.date.and_time(NaiveTime::MIN).and_utc())
+    }
+
+    /// Returns true if the values have the same timestamp, or the same date if either value has no
+    /// timestamp.
+    pub fn is_same_time<S>(&self, other: &Dated<S>) -> bool {
+        match (self.timestamp, other.timestamp) {
+            (Some(timestamp), Some(other_timestamp)) => timestamp == other_timestamp,
+            _ => self.date == other.date,
+        }
+    }
+
     pub fn into_inner(self) -> T {
         self.inner
     }
@@ -32,16 +59,44 @@ impl<T> Dated<T> {
         T: From<S>,
     {
         let inner = T::from(arg);
-        Self { inner, date }
+        Self {
+            inner,
+            date,
+            timestamp: None,
+        }
+    }
+
+    /// Like [`Dated::from`], but with the full timestamp of the value.
+    pub fn from_timestamp<S>(timestamp: DateTime<Utc>, arg: S) -> Self
+    where
+        T: From<S>,
+    {
+        Self {
+            inner: T::from(arg),
+            date: timestamp.date_naive(),
+            timestamp: Some(timestamp),
+        }
+    }
+
+    /// Maps the value by reference, keeping the date and the timestamp.
+    pub fn map_ref<S>(&self, f: impl FnOnce(&T) -> S) -> Dated<S> {
+        Dated::<S> {
+            inner: f(&self.inner),
+            date: self.date,
+            timestamp: self.timestamp,
+        }
     }
 
     pub fn map_into<S>(self) -> Dated<S>
     where
         S: From<T>,
     {
-        let date = self.date();
         let inner = S::from(self.inner);
-        Dated::<S> { inner, date }
+        Dated::<S> {
+            inner,
+            date: self.date,
+            timestamp: self.timestamp,
+        }
     }
 }
 
@@ -50,6 +105,7 @@ impl<T> From<(T, NaiveDate)> for Dated<T> {
         Self {
             inner: value.0,
             date: value.1,
+            timestamp: None,
         }
     }
 }
@@ -61,6 +117,7 @@ impl<T: Clone> Clone for Dated<T> {
         Self {
             inner: self.inner.clone(),
             date: self.date,
+            timestamp: self.timestamp,
         }
     }
 }
@@ -70,6 +127,7 @@ impl<T: std::fmt::Debug> std::fmt::Debug for Dated<T> {
         f.debug_struct("Dated")
             .field("inner", &self.inner)
             .field("date", &self.date)
+            .field("timestamp", &self.timestamp)
             .finish()
     }
 }
@@ -79,6 +137,7 @@ impl<T: Default> Dated<T> {
         Self {
             inner: T::default(),
             date,
+            timestamp: None,
         }
     }
 
@@ -86,10 +145,10 @@ impl<T: Default> Dated<T> {
     where
         S: TryFrom<T>,
     {
-        let new_date = self.date();
         let new_inner: S = self.inner.try_into()?;
         Ok(Dated::<S> {
-            date: new_date,
+            date: self.date,
+            timestamp: self.timestamp,
             inner: new_inner,
         })
     }
@@ -108,3 +167,10 @@ impl<T> DerefMut for Dated<T> {
         &mut self.inner
     }
 }
+
+impl<T> UserValueCollision<Dated<T>> {
+    /// The date of the colliding values.
+    pub fn date(&self) -> NaiveDate {
+        self.incoming().date()
+    }
+}
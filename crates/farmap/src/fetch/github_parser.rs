@@ -1,3 +1,4 @@
+use crate::spam_score::CommitHash;
 use crate::spam_score::DatedSpamUpdate;
 use crate::Fidded;
 use crate::UnprocessedUserLine;
@@ -46,6 +47,17 @@ pub fn into_fidded_user_value_iter(
         .map(|x| Fidded::<DatedSpamUpdate>::try_from(x).unwrap())
 }
 
+/// Like [`into_fidded_user_value_iter`], but every update records the commit the lines were
+/// imported from.
+pub fn into_fidded_user_value_iter_from_commit(
+    previous_iter: impl IntoIterator<Item = UnprocessedUserLine>,
+    commit: CommitHash,
+) -> impl Iterator<Item = Fidded<DatedSpamUpdate>> {
+    previous_iter
+        .into_iter()
+        .flat_map(move |x| x.try_into_spam_update_from_commit(commit))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -64,4 +76,17 @@ pub mod tests {
         let spam_data = fidded_user_values_from_raw_spam_data_file();
         assert_eq!(3, spam_data.count())
     }
+
+    #[test]
+    fn test_updates_from_commit_record_the_commit() {
+        let commit = CommitHash::try_from("ab".repeat(20)).unwrap();
+        let body = read_to_string("data/dummy-data/spam.jsonl").unwrap();
+        let updates =
+            into_fidded_user_value_iter_from_commit(parse_commit_hash_body(&body).0, commit)
+                .collect::<Vec<_>>();
+        assert_eq!(updates.len(), 3);
+        assert!(updates
+            .iter()
+            .all(|x| x.unfid().source_commit() == Some(commit)));
+    }
 }
@@ -1,4 +1,7 @@
 use super::github_parser;
+use crate::spam_score::CommitHash;
+use crate::spam_score::DatedSpamUpdate;
+use crate::Fidded;
 use crate::UnprocessedUserLine;
 use log::{error, info, trace};
 use reqwest::header::HeaderMap;
@@ -100,6 +103,21 @@ impl GithubFetcher {
         Ok(github_parser::parse_commit_hash_body(&body))
     }
 
+    /// Fetch the spam updates of the commit. Every update records the commit. Lines that cannot be
+    /// parsed are returned as errors like in [`Self::fetch`], lines with an invalid label or
+    /// timestamp are skipped.
+    pub async fn fetch_spam_updates(
+        &self,
+        commit_hash: &str,
+    ) -> Result<(Vec<Fidded<DatedSpamUpdate>>, Vec<ImporterError>), ImporterError> {
+        let commit = CommitHash::try_from(commit_hash)
+            .map_err(|_| ImporterError::BadApiResponse(commit_hash.to_string()))?;
+        let (lines, errors) = self.fetch(commit_hash).await?;
+        let updates =
+            github_parser::into_fidded_user_value_iter_from_commit(lines, commit).collect();
+        Ok((updates, errors))
+    }
+
     fn build_path(&self, status: &str) -> Result<Url, ConversionError> {
         let url_string = format!("{}{}/spam.jsonl", self.base_url, status);
         let url = Url::parse(&url_string).map_err(|_| ConversionError::ConversionError)?;
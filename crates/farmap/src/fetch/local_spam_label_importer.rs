@@ -19,6 +19,7 @@ pub fn import_data_from_file(
     import_result
         .into_iter()
         .flatten()
+        .filter(UnprocessedUserLine::is_warpcast_spam)
         .map(TryInto::<Fidded<DatedSpamUpdate>>::try_into)
         .try_collect()
         .map_err(|_| RetrieveError::InvalidFetchedData)
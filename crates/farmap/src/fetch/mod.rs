@@ -22,3 +22,4 @@ pub use import::ImporterError;
 pub use pinata_importer::PinataFetcher;
 pub use retrieve_error::RetrieveError;
 pub use wield_importer::WieldFetcher;
+pub use wield_importer::WieldFollowers;
@@ -1,9 +1,21 @@
-use super::pinata_parser::followers_from_pinata_response;
+use super::pinata_parser::casts_from_pinata_response;
+use super::pinata_parser::followers_from_json;
+use super::pinata_parser::following_from_json;
+use super::pinata_parser::next_page_token;
+use super::pinata_parser::profile_from_pinata_response;
+use super::pinata_parser::raw_json_from_response;
 use super::pinata_parser::reaction_times_from_response;
+use super::pinata_parser::reactions_from_response;
+use super::pinata_parser::ParsedCasts;
 use super::ImporterError;
+use crate::Dated;
+use crate::Profile;
+use crate::Reaction;
+use crate::ReactionType;
 use chrono::NaiveDateTime;
 use log::trace;
 use reqwest::{Client, Response};
+use serde_json::Value;
 use url::Url;
 
 pub struct PinataFetcher {
@@ -29,10 +41,53 @@ impl PinataFetcher {
         }
     }
 
+    /// Fetch the followers of the fid from every page of the linksByTargetFid endpoint.
     pub async fn fetch_followers_for_fid(&self, fid: u64) -> Result<Vec<u64>, ImporterError> {
-        let api_response = self.link_request_for_fid(fid).await?;
-        trace!("api response: {api_response:?}");
-        followers_from_pinata_response(api_response).await
+        let query = format!("link_type=follow&target_fid={fid}");
+        let mut followers = Vec::new();
+        for page in self.all_pages("linksByTargetFid", &query).await? {
+            followers.append(&mut followers_from_json(&page)?);
+        }
+        Ok(followers)
+    }
+
+    /// Fetch the fids that the fid follows from every page of the linksByFid endpoint.
+    pub async fn fetch_following_for_fid(&self, fid: u64) -> Result<Vec<u64>, ImporterError> {
+        let query = format!("link_type=follow&fid={fid}");
+        let mut following = Vec::new();
+        for page in self.all_pages("linksByFid", &query).await? {
+            following.append(&mut following_from_json(&page)?);
+        }
+        Ok(following)
+    }
+
+    /// Request every page of a paginated endpoint, following the nextPageToken of each page.
+    async fn all_pages(&self, extension: &str, query: &str) -> Result<Vec<Value>, ImporterError> {
+        let mut pages = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = self.base_url.clone().join(extension).unwrap();
+            url.set_query(Some(query));
+            if let Some(page_token) = &page_token {
+                url.query_pairs_mut().append_pair("pageToken", page_token);
+            }
+            trace!("url: {url}");
+            let response = self
+                .client
+                .get(url)
+                .send()
+                .await
+                .map_err(|_| ImporterError::FailedApiRequest)?;
+            let page = raw_json_from_response(response).await?;
+            let next_page_token = next_page_token(&page).map(str::to_string);
+            pages.push(page);
+
+            // a token that does not change would request the same page forever.
+            if next_page_token.is_none() || next_page_token == page_token {
+                return Ok(pages);
+            }
+            page_token = next_page_token;
+        }
     }
 
     // fetches reaction times (i.e. a collection of times when a user has either recasted or
@@ -49,6 +104,44 @@ impl PinataFetcher {
         Ok(reaction_times)
     }
 
+    /// Fetch the profile of the fid from its user data. Returns None if the fid has no user data.
+    pub async fn fetch_profile_for_fid(
+        &self,
+        fid: u64,
+    ) -> Result<Option<Dated<Profile>>, ImporterError> {
+        let api_response = self.user_data_by_fid(fid).await?;
+        profile_from_pinata_response(api_response).await
+    }
+
+    pub async fn user_data_by_fid(&self, fid: u64) -> Result<Response, ImporterError> {
+        let extension = "userDataByFid";
+        let mut url = self.base_url.clone().join(extension).unwrap();
+        url.set_query(Some(&format!("fid={fid}")));
+        self.client
+            .get(url)
+            .send()
+            .await
+            .map_err(|_| ImporterError::FailedApiRequest)
+    }
+
+    /// Fetch the likes and recasts of the fid, sorted by date.
+    pub async fn fetch_reactions_for_fid(
+        &self,
+        fid: u64,
+    ) -> Result<Vec<Dated<Reaction>>, ImporterError> {
+        let mut reactions = reactions_from_response(self.likes_by_fid(fid).await?).await?;
+        reactions.append(&mut reactions_from_response(self.recasts_by_fid(fid).await?).await?);
+        reactions.sort_by_key(|x| x.date());
+        Ok(reactions)
+    }
+
+    /// Fetch the cast messages of the fid. Messages that are not supported are recorded in the result
+    /// instead of failing the fetch.
+    pub async fn fetch_casts_for_fid(&self, fid: u64) -> Result<ParsedCasts, ImporterError> {
+        let api_response = self.casts_by_fid(fid).await?;
+        casts_from_pinata_response(api_response).await
+    }
+
     pub async fn casts_by_fid(&self, id: u64) -> Result<Response, ImporterError> {
         let extension = "castsByFid";
         let mut url = self.base_url.clone().join(extension).unwrap();
@@ -75,11 +168,11 @@ impl PinataFetcher {
     async fn reactions_by_fid(
         &self,
         fid: u64,
-        reaction: Reaction,
+        reaction: ReactionType,
     ) -> Result<Response, ImporterError> {
         let reaction_str = match reaction {
-            Reaction::Like => "Like",
-            Reaction::Recast => "Recast",
+            ReactionType::Like => "Like",
+            ReactionType::Recast => "Recast",
         };
 
         let extension = "reactionsByFid";
@@ -93,15 +186,10 @@ impl PinataFetcher {
     }
 
     pub async fn likes_by_fid(&self, fid: u64) -> Result<Response, ImporterError> {
-        self.reactions_by_fid(fid, Reaction::Like).await
+        self.reactions_by_fid(fid, ReactionType::Like).await
     }
 
     pub async fn recasts_by_fid(&self, fid: u64) -> Result<Response, ImporterError> {
-        self.reactions_by_fid(fid, Reaction::Recast).await
+        self.reactions_by_fid(fid, ReactionType::Recast).await
     }
 }
-
-enum Reaction {
-    Like,
-    Recast,
-}
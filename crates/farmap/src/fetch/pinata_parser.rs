@@ -1,8 +1,16 @@
 use super::ImporterError;
 use crate::dated::Dated;
 use crate::fidded::Fidded;
+use crate::Cast;
+use crate::CastId;
+use crate::CastParent;
+use crate::CastRemove;
 use crate::CastType;
+use crate::Embed;
 use crate::Fid;
+use crate::Profile;
+use crate::Reaction;
+use crate::ReactionType;
 use chrono::Duration;
 use chrono::NaiveDate;
 use chrono::NaiveDateTime;
@@ -10,7 +18,7 @@ use chrono::NaiveTime;
 use reqwest::Response;
 use serde_json::Value;
 
-async fn raw_json_from_response(response: Response) -> Result<Value, ImporterError> {
+pub(crate) async fn raw_json_from_response(response: Response) -> Result<Value, ImporterError> {
     if !response.status().is_success() {
         return Err(ImporterError::FailedApiRequest);
     };
@@ -55,13 +63,115 @@ fn fid_from_object(input: &Value) -> Result<u64, ImporterError> {
         .and_then(|x| x.as_u64().ok_or(ImporterError::FailedApiRequest))
 }
 
-fn type_from_object(input: &Value) -> Result<CastType, ImporterError> {
-    let cast_type: CastType = input["data"]["castAddBody"]["type"]
-        .as_str()
-        .ok_or(ImporterError::BadApiResponse(input.to_string().clone()))?
-        .try_into()
-        .map_err(|_| ImporterError::BadApiResponse(input.to_string().clone()))?;
-    Ok(cast_type)
+fn type_from_object(input: &Value) -> Result<CastType, UnsupportedMessage> {
+    let cast_type = input["data"]["castAddBody"]["type"].as_str();
+    cast_type
+        .and_then(|x| CastType::try_from(x).ok())
+        .ok_or_else(|| UnsupportedMessage {
+            hash: hash_from_object(input),
+            message_type: "MESSAGE_TYPE_CAST_ADD".to_string(),
+            cast_type: cast_type.map(str::to_string),
+        })
+}
+
+fn hash_from_object(input: &Value) -> Option<String> {
+    input["hash"].as_str().map(str::to_string)
+}
+
+fn cast_id_from_object(input: &Value) -> Option<CastId> {
+    Some(CastId::new(input["fid"].as_u64()?, input["hash"].as_str()?))
+}
+
+fn cast_from_object(input: &Value) -> Result<Cast, UnsupportedMessage> {
+    let body = &input["data"]["castAddBody"];
+    let mut cast = Cast::new(type_from_object(input)?);
+    if let Some(hash) = hash_from_object(input) {
+        cast = cast.with_hash(hash);
+    }
+    if let Some(parent) = cast_id_from_object(&body["parentCastId"]) {
+        cast = cast.with_parent(CastParent::Cast(parent));
+    } else if let Some(url) = body["parentUrl"].as_str() {
+        cast = cast.with_parent(CastParent::Url(url.to_string()));
+    }
+    let mentions = body["mentions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(Value::as_u64);
+    let embeds = body["embeds"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|embed| match embed["url"].as_str() {
+            Some(url) => Some(Embed::Url(url.to_string())),
+            None => cast_id_from_object(&embed["castId"]).map(Embed::Cast),
+        });
+    Ok(cast.with_mentions(mentions).with_embeds(embeds))
+}
+
+/// A message in a castsByFid response that could not be parsed into a [`Cast`] or a
+/// [`CastRemove`], for instance a cast type that is newer than this crate or a message without a
+/// timestamp.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsupportedMessage {
+    pub hash: Option<String>,
+    pub message_type: String,
+    /// The cast type of a cast add message.
+    pub cast_type: Option<String>,
+}
+
+/// The messages in a castsByFid response. Messages that are not supported are recorded instead of
+/// failing the parse.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedCasts {
+    pub casts: Vec<Fidded<Dated<Cast>>>,
+    pub removes: Vec<Fidded<Dated<CastRemove>>>,
+    pub unsupported: Vec<UnsupportedMessage>,
+}
+
+pub async fn casts_from_pinata_response(response: Response) -> Result<ParsedCasts, ImporterError> {
+    let json = raw_json_from_response(response).await?;
+    casts_from_json(&json)
+}
+
+fn casts_from_json(json: &Value) -> Result<ParsedCasts, ImporterError> {
+    let json_vec = json["messages"]
+        .as_array()
+        .ok_or(ImporterError::BadApiResponse(json.to_string()))?;
+
+    let mut parsed = ParsedCasts::default();
+    for message in json_vec {
+        let message_type = message["data"]["type"].as_str();
+        let unsupported = || UnsupportedMessage {
+            hash: hash_from_object(message),
+            message_type: message_type.unwrap_or_default().to_string(),
+            cast_type: None,
+        };
+        let (Ok(time), Ok(fid)) = (date_time_from_object(message), fid_from_object(message)) else {
+            parsed.unsupported.push(unsupported());
+            continue;
+        };
+        let (time, fid) = (time.and_utc(), Fid::from(fid));
+        match message_type {
+            Some("MESSAGE_TYPE_CAST_ADD") => match cast_from_object(message) {
+                Ok(cast) => parsed
+                    .casts
+                    .push((Dated::from_timestamp(time, cast), fid).into()),
+                Err(unsupported) => parsed.unsupported.push(unsupported),
+            },
+            Some("MESSAGE_TYPE_CAST_REMOVE") => {
+                match message["data"]["castRemoveBody"]["targetHash"].as_str() {
+                    Some(target_hash) => {
+                        let remove = Dated::from_timestamp(time, CastRemove::new(target_hash));
+                        parsed.removes.push((remove, fid).into());
+                    }
+                    None => parsed.unsupported.push(unsupported()),
+                }
+            }
+            _ => parsed.unsupported.push(unsupported()),
+        }
+    }
+    Ok(parsed)
 }
 
 pub async fn number_of_casts_from_response(response: Response) -> Result<u64, ImporterError> {
@@ -76,31 +186,54 @@ pub async fn reaction_times_from_response(
     json_vec.iter().map(date_time_from_object).collect()
 }
 
+/// The likes and recasts in a reactionsByFid response. The target cast is kept when the reaction
+/// targets a cast id.
+pub async fn reactions_from_response(
+    response: Response,
+) -> Result<Vec<Dated<Reaction>>, ImporterError> {
+    let json = raw_json_from_response(response).await?;
+    let json_vec = json["messages"]
+        .as_array()
+        .ok_or(ImporterError::BadApiResponse(json.to_string()))?;
+    json_vec.iter().map(reaction_from_object).collect()
+}
+
+fn reaction_from_object(input: &Value) -> Result<Dated<Reaction>, ImporterError> {
+    let body = &input["data"]["reactionBody"];
+    let reaction_type = match body["type"].as_str() {
+        Some("REACTION_TYPE_LIKE") => ReactionType::Like,
+        Some("REACTION_TYPE_RECAST") => ReactionType::Recast,
+        _ => return Err(ImporterError::BadApiResponse(input.to_string())),
+    };
+    let target = &body["targetCastId"];
+    let reaction = match (target["fid"].as_u64(), target["hash"].as_str()) {
+        (Some(fid), Some(hash)) => Reaction::new(reaction_type).with_target(fid, hash),
+        _ => Reaction::new(reaction_type),
+    };
+    Ok(Dated::from(date_from_object(input)?, reaction))
+}
+
+/// The cast type of every cast add message in a castsByFid response. Cast removes and unsupported
+/// messages are skipped.
 pub async fn cast_meta_from_pinata_response(
     response: Response,
 ) -> Result<Vec<Fidded<Dated<CastType>>>, ImporterError> {
-    let json = raw_json_from_response(response).await?;
-    let json_vec = json["messages"].as_array().unwrap();
-    json_vec
-        .iter()
+    let parsed = casts_from_pinata_response(response).await?;
+    Ok(parsed
+        .casts
+        .into_iter()
         .map(|x| {
-            date_from_object(x)
-                .and_then(|date| type_from_object(x).map(|cast_type| (date, cast_type)))
-                .and_then(|(date, cast_type)| {
-                    fid_from_object(x).map(|fid| (date, cast_type, fid as usize))
-                })
-                .map(|x| {
-                    let dated_cast_type = Dated::<CastType>::from(x.0, x.1);
-                    let fid = Fid::from(x.2);
-                    let result: Fidded<Dated<CastType>> = (dated_cast_type, fid).into();
-                    result
-                })
+            let fid = x.fid();
+            (x.unfid().map_ref(Cast::cast_type), fid).into()
         })
-        .collect::<Result<Vec<Fidded<Dated<CastType>>>, ImporterError>>()
+        .collect())
 }
 
 pub async fn followers_from_pinata_response(response: Response) -> Result<Vec<u64>, ImporterError> {
-    let json = raw_json_from_response(response).await?;
+    followers_from_json(&raw_json_from_response(response).await?)
+}
+
+pub(crate) fn followers_from_json(json: &Value) -> Result<Vec<u64>, ImporterError> {
     let json_vec = json["messages"]
         .as_array()
         .ok_or(ImporterError::BadApiResponse(json.to_string()))?;
@@ -114,3 +247,69 @@ pub async fn followers_from_pinata_response(response: Response) -> Result<Vec<u6
         })
         .collect::<Result<Vec<u64>, ImporterError>>()
 }
+
+/// The fids that the fid follows in a linksByFid response.
+pub(crate) fn following_from_json(json: &Value) -> Result<Vec<u64>, ImporterError> {
+    let json_vec = json["messages"]
+        .as_array()
+        .ok_or(ImporterError::BadApiResponse(json.to_string()))?;
+
+    json_vec
+        .iter()
+        .map(|x| {
+            x["data"]["linkBody"]["targetFid"]
+                .as_u64()
+                .ok_or(ImporterError::BadApiResponse(x.to_string()))
+        })
+        .collect::<Result<Vec<u64>, ImporterError>>()
+}
+
+/// The token of the next page of a paginated response. None if the response is the last page.
+pub(crate) fn next_page_token(json: &Value) -> Option<&str> {
+    json["nextPageToken"]
+        .as_str()
+        .filter(|token| !token.is_empty())
+}
+
+/// The profile in a userDataByFid response, dated at the latest user data message. Returns None if
+/// the response has no user data. Unknown user data types are ignored.
+pub async fn profile_from_pinata_response(
+    response: Response,
+) -> Result<Option<Dated<Profile>>, ImporterError> {
+    let json = raw_json_from_response(response).await?;
+    profile_from_json(&json)
+}
+
+fn profile_from_json(json: &Value) -> Result<Option<Dated<Profile>>, ImporterError> {
+    let json_vec = json["messages"]
+        .as_array()
+        .ok_or(ImporterError::BadApiResponse(json.to_string()))?;
+
+    let mut messages = json_vec
+        .iter()
+        .map(|x| date_time_from_object(x).map(|time| (time, x)))
+        .collect::<Result<Vec<_>, ImporterError>>()?;
+    messages.sort_by_key(|(time, _)| *time);
+
+    let Some((latest, _)) = messages.last() else {
+        return Ok(None);
+    };
+    let date = latest.date();
+
+    let profile = messages
+        .iter()
+        .fold(Profile::default(), |profile, (_, message)| {
+            let body = &message["data"]["userDataBody"];
+            let Some(value) = body["value"].as_str() else {
+                return profile;
+            };
+            match body["type"].as_str() {
+                Some("USER_DATA_TYPE_USERNAME") => profile.with_username(value),
+                Some("USER_DATA_TYPE_DISPLAY") => profile.with_display_name(value),
+                Some("USER_DATA_TYPE_BIO") => profile.with_bio(value),
+                Some("USER_DATA_TYPE_PFP") => profile.with_pfp_url(value),
+                _ => profile,
+            }
+        });
+    Ok(Some(Dated::from(date, profile)))
+}
@@ -1,5 +1,11 @@
 use super::wield_parser;
 use super::ImporterError;
+use crate::Dated;
+use crate::Fid;
+use crate::Fidded;
+use crate::FollowCount;
+use crate::Profile;
+use chrono::Utc;
 use log::{trace, warn};
 use reqwest::Response;
 use std::str::FromStr;
@@ -72,6 +78,32 @@ impl WieldFetcher {
         Ok(followers)
     }
 
+    /// Fetch the followers of the fid along with the profile and the follower and following counts
+    /// of each follower. The profiles and counts are dated today.
+    pub async fn fetch_followers_with_details(
+        &self,
+        fid: u64,
+    ) -> Result<WieldFollowers, ImporterError> {
+        let response = self
+            .fetch_follower_response_for_fid(fid)
+            .await
+            .inspect_err(|e| trace!("fetch failed with error {e:?}"))?;
+        let today = Utc::now().date_naive();
+        let mut followers = WieldFollowers::default();
+        for (fid, profile, follow_count) in
+            wield_parser::parse_follow_response_with_details(response).await?
+        {
+            let fid = Fid::from(fid);
+            followers
+                .profiles
+                .push(Fidded::from((Dated::from(today, profile), fid)));
+            followers
+                .follow_counts
+                .push(Fidded::from((Dated::from(today, follow_count), fid)));
+        }
+        Ok(followers)
+    }
+
     pub async fn fetch_follower_response_for_fid(
         &self,
         fid: u64,
@@ -88,6 +120,13 @@ impl WieldFetcher {
     }
 }
 
+/// The followers in a wield followers response, in the order of the response.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WieldFollowers {
+    pub profiles: Vec<Fidded<Dated<Profile>>>,
+    pub follow_counts: Vec<Fidded<Dated<FollowCount>>>,
+}
+
 #[derive(Error, Debug)]
 pub enum ApiKeyError {
     #[error("could not read api key")]
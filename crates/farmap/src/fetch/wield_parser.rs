@@ -1,5 +1,8 @@
 use super::importer_utils::parse_json_from_response;
 use super::ImporterError;
+use crate::FollowCount;
+use crate::Profile;
+use chrono::DateTime;
 use log::trace;
 use reqwest::Response;
 use serde_json::Value;
@@ -10,7 +13,46 @@ pub async fn parse_follow_response(response: Response) -> Result<Vec<u64>, Impor
     parse_raw_json(json)
 }
 
+/// Like [`parse_follow_response`], but keeps the profile and the follower and following counts of
+/// every follower.
+pub async fn parse_follow_response_with_details(
+    response: Response,
+) -> Result<Vec<(u64, Profile, FollowCount)>, ImporterError> {
+    let json = parse_json_from_response(response).await?;
+    trace!("successfully parsed into raw json {json:?}");
+    parse_raw_json_with_details(json)
+}
+
 fn parse_raw_json(json: Value) -> Result<Vec<u64>, ImporterError> {
+    Ok(parse_raw_json_with_profiles(json)?
+        .into_iter()
+        .map(|(fid, _)| fid)
+        .collect())
+}
+
+fn parse_raw_json_with_profiles(json: Value) -> Result<Vec<(u64, Profile)>, ImporterError> {
+    parse_users(json, profile_from_object)
+}
+
+fn parse_raw_json_with_details(
+    json: Value,
+) -> Result<Vec<(u64, Profile, FollowCount)>, ImporterError> {
+    Ok(parse_users(json, |object| {
+        (
+            profile_from_object(object),
+            follow_count_from_object(object),
+        )
+    })?
+    .into_iter()
+    .map(|(fid, (profile, follow_count))| (fid, profile, follow_count))
+    .collect())
+}
+
+/// The fid of every user in the response along with the value read from the user object.
+fn parse_users<T>(
+    json: Value,
+    value_from_object: fn(&Value) -> T,
+) -> Result<Vec<(u64, T)>, ImporterError> {
     let array = json
         .pointer("/result/users")
         .and_then(|x| x.as_array())
@@ -23,9 +65,55 @@ fn parse_raw_json(json: Value) -> Result<Vec<u64>, ImporterError> {
                 .and_then(|object| object.get("fid"))
                 .and_then(|fid_str| fid_str.as_str())
                 .and_then(|fid| fid.parse::<u64>().ok())
+                .map(|fid| (fid, value_from_object(object)))
         })
         .map(|x| x.ok_or(ImporterError::BadApiResponse(json.to_string())))
-        .collect::<Result<Vec<u64>, ImporterError>>()
+        .collect::<Result<Vec<(u64, T)>, ImporterError>>()
+}
+
+/// The follower and following counts of a wield user object. Missing counts are left empty.
+fn follow_count_from_object(object: &Value) -> FollowCount {
+    let mut follow_count = FollowCount::default();
+    if let Some(followers) = object.get("followerCount").and_then(|x| x.as_u64()) {
+        follow_count = follow_count.with_followers(followers);
+    }
+    if let Some(following) = object.get("followingCount").and_then(|x| x.as_u64()) {
+        follow_count = follow_count.with_following(following);
+    }
+    follow_count
+}
+
+/// The profile fields of a wield user object. Missing or malformed fields are left empty.
+fn profile_from_object(object: &Value) -> Profile {
+    let string_at = |pointer: &str| {
+        object
+            .pointer(pointer)
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string())
+    };
+    let mut profile = Profile::default();
+    if let Some(username) = string_at("/username") {
+        profile = profile.with_username(username);
+    }
+    if let Some(display_name) = string_at("/displayName") {
+        profile = profile.with_display_name(display_name);
+    }
+    if let Some(bio) = string_at("/bio/text") {
+        profile = profile.with_bio(bio);
+    }
+    if let Some(pfp_url) = string_at("/pfp/url") {
+        profile = profile.with_pfp_url(pfp_url);
+    }
+    if let Some(custody_address) = string_at("/custodyAddress") {
+        profile = profile.with_custody_address(custody_address);
+    }
+    if let Some(registered_at) = string_at("/registeredAt")
+        .and_then(|x| DateTime::parse_from_rfc3339(&x).ok())
+        .map(|x| x.naive_utc())
+    {
+        profile = profile.with_registered_at(registered_at);
+    }
+    profile
 }
 
 #[cfg(test)]
@@ -63,6 +151,22 @@ pub mod test {
   "source": "v2"
 }"#;
         let json: Value = serde_json::from_str(example).unwrap();
-        assert_eq!(*parse_raw_json(json).unwrap().first().unwrap(), 111);
+        assert_eq!(*parse_raw_json(json.clone()).unwrap().first().unwrap(), 111);
+
+        let (_, details_profile, follow_count) =
+            parse_raw_json_with_details(json.clone()).unwrap().remove(0);
+        assert_eq!(follow_count.followers(), Some(123));
+        assert_eq!(follow_count.following(), Some(123));
+
+        let (fid, profile) = parse_raw_json_with_profiles(json).unwrap().remove(0);
+        assert_eq!(details_profile, profile);
+        assert_eq!(fid, 111);
+        assert_eq!(profile.bio(), Some("a test"));
+        assert_eq!(profile.pfp_url(), Some("test.com"));
+        assert_eq!(profile.username(), Some("...."));
+        assert_eq!(
+            profile.registered_at().unwrap().to_string(),
+            "2023-11-07 05:31:56"
+        );
     }
 }
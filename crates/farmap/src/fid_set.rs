@@ -0,0 +1,350 @@
+use crate::Fid;
+use itertools::EitherOrBoth;
+use itertools::Itertools;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+use std::ops::RangeInclusive;
+
+const WORD_BITS: u64 = u64::BITS as u64;
+
+/// A set of fids stored as the non empty words of a bitmap where bit n is set if fid n is in the
+/// set. Each word is stored with the first fid it covers, sorted by that fid.
+///
+/// Fids are assigned in order by the id registry, so the fids of a collection are mostly dense and
+/// a word holds many fids. Only the non empty words are stored, so a fid far from the others takes
+/// a single word instead of a bitmap up to that fid.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct FidSet {
+    words: Vec<(u64, u64)>,
+}
+
+impl FidSet {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn position(fid: Fid) -> (u64, u64) {
+        let fid = u64::from(fid);
+        (fid - fid % WORD_BITS, 1 << (fid % WORD_BITS))
+    }
+
+    /// The index of the word that starts at base, or the index where it would be inserted.
+    fn find(&self, base: u64) -> Result<usize, usize> {
+        self.words
+            .binary_search_by_key(&base, |(word_base, _)| *word_base)
+    }
+
+    /// Adds the fid to the set. Returns true if the fid was not already in the set. Fids that are
+    /// inserted in ascending order are appended.
+    pub(crate) fn insert(&mut self, fid: Fid) -> bool {
+        let (base, bit) = Self::position(fid);
+        match self.find(base) {
+            Ok(index) => {
+                let word = &mut self.words[index].1;
+                let inserted = *word & bit == 0;
+                *word |= bit;
+                inserted
+            }
+            Err(index) => {
+                self.words.insert(index, (base, bit));
+                true
+            }
+        }
+    }
+
+    /// Removes the fid from the set. Returns true if the fid was in the set.
+    pub(crate) fn remove(&mut self, fid: Fid) -> bool {
+        let (base, bit) = Self::position(fid);
+        let Ok(index) = self.find(base) else {
+            return false;
+        };
+        let word = &mut self.words[index].1;
+        let removed = *word & bit != 0;
+        *word &= !bit;
+        if *word == 0 {
+            self.words.remove(index);
+        }
+        removed
+    }
+
+    pub(crate) fn contains(&self, fid: Fid) -> bool {
+        let (base, bit) = Self::position(fid);
+        self.find(base)
+            .is_ok_and(|index| self.words[index].1 & bit != 0)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|(_, word)| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The fids of the set in ascending order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Fid> + '_ {
+        self.range(..)
+    }
+
+    /// The fids of the set in the range in ascending order.
+    pub(crate) fn range(&self, range: impl RangeBounds<Fid>) -> impl Iterator<Item = Fid> + '_ {
+        self.words(range).flat_map(word_fids)
+    }
+
+    /// The words of the set with the first fid that each word covers, in ascending order. Only the
+    /// bits of the fids in the range are set, and words without such fids are skipped.
+    pub(crate) fn words(
+        &self,
+        range: impl RangeBounds<Fid>,
+    ) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let (start, end) = fid_bounds(range);
+        let first = self
+            .find(start - start % WORD_BITS)
+            .unwrap_or_else(|index| index);
+        self.words[first..]
+            .iter()
+            .take_while(move |(base, _)| end.is_none_or(|end| *base < end))
+            .map(move |(base, word)| (*base, word & range_mask(*base, start, end)))
+            .filter(|(_, word)| *word != 0)
+    }
+
+    /// The words of the set with the first fid that each word covers, in ascending order.
+    pub(crate) fn into_words(self) -> std::vec::IntoIter<(u64, u64)> {
+        self.words.into_iter()
+    }
+
+    /// The words of the set with the first fid that each word covers, so that they can be iterated
+    /// in parallel.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_words(
+        &self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = (u64, u64)> + '_ {
+        use rayon::prelude::*;
+        self.words.par_iter().copied()
+    }
+
+    /// Keeps only the fids in the range.
+    pub(crate) fn retain_range(&mut self, range: impl RangeBounds<Fid>) {
+        let (start, end) = fid_bounds(range);
+        self.words.retain_mut(|(base, word)| {
+            *word &= range_mask(*base, start, end);
+            *word != 0
+        });
+    }
+
+    pub(crate) fn union_with(&mut self, other: &FidSet) {
+        self.merge_with(other, |word, other| word | other);
+    }
+
+    pub(crate) fn intersect_with(&mut self, other: &FidSet) {
+        self.merge_with(other, |word, other| word & other);
+    }
+
+    pub(crate) fn difference_with(&mut self, other: &FidSet) {
+        self.merge_with(other, |word, other| word & !other);
+    }
+
+    pub(crate) fn symmetric_difference_with(&mut self, other: &FidSet) {
+        self.merge_with(other, |word, other| word ^ other);
+    }
+
+    /// Combines the words that start at the same fid with the operation, where a word that is only
+    /// in one of the sets is combined with an empty word. Empty results are dropped.
+    fn merge_with(&mut self, other: &FidSet, operation: fn(u64, u64) -> u64) {
+        self.words = self
+            .words
+            .iter()
+            .merge_join_by(&other.words, |(x, _), (y, _)| x.cmp(y))
+            .map(|words| match words {
+                EitherOrBoth::Both((base, word), (_, other)) => (*base, operation(*word, *other)),
+                EitherOrBoth::Left((base, word)) => (*base, operation(*word, 0)),
+                EitherOrBoth::Right((base, other)) => (*base, operation(0, *other)),
+            })
+            .filter(|(_, word)| *word != 0)
+            .collect();
+    }
+}
+
+/// The fids of a word that starts at the fid base.
+pub(crate) fn word_fids((base, word): (u64, u64)) -> impl Iterator<Item = Fid> {
+    bits(word).map(move |bit| Fid::from(base + bit))
+}
+
+/// The fids that a word that starts at the fid base covers.
+pub(crate) fn word_range((base, _): (u64, u64)) -> RangeInclusive<Fid> {
+    Fid::from(base)..=Fid::from(base + (WORD_BITS - 1))
+}
+
+/// Returns true if the bit of the fid is set in the word that starts at the fid base.
+pub(crate) fn word_contains((base, word): (u64, u64), fid: Fid) -> bool {
+    u64::from(fid)
+        .checked_sub(base)
+        .is_some_and(|offset| offset < WORD_BITS && word & (1 << offset) != 0)
+}
+
+/// The positions of the set bits of the word in ascending order.
+fn bits(mut word: u64) -> impl Iterator<Item = u64> {
+    std::iter::from_fn(move || {
+        (word != 0).then(|| {
+            let bit = word.trailing_zeros() as u64;
+            word &= word - 1;
+            bit
+        })
+    })
+}
+
+/// The inclusive start and exclusive end of the range as integers. An end of None is unbounded.
+fn fid_bounds(range: impl RangeBounds<Fid>) -> (u64, Option<u64>) {
+    let start = match range.start_bound() {
+        Bound::Included(fid) => u64::from(*fid),
+        Bound::Excluded(fid) => u64::from(*fid).saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(fid) => u64::from(*fid).checked_add(1),
+        Bound::Excluded(fid) => Some(u64::from(*fid)),
+        Bound::Unbounded => None,
+    };
+    (start, end)
+}
+
+/// The mask of the bits of the word starting at base that are in the range from start to end.
+fn range_mask(base: u64, start: u64, end: Option<u64>) -> u64 {
+    let low = start.saturating_sub(base).min(WORD_BITS);
+    let high = end.map_or(WORD_BITS, |end| end.saturating_sub(base).min(WORD_BITS));
+    if low >= high {
+        return 0;
+    }
+    let ones_below = |n: u64| {
+        if n == WORD_BITS {
+            u64::MAX
+        } else {
+            (1 << n) - 1
+        }
+    };
+    ones_below(high) & !ones_below(low)
+}
+
+impl std::fmt::Debug for FidSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<Fid> for FidSet {
+    fn from_iter<I: IntoIterator<Item = Fid>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for fid in iter {
+            set.insert(fid);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(fids: &[u64]) -> FidSet {
+        fids.iter().map(|fid| Fid::from(*fid)).collect()
+    }
+
+    fn fids(set: &FidSet) -> Vec<u64> {
+        set.iter().map(u64::from).collect()
+    }
+
+    #[test]
+    fn test_insert_remove_and_iterate() {
+        let mut fid_set = set(&[130, 1, 64, 63]);
+        assert_eq!(fids(&fid_set), [1, 63, 64, 130]);
+        assert_eq!(fid_set.len(), 4);
+        assert!(!fid_set.insert(Fid::from(64_u64)));
+        assert!(fid_set.remove(Fid::from(64_u64)));
+        assert!(!fid_set.remove(Fid::from(1000_u64)));
+        assert!(!fid_set.contains(Fid::from(64_u64)));
+        assert!(fid_set.contains(Fid::from(130_u64)));
+        assert_eq!(fids(&fid_set), [1, 63, 130]);
+        assert_eq!(
+            fid_set
+                .clone()
+                .into_words()
+                .flat_map(word_fids)
+                .collect_vec(),
+            fid_set.iter().collect_vec()
+        );
+        assert!(!fid_set.is_empty());
+        assert!(set(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_large_fids() {
+        let mut fid_set = set(&[u64::MAX, 3, 1 << 40]);
+        assert_eq!(fids(&fid_set), [3, 1 << 40, u64::MAX]);
+        assert_eq!(fid_set.words.len(), 3);
+        assert!(fid_set.contains(Fid::from(u64::MAX)));
+        assert_eq!(
+            fid_set
+                .range(Fid::from(4_u64)..)
+                .map(u64::from)
+                .collect_vec(),
+            [1 << 40, u64::MAX]
+        );
+        fid_set.intersect_with(&set(&[3, u64::MAX]));
+        assert_eq!(fids(&fid_set), [3, u64::MAX]);
+        assert!(fid_set.remove(Fid::from(u64::MAX)));
+        assert_eq!(fid_set, set(&[3]));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let fid_set = set(&[1, 63, 64, 65, 128, 200]);
+        let range = |range: (Bound<u64>, Bound<u64>)| -> Vec<u64> {
+            let range = (range.0.map(Fid::from), range.1.map(Fid::from));
+            let mut retained = fid_set.clone();
+            retained.retain_range(range);
+            let iterated: Vec<u64> = fid_set.range(range).map(u64::from).collect();
+            assert_eq!(fids(&retained), iterated);
+            iterated
+        };
+        assert_eq!(
+            range((Bound::Included(63), Bound::Excluded(128))),
+            [63, 64, 65]
+        );
+        assert_eq!(
+            range((Bound::Excluded(63), Bound::Included(128))),
+            [64, 65, 128]
+        );
+        assert_eq!(range((Bound::Included(129), Bound::Unbounded)), [200]);
+        assert_eq!(range((Bound::Unbounded, Bound::Included(1))), [1]);
+        assert_eq!(
+            range((Bound::Included(300), Bound::Unbounded)),
+            Vec::<u64>::new()
+        );
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&[1, 2, 70, 200]);
+        let b = set(&[2, 3, 70]);
+        let apply = |operation: fn(&mut FidSet, &FidSet), x: &FidSet, y: &FidSet| {
+            let mut result = x.clone();
+            operation(&mut result, y);
+            fids(&result)
+        };
+        assert_eq!(apply(FidSet::union_with, &a, &b), [1, 2, 3, 70, 200]);
+        assert_eq!(apply(FidSet::intersect_with, &a, &b), [2, 70]);
+        assert_eq!(apply(FidSet::difference_with, &a, &b), [1, 200]);
+        assert_eq!(apply(FidSet::difference_with, &b, &a), [3]);
+        assert_eq!(
+            apply(FidSet::symmetric_difference_with, &b, &a),
+            [1, 3, 200]
+        );
+
+        let mut trimmed = a.clone();
+        trimmed.remove(Fid::from(200_u64));
+        assert_eq!(trimmed, set(&[1, 2, 70]));
+    }
+}
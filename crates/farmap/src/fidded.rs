@@ -1,4 +1,3 @@
-use crate::native_user_value::NativeUserValue;
 use crate::Fid;
 use crate::HasTag;
 
@@ -28,7 +27,7 @@ impl<T> From<(T, Fid)> for Fidded<T> {
     }
 }
 
-impl<T: NativeUserValue> HasTag<Fid, T> for Fidded<T> {
+impl<T> HasTag<Fid, T> for Fidded<T> {
     fn tag(&self) -> Fid {
         self.fid
     }
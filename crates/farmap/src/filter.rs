@@ -0,0 +1,753 @@
+//! A filter language for users that is shared by the library, the cli and the api.
+//!
+//! A [`UserFilter`] is an expression of conditions on a user combined with `and`, `or` and `not`.
+//! Filters are serialized as json and can also be written as a string, for example
+//!
+//! ```text
+//! fid in 1..=20000 and first_label >= 2024-06-01 and not (score(2025-01-01) = 0 or casts < 10)
+//! ```
+//!
+//! The conditions are
+//! - `fid in A..=B`, `fid >= A`, `fid <= B`, `fid = A`: the fid is in the inclusive range.
+//! - `first_label in A..=B` or a comparison with a date: the date of the earliest spam label is in
+//!   the inclusive range.
+//! - `casts in A..=B` or a comparison with a number: the number of cast records is in the
+//!   inclusive range.
+//! - `score = N`: the most recent spam score is N.
+//! - `score(DATE) = N`: the spam score at the date is N.
+//! - `has(KIND)`: the user has at least one value of the [`ValueKind`].
+//! - `all` and `none`, which match every and no user.
+//!
+//! Either side of a range can be left out. `not` binds tighter than `and`, which binds tighter
+//! than `or`. The `Display` implementation writes a filter in this syntax, and [`str::parse`]
+//! accepts both this syntax and json.
+use crate::follow_graph::FollowerEdge;
+use crate::spam_score::DatedSpamUpdate;
+use crate::Cast;
+use crate::CastRemove;
+use crate::CastType;
+use crate::CustomUserValue;
+use crate::Dated;
+use crate::Fid;
+use crate::FollowCount;
+use crate::Label;
+use crate::Profile;
+use crate::Reaction;
+use crate::SetWithSpamEntries;
+use crate::SpamScore;
+use crate::UserStoreWithNativeUserValue;
+use crate::UserWithSpamData;
+use crate::UsersSubset;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A condition on a user. See the [module documentation](self) for the string syntax.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserFilter {
+    /// Matches the users that match every filter. An empty list matches every user.
+    And(Vec<UserFilter>),
+    /// Matches the users that match at least one filter. An empty list matches no user.
+    Or(Vec<UserFilter>),
+    Not(Box<UserFilter>),
+    /// The fid is in the inclusive range. A missing bound is unbounded.
+    FidRange {
+        from: Option<Fid>,
+        to: Option<Fid>,
+    },
+    /// The date of the earliest spam label is in the inclusive range. A missing bound is
+    /// unbounded. Users without spam labels do not match.
+    FirstLabelDate {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    /// The most recent spam score is the score.
+    CurrentScore(SpamScore),
+    /// The spam score at the date is the score. Users without a spam score at the date do not
+    /// match.
+    ScoreAtDate {
+        date: NaiveDate,
+        score: SpamScore,
+    },
+    /// The number of cast records is in the inclusive range. A missing bound is unbounded.
+    CastCount {
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+    /// The user has at least one value of the kind.
+    Has(ValueKind),
+}
+
+/// The kinds of values of a [`UserStoreWithNativeUserValue`] that a [`UserFilter`] can check for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    Spam,
+    /// Full cast records or cast type records.
+    Casts,
+    CastRemoves,
+    Reactions,
+    FollowCounts,
+    Profile,
+    Followers,
+    Labels,
+    Custom,
+}
+
+#[derive(Error, Debug)]
+pub enum FilterParseError {
+    #[error("unexpected end of filter expression")]
+    UnexpectedEnd,
+    #[error("unexpected {0:?} in filter expression")]
+    UnexpectedToken(String),
+    #[error("invalid value {0:?} in filter expression")]
+    InvalidValue(String),
+    #[error("the range of {0:?} is empty")]
+    EmptyRange(String),
+    #[error("unknown value kind {0:?}")]
+    UnknownValueKind(String),
+    #[error("invalid filter json")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Default for UserFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl UserFilter {
+    /// A filter that matches every user.
+    pub fn all() -> Self {
+        Self::And(Vec::new())
+    }
+
+    /// A filter that matches no user.
+    pub fn none() -> Self {
+        Self::Or(Vec::new())
+    }
+
+    /// Matches the users that match both filters.
+    pub fn and(self, other: UserFilter) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            filter => Self::And(vec![filter, other]),
+        }
+    }
+
+    /// Matches the users that match either filter.
+    pub fn or(self, other: UserFilter) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            filter => Self::Or(vec![filter, other]),
+        }
+    }
+
+    /// Parse a filter from json.
+    pub fn from_json(json: &str) -> Result<Self, FilterParseError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn matches(&self, user: &UserStoreWithNativeUserValue) -> bool {
+        match self {
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(user)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(user)),
+            Self::Not(filter) => !filter.matches(user),
+            Self::FidRange { from, to } => in_range(user.fid(), *from, *to),
+            Self::FirstLabelDate { from, to } => UserWithSpamData::try_from(user)
+                .is_ok_and(|user| in_range(user.earliest_spam_update().date(), *from, *to)),
+            Self::CurrentScore(score) => UserWithSpamData::try_from(user)
+                .is_ok_and(|user| user.latest_spam_update().score() == *score),
+            Self::ScoreAtDate { date, score } => UserWithSpamData::try_from(user)
+                .is_ok_and(|user| user.spam_score_at_date(*date) == Some(*score)),
+            Self::CastCount { min, max } => in_range(user.cast_count(), *min, *max),
+            Self::Has(kind) => kind.is_in(user),
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        matches!(self, Self::And(filters) | Self::Or(filters) if !filters.is_empty())
+    }
+}
+
+fn in_range<T: PartialOrd>(value: T, from: Option<T>, to: Option<T>) -> bool {
+    from.is_none_or(|from| value >= from) && to.is_none_or(|to| value <= to)
+}
+
+impl std::ops::Not for UserFilter {
+    type Output = UserFilter;
+
+    fn not(self) -> Self::Output {
+        Self::Not(Box::new(self))
+    }
+}
+
+impl ValueKind {
+    const ALL: [ValueKind; 9] = [
+        Self::Spam,
+        Self::Casts,
+        Self::CastRemoves,
+        Self::Reactions,
+        Self::FollowCounts,
+        Self::Profile,
+        Self::Followers,
+        Self::Labels,
+        Self::Custom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Spam => "spam",
+            Self::Casts => "casts",
+            Self::CastRemoves => "cast_removes",
+            Self::Reactions => "reactions",
+            Self::FollowCounts => "follow_counts",
+            Self::Profile => "profile",
+            Self::Followers => "followers",
+            Self::Labels => "labels",
+            Self::Custom => "custom",
+        }
+    }
+
+    /// Returns true if the user has at least one value of the kind.
+    pub fn is_in(&self, user: &UserStoreWithNativeUserValue) -> bool {
+        match self {
+            Self::Spam => user.has::<DatedSpamUpdate>(),
+            Self::Casts => user.has::<Dated<Cast>>() || user.has::<Dated<CastType>>(),
+            Self::CastRemoves => user.has::<Dated<CastRemove>>(),
+            Self::Reactions => user.has::<Dated<Reaction>>(),
+            Self::FollowCounts => user.has::<Dated<FollowCount>>(),
+            Self::Profile => user.has::<Dated<Profile>>(),
+            Self::Followers => user.has::<FollowerEdge>(),
+            Self::Labels => user.has::<Dated<Label>>(),
+            Self::Custom => user.has::<CustomUserValue>(),
+        }
+    }
+}
+
+impl FromStr for ValueKind {
+    type Err = FilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or(FilterParseError::UnknownValueKind(s.to_string()))
+    }
+}
+
+impl Display for ValueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Display for UserFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_operand = |f: &mut std::fmt::Formatter<'_>, filter: &UserFilter| {
+            if filter.is_compound() {
+                write!(f, "({filter})")
+            } else {
+                write!(f, "{filter}")
+            }
+        };
+        let write_range =
+            |f: &mut std::fmt::Formatter<'_>, from: Option<String>, to: Option<String>| {
+                write!(f, "{}..", from.unwrap_or_default())?;
+                to.map_or(Ok(()), |to| write!(f, "={to}"))
+            };
+
+        match self {
+            Self::And(filters) if filters.is_empty() => write!(f, "all"),
+            Self::Or(filters) if filters.is_empty() => write!(f, "none"),
+            Self::And(filters) | Self::Or(filters) => {
+                let separator = if matches!(self, Self::And(_)) {
+                    " and "
+                } else {
+                    " or "
+                };
+                for (index, filter) in filters.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(separator)?;
+                    }
+                    write_operand(f, filter)?;
+                }
+                Ok(())
+            }
+            Self::Not(filter) => {
+                write!(f, "not ")?;
+                write_operand(f, filter)
+            }
+            Self::FidRange { from, to } => {
+                write!(f, "fid in ")?;
+                write_range(f, from.map(|x| x.to_string()), to.map(|x| x.to_string()))
+            }
+            Self::FirstLabelDate { from, to } => {
+                write!(f, "first_label in ")?;
+                write_range(f, from.map(|x| x.to_string()), to.map(|x| x.to_string()))
+            }
+            Self::CastCount { min, max } => {
+                write!(f, "casts in ")?;
+                write_range(f, min.map(|x| x.to_string()), max.map(|x| x.to_string()))
+            }
+            Self::CurrentScore(score) => write!(f, "score = {}", *score as u8),
+            Self::ScoreAtDate { date, score } => write!(f, "score({date}) = {}", *score as u8),
+            Self::Has(kind) => write!(f, "has({kind})"),
+        }
+    }
+}
+
+/// Parses the string syntax, or json if the string starts with `{`.
+impl FromStr for UserFilter {
+    type Err = FilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('{') {
+            return Self::from_json(s);
+        }
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter().peekable(),
+        };
+        let filter = parser.or_expression()?;
+        match parser.tokens.next() {
+            Some(token) => Err(FilterParseError::UnexpectedToken(token.to_string())),
+            None => Ok(filter),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A keyword or the name of a condition.
+    Word(String),
+    /// A number or a date.
+    Literal(String),
+    Symbol(&'static str),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(x) | Self::Literal(x) => f.write_str(x),
+            Self::Symbol(x) => f.write_str(x),
+        }
+    }
+}
+
+/// The symbols of the syntax. Longer symbols come first so that they are matched first.
+const SYMBOLS: [&str; 9] = ["..=", "..", ">=", "<=", "=", ">", "<", "(", ")"];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FilterParseError> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while let Some(next) = rest.chars().next() {
+        let length = if let Some(symbol) = SYMBOLS.iter().find(|x| rest.starts_with(**x)) {
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        } else if next.is_ascii_alphabetic() || next == '_' {
+            let length = rest
+                .find(|x: char| !(x.is_ascii_alphanumeric() || x == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..length].to_string()));
+            length
+        } else if next.is_ascii_digit() {
+            let length = rest
+                .find(|x: char| !(x.is_ascii_digit() || x == '-'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Literal(rest[..length].to_string()));
+            length
+        } else {
+            return Err(FilterParseError::UnexpectedToken(next.to_string()));
+        };
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// A value that can be the bound of a range condition.
+trait Bound: Copy + Sized {
+    fn parse(literal: &str) -> Option<Self>;
+    fn succ(self) -> Option<Self>;
+    fn pred(self) -> Option<Self>;
+}
+
+impl Bound for u64 {
+    fn parse(literal: &str) -> Option<Self> {
+        literal.parse().ok()
+    }
+    fn succ(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+    fn pred(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+impl Bound for usize {
+    fn parse(literal: &str) -> Option<Self> {
+        literal.parse().ok()
+    }
+    fn succ(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+    fn pred(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+impl Bound for NaiveDate {
+    fn parse(literal: &str) -> Option<Self> {
+        NaiveDate::parse_from_str(literal, "%Y-%m-%d").ok()
+    }
+    fn succ(self) -> Option<Self> {
+        self.succ_opt()
+    }
+    fn pred(self) -> Option<Self> {
+        self.pred_opt()
+    }
+}
+
+type Range<T> = (Option<T>, Option<T>);
+
+struct Parser<I: Iterator<Item = Token>> {
+    tokens: Peekable<I>,
+}
+
+impl<I: Iterator<Item = Token>> Parser<I> {
+    fn next(&mut self) -> Result<Token, FilterParseError> {
+        self.tokens.next().ok_or(FilterParseError::UnexpectedEnd)
+    }
+
+    fn next_if_word(&mut self, word: &str) -> bool {
+        self.tokens
+            .next_if(|token| matches!(token, Token::Word(x) if x == word))
+            .is_some()
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), FilterParseError> {
+        match self.next()? {
+            Token::Symbol(x) if x == symbol => Ok(()),
+            token => Err(FilterParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn literal(&mut self) -> Result<String, FilterParseError> {
+        match self.next()? {
+            Token::Literal(x) => Ok(x),
+            token => Err(FilterParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn value<T: Bound>(&mut self) -> Result<T, FilterParseError> {
+        let literal = self.literal()?;
+        T::parse(&literal).ok_or(FilterParseError::InvalidValue(literal))
+    }
+
+    fn or_expression(&mut self) -> Result<UserFilter, FilterParseError> {
+        let mut filters = vec![self.and_expression()?];
+        while self.next_if_word("or") {
+            filters.push(self.and_expression()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            UserFilter::Or(filters)
+        })
+    }
+
+    fn and_expression(&mut self) -> Result<UserFilter, FilterParseError> {
+        let mut filters = vec![self.unary_expression()?];
+        while self.next_if_word("and") {
+            filters.push(self.unary_expression()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            UserFilter::And(filters)
+        })
+    }
+
+    fn unary_expression(&mut self) -> Result<UserFilter, FilterParseError> {
+        if self.next_if_word("not") {
+            return Ok(!self.unary_expression()?);
+        }
+        match self.next()? {
+            Token::Symbol("(") => {
+                let filter = self.or_expression()?;
+                self.expect_symbol(")")?;
+                Ok(filter)
+            }
+            Token::Word(word) => self.condition(&word),
+            token => Err(FilterParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn condition(&mut self, word: &str) -> Result<UserFilter, FilterParseError> {
+        match word {
+            "all" => Ok(UserFilter::all()),
+            "none" => Ok(UserFilter::none()),
+            "fid" => {
+                let (from, to) = self.range::<u64>(word)?;
+                Ok(UserFilter::FidRange {
+                    from: from.map(Fid::from),
+                    to: to.map(Fid::from),
+                })
+            }
+            "first_label" => {
+                let (from, to) = self.range(word)?;
+                Ok(UserFilter::FirstLabelDate { from, to })
+            }
+            "casts" => {
+                let (min, max) = self.range(word)?;
+                Ok(UserFilter::CastCount { min, max })
+            }
+            "score" => {
+                let date = if self.tokens.next_if_eq(&Token::Symbol("(")).is_some() {
+                    let date = self.value::<NaiveDate>()?;
+                    self.expect_symbol(")")?;
+                    Some(date)
+                } else {
+                    None
+                };
+                self.expect_symbol("=")?;
+                let literal = self.literal()?;
+                let score = literal
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|x| SpamScore::try_from(x).ok())
+                    .ok_or(FilterParseError::InvalidValue(literal))?;
+                Ok(match date {
+                    Some(date) => UserFilter::ScoreAtDate { date, score },
+                    None => UserFilter::CurrentScore(score),
+                })
+            }
+            "has" => {
+                self.expect_symbol("(")?;
+                let kind = match self.next()? {
+                    Token::Word(x) => x.parse::<ValueKind>()?,
+                    token => return Err(FilterParseError::UnexpectedToken(token.to_string())),
+                };
+                self.expect_symbol(")")?;
+                Ok(UserFilter::Has(kind))
+            }
+            _ => Err(FilterParseError::UnexpectedToken(word.to_string())),
+        }
+    }
+
+    /// An inclusive range, written as `in A..=B` with either side optional or as a comparison.
+    fn range<T: Bound>(&mut self, name: &str) -> Result<Range<T>, FilterParseError> {
+        let empty = || FilterParseError::EmptyRange(name.to_string());
+        let operator = match self.next()? {
+            Token::Word(x) if x == "in" => None,
+            Token::Symbol(x) => Some(x),
+            token => return Err(FilterParseError::UnexpectedToken(token.to_string())),
+        };
+
+        match operator {
+            None => {
+                let from = match self.tokens.peek() {
+                    Some(Token::Literal(_)) => Some(self.value::<T>()?),
+                    _ => None,
+                };
+                match self.next()? {
+                    Token::Symbol("..=") => Ok((from, Some(self.value::<T>()?))),
+                    Token::Symbol("..") => match self.tokens.peek() {
+                        Some(Token::Literal(x)) => {
+                            Err(FilterParseError::UnexpectedToken(x.to_string()))
+                        }
+                        _ => Ok((from, None)),
+                    },
+                    token => Err(FilterParseError::UnexpectedToken(token.to_string())),
+                }
+            }
+            Some("=") => {
+                let value = self.value::<T>()?;
+                Ok((Some(value), Some(value)))
+            }
+            Some(">=") => Ok((Some(self.value()?), None)),
+            Some("<=") => Ok((None, Some(self.value()?))),
+            Some(">") => Ok((Some(self.value::<T>()?.succ().ok_or_else(empty)?), None)),
+            Some("<") => Ok((None, Some(self.value::<T>()?.pred().ok_or_else(empty)?))),
+            Some(symbol) => Err(FilterParseError::UnexpectedToken(symbol.to_string())),
+        }
+    }
+}
+
+impl<'a> UsersSubset<'a> {
+    /// Restrict the subset to the users that match the filter.
+    pub fn filter_by(&mut self, filter: &UserFilter) {
+        self.filter(|user| filter.matches(user))
+    }
+
+    /// Return a new subset with the users that match the filter.
+    pub fn filtered_by(&self, filter: &UserFilter) -> Self {
+        self.filtered(|user| filter.matches(user))
+    }
+}
+
+impl SetWithSpamEntries<'_> {
+    /// Restrict the set to the users that match the filter. Like [`Self::filter`], this method
+    /// returns None (and does not filter) if no user in the set matches the filter.
+    #[must_use = "the set is left unfiltered if no user matches the filter"]
+    pub fn filter_by(&mut self, filter: &UserFilter) -> Option<()> {
+        self.filter(|user: &UserWithSpamData| filter.matches(user.user()))
+    }
+
+    /// Create a new set with the users that match the filter. Returns None if no user in the set
+    /// matches the filter.
+    pub fn filtered_by(&self, filter: &UserFilter) -> Option<Self> {
+        self.filtered(|user: &UserWithSpamData| filter.matches(user.user()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_utils::date;
+    use crate::user_collection::tests::dummy_data;
+
+    fn fid_range(from: Option<u64>, to: Option<u64>) -> UserFilter {
+        UserFilter::FidRange {
+            from: from.map(Fid::from),
+            to: to.map(Fid::from),
+        }
+    }
+
+    fn fids(set: &UsersSubset) -> Vec<u64> {
+        set.iter().map(|user| user.fid().into()).collect()
+    }
+
+    #[test]
+    fn test_parse_conditions() {
+        let check = |input: &str, expected: UserFilter| {
+            assert_eq!(input.parse::<UserFilter>().unwrap(), expected, "{input}");
+        };
+        check("fid in 10..=20", fid_range(Some(10), Some(20)));
+        check("fid in 10..", fid_range(Some(10), None));
+        check("fid in ..=20", fid_range(None, Some(20)));
+        check("fid > 10", fid_range(Some(11), None));
+        check("fid < 10", fid_range(None, Some(9)));
+        check("fid = 7", fid_range(Some(7), Some(7)));
+        check(
+            "first_label >= 2025-01-01",
+            UserFilter::FirstLabelDate {
+                from: Some(date("2025-01-01")),
+                to: None,
+            },
+        );
+        check(
+            "casts < 10",
+            UserFilter::CastCount {
+                min: None,
+                max: Some(9),
+            },
+        );
+        check("score = 2", UserFilter::CurrentScore(SpamScore::Two));
+        check(
+            "score(2025-01-23) = 0",
+            UserFilter::ScoreAtDate {
+                date: date("2025-01-23"),
+                score: SpamScore::Zero,
+            },
+        );
+        check("has(cast_removes)", UserFilter::Has(ValueKind::CastRemoves));
+        check("all", UserFilter::all());
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        let a = UserFilter::Has(ValueKind::Spam);
+        let b = UserFilter::Has(ValueKind::Casts);
+        let c = UserFilter::Has(ValueKind::Reactions);
+        assert_eq!(
+            "has(spam) or not has(casts) and has(reactions)"
+                .parse::<UserFilter>()
+                .unwrap(),
+            a.clone().or((!b.clone()).and(c.clone()))
+        );
+        assert_eq!(
+            "not (has(spam) or has(casts)) and has(reactions)"
+                .parse::<UserFilter>()
+                .unwrap(),
+            (!a.or(b)).and(c)
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions_are_err() {
+        for input in [
+            "",
+            "fid in 10..20",
+            "fid >",
+            "score = 3",
+            "has(bananas)",
+            "first_label >= 2025-13-01",
+            "(fid = 1",
+            "fid = 1 fid = 2",
+            "fid < 0",
+            "fid ! 2",
+        ] {
+            assert!(input.parse::<UserFilter>().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_display_and_json_round_trip() {
+        let filter: UserFilter = "fid in 1..=100 and not (score(2025-01-01) = 1 or casts >= 3) \
+                                  and first_label <= 2025-02-01 and has(follow_counts)"
+            .parse()
+            .unwrap();
+        assert_eq!(filter.to_string().parse::<UserFilter>().unwrap(), filter);
+        assert_eq!(
+            filter.to_string(),
+            "fid in 1..=100 and not (score(2025-01-01) = 1 or casts in 3..) \
+             and first_label in ..=2025-02-01 and has(follow_counts)"
+        );
+
+        let json = serde_json::to_string(&filter).unwrap();
+        assert_eq!(UserFilter::from_json(&json).unwrap(), filter);
+        assert_eq!(json.parse::<UserFilter>().unwrap(), filter);
+        assert_eq!(
+            UserFilter::from_json(r#"{"and":[{"current_score":"Two"},{"has":"spam"}]}"#).unwrap(),
+            UserFilter::CurrentScore(SpamScore::Two).and(UserFilter::Has(ValueKind::Spam))
+        );
+    }
+
+    #[test]
+    fn test_filter_subset_of_dummy_data() {
+        let users = dummy_data();
+        let set = UsersSubset::from(&users);
+        let filtered = |input: &str| fids(&set.filtered_by(&input.parse().unwrap()));
+        assert_eq!(filtered("all"), [1, 2]);
+        assert_eq!(filtered("none"), Vec::<u64>::new());
+        assert_eq!(filtered("fid >= 2"), [2]);
+        assert_eq!(filtered("score(2024-01-01) = 1"), [1]);
+        assert_eq!(filtered("score(2025-01-23) = 2 or score = 0"), [1, 2]);
+        assert_eq!(filtered("first_label < 2025-01-23"), [1]);
+        assert_eq!(filtered("not has(casts) and casts = 0"), [1, 2]);
+        assert_eq!(filtered("has(spam) and not has(profile)"), [1, 2]);
+    }
+
+    #[test]
+    fn test_filter_set_with_spam_entries() {
+        let users = dummy_data();
+        let mut set = SetWithSpamEntries::new(&users).unwrap();
+        assert!(set.filtered_by(&"fid > 2".parse().unwrap()).is_none());
+        assert_eq!(set.filter_by(&"score = 2".parse().unwrap()), Some(()));
+        assert_eq!(set.user_count(), 1);
+        assert!(set
+            .fid(2)
+            .is_some_and(|user| user.fid() == Fid::from(2_u64)));
+    }
+}
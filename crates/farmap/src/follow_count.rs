@@ -1,35 +1,73 @@
+use crate::core::UserValueContainer;
+use crate::dated::Dated;
 use crate::native_user_value::AnyNativeUserValue;
 use crate::NativeUserValue;
 
 use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
-pub struct FollowCount(u64);
+/// The follower and following counts of a user. Counts are stored as [`Dated<FollowCount>`] where
+/// the date is the date of the fetch. A count is None if the fetch did not return it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
+pub struct FollowCount {
+    followers: Option<u64>,
+    following: Option<u64>,
+}
+
+impl FollowCount {
+    pub fn with_followers(mut self, followers: u64) -> Self {
+        self.followers = Some(followers);
+        self
+    }
+
+    pub fn with_following(mut self, following: u64) -> Self {
+        self.following = Some(following);
+        self
+    }
+
+    /// The number of users that follow the user.
+    pub fn followers(&self) -> Option<u64> {
+        self.followers
+    }
 
-impl NativeUserValueSeal for FollowCount {}
+    /// The number of users that the user follows.
+    pub fn following(&self) -> Option<u64> {
+        self.following
+    }
+}
 
-impl NativeUserValue for FollowCount {
+impl NativeUserValueSeal for Dated<FollowCount> {}
+
+impl NativeUserValue for Dated<FollowCount> {
     fn as_any_user_value(&self) -> AnyNativeUserValue {
-        AnyNativeUserValue::FollowCount(*self)
+        AnyNativeUserValue::DatedFollowCount(*self)
     }
 
     fn into_any_user_value(self) -> AnyNativeUserValue {
-        AnyNativeUserValue::FollowCount(self)
+        AnyNativeUserValue::DatedFollowCount(self)
     }
 
     fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
         match any_user_value {
-            AnyNativeUserValue::FollowCount(x) => Some(x),
+            AnyNativeUserValue::DatedFollowCount(x) => Some(x),
             _ => None,
         }
     }
 
     fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
         match any_user_value {
-            AnyNativeUserValue::FollowCount(x) => Some(x),
+            AnyNativeUserValue::DatedFollowCount(x) => Some(x),
             _ => None,
         }
     }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_follow_counts
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_follow_counts
+    }
 }
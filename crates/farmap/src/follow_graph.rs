@@ -0,0 +1,359 @@
+//! Store follower edges from follower fetches and query the follow graph.
+//!
+//! Every fetch of the followers of a fid is stored as [`FollowerEdge`]s in the
+//! [`UserStoreWithNativeUserValue`] of the followed fid. Repeated fetches are merged, so each
+//! follower has a single edge that records the first and the last date the edge was seen. A
+//! [`FollowGraph`] indexes the edges of a collection in both directions.
+use crate::core::SourceId;
+use crate::core::UserValueContainer;
+use crate::native_user_value::AnyNativeUserValue;
+use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
+use crate::spam_score::EmptyScoreCountError;
+use crate::Fid;
+use crate::NativeUserValue;
+use crate::SetWithSpamEntries;
+use crate::Source;
+use crate::SpamScoreCount;
+use crate::SpamScoreDistribution;
+use crate::UserCollectionWithNativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A follower of the user that stores the edge, seen in the fetches at the first and the last
+/// date. Fetches in between are not recorded, so a follower that unfollowed and followed again
+/// between the dates has the same edge as a follower that never unfollowed.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct FollowerEdge {
+    follower: Fid,
+    first_seen: NaiveDate,
+    last_seen: NaiveDate,
+}
+
+impl FollowerEdge {
+    pub fn new(follower: impl Into<Fid>, date: NaiveDate) -> Self {
+        Self {
+            follower: follower.into(),
+            first_seen: date,
+            last_seen: date,
+        }
+    }
+
+    pub fn follower(&self) -> Fid {
+        self.follower
+    }
+
+    pub fn first_seen(&self) -> NaiveDate {
+        self.first_seen
+    }
+
+    pub fn last_seen(&self) -> NaiveDate {
+        self.last_seen
+    }
+
+    /// Extend the dates of the edge to cover the dates of the other edge.
+    fn merge(&mut self, other: &FollowerEdge) {
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+    }
+}
+
+impl NativeUserValueSeal for FollowerEdge {}
+
+impl NativeUserValue for FollowerEdge {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::FollowerEdge(*self)
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::FollowerEdge(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::FollowerEdge(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::FollowerEdge(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.follower_edges
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.follower_edges
+    }
+}
+
+/// Insert the edge so that the container stays sorted by follower.
+pub(crate) fn insert_by_follower(
+    container: &mut UserValueContainer<FollowerEdge>,
+    edge: FollowerEdge,
+    source: Option<SourceId>,
+) {
+    let index = container
+        .values()
+        .partition_point(|x| x.follower <= edge.follower);
+    container.insert(index, edge, source);
+}
+
+impl UserStoreWithNativeUserValue {
+    /// Merge the edge with the stored edge of the same follower. The edge is added if the user
+    /// has no edge for the follower. Returns true if the edge was added.
+    pub(crate) fn merge_follower_edge(
+        &mut self,
+        edge: FollowerEdge,
+        source: Option<SourceId>,
+    ) -> bool {
+        let edges = self.user_values_of_kind_mut::<FollowerEdge>();
+        match edges.binary_search_by_key(&edge.follower, |x| x.follower) {
+            Ok(index) => {
+                edges[index].merge(&edge);
+                false
+            }
+            Err(_) => {
+                self.add_user_value_with_source(edge, source);
+                true
+            }
+        }
+    }
+
+    /// The follower edges of the user, sorted by follower.
+    pub fn follower_edges(&self) -> &[FollowerEdge] {
+        self.user_values_of_kind::<FollowerEdge>()
+    }
+}
+
+impl UserCollectionWithNativeUserValue {
+    /// Store the result of a follower fetch for the fid at the date. The fetch is merged with the
+    /// stored edges of the fid, see [`FollowerEdge`]. Returns the number of new edges.
+    pub fn add_follower_fetch(
+        &mut self,
+        fid: impl Into<Fid>,
+        followers: impl IntoIterator<Item = impl Into<Fid>>,
+        date: NaiveDate,
+    ) -> usize {
+        self.add_follower_fetch_with_source(fid.into(), followers, date, None)
+    }
+
+    /// Like [`Self::add_follower_fetch`], but the source of the new edges is recorded. Returns the
+    /// id of the source along with the number of new edges.
+    pub fn add_follower_fetch_from_source(
+        &mut self,
+        source: Source,
+        fid: impl Into<Fid>,
+        followers: impl IntoIterator<Item = impl Into<Fid>>,
+        date: NaiveDate,
+    ) -> (SourceId, usize) {
+        let source = self.register_source(source);
+        let added = self.add_follower_fetch_with_source(fid.into(), followers, date, Some(source));
+        (source, added)
+    }
+
+    fn add_follower_fetch_with_source(
+        &mut self,
+        fid: Fid,
+        followers: impl IntoIterator<Item = impl Into<Fid>>,
+        date: NaiveDate,
+        source: Option<SourceId>,
+    ) -> usize {
+        if self.user(fid).is_none() {
+            self.add_user(UserStoreWithNativeUserValue::new(fid))
+                .expect("new user cannot collide");
+        }
+        let user = self.user_mut_unchecked(fid);
+        followers
+            .into_iter()
+            .map(|follower| FollowerEdge::new(follower, date))
+            .filter(|edge| user.merge_follower_edge(*edge, source))
+            .count()
+    }
+}
+
+/// An index of the follower edges of a collection in both directions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FollowGraph {
+    followers: BTreeMap<Fid, Vec<Fid>>,
+    followees: BTreeMap<Fid, Vec<Fid>>,
+}
+
+impl FollowGraph {
+    /// The graph of every follower edge in the collection.
+    pub fn new(collection: &UserCollectionWithNativeUserValue) -> Self {
+        Self::from_edges(collection, |_| true)
+    }
+
+    /// The graph of the follower edges that were last seen at or after the date.
+    pub fn seen_since(collection: &UserCollectionWithNativeUserValue, date: NaiveDate) -> Self {
+        Self::from_edges(collection, |edge| edge.last_seen >= date)
+    }
+
+    fn from_edges(
+        collection: &UserCollectionWithNativeUserValue,
+        predicate: impl Fn(&FollowerEdge) -> bool,
+    ) -> Self {
+        let mut graph = Self::default();
+        for user in collection.iter() {
+            let followers: Vec<Fid> = user
+                .follower_edges()
+                .iter()
+                .filter(|edge| predicate(edge))
+                .map(|edge| edge.follower)
+                .collect();
+            for follower in &followers {
+                graph
+                    .followees
+                    .entry(*follower)
+                    .or_default()
+                    .push(user.fid());
+            }
+            if !followers.is_empty() {
+                graph.followers.insert(user.fid(), followers);
+            }
+        }
+        graph
+    }
+
+    /// The followers of the fid, in ascending fid order.
+    pub fn followers(&self, fid: impl Into<Fid>) -> &[Fid] {
+        self.followers
+            .get(&fid.into())
+            .map_or(&[], |x| x.as_slice())
+    }
+
+    /// The fids that the fid follows, in ascending fid order.
+    pub fn followees(&self, fid: impl Into<Fid>) -> &[Fid] {
+        self.followees
+            .get(&fid.into())
+            .map_or(&[], |x| x.as_slice())
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.followers.values().map(Vec::len).sum()
+    }
+
+    /// The spam scores at the date of the followers of the fid. Followers without a spam score in
+    /// the set at the date are not counted.
+    pub fn follower_spam_score_count(
+        &self,
+        fid: impl Into<Fid>,
+        set: &SetWithSpamEntries,
+        date: NaiveDate,
+    ) -> SpamScoreCount {
+        let mut count = SpamScoreCount::default();
+        for score in self
+            .followers(fid)
+            .iter()
+            .filter_map(|follower| set.spam_score_of(*follower, date))
+        {
+            count.add(score);
+        }
+        count
+    }
+
+    /// The spam score distribution at the date of the followers of the fid, e.g. the share of the
+    /// followers that are labelled spam. Returns an error if no follower has a spam score at the
+    /// date.
+    pub fn follower_spam_score_distribution(
+        &self,
+        fid: impl Into<Fid>,
+        set: &SetWithSpamEntries,
+        date: NaiveDate,
+    ) -> Result<SpamScoreDistribution, EmptyScoreCountError> {
+        SpamScoreDistribution::try_from(self.follower_spam_score_count(fid, set, date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam_score::DatedSpamUpdate;
+    use crate::time_utils::date;
+    use crate::Fidded;
+    use crate::SpamScore;
+
+    fn graph_collection() -> UserCollectionWithNativeUserValue {
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_follower_fetch(1_u64, [2_u64, 3, 4], date("2025-01-01"));
+        collection.add_follower_fetch(2_u64, [3_u64], date("2025-01-01"));
+        collection
+    }
+
+    #[test]
+    fn test_repeated_fetches_are_merged() {
+        let mut collection = graph_collection();
+        assert_eq!(
+            collection.add_follower_fetch(1_u64, [4_u64, 5, 2], date("2025-02-01")),
+            1
+        );
+        let edges = collection.user(1_u64).unwrap().follower_edges();
+        assert_eq!(
+            edges.iter().map(|x| x.follower()).collect::<Vec<_>>(),
+            [2_u64, 3, 4, 5].map(Fid::from)
+        );
+        assert_eq!(edges[0].first_seen(), date("2025-01-01"));
+        assert_eq!(edges[0].last_seen(), date("2025-02-01"));
+        assert_eq!(edges[1].last_seen(), date("2025-01-01"));
+    }
+
+    #[test]
+    fn test_followers_and_followees() {
+        let graph = FollowGraph::new(&graph_collection());
+        assert_eq!(graph.followers(1_u64), [2_u64, 3, 4].map(Fid::from));
+        assert_eq!(graph.followees(3_u64), [1_u64, 2].map(Fid::from));
+        assert!(graph.followers(3_u64).is_empty());
+        assert_eq!(graph.edge_count(), 4);
+    }
+
+    #[test]
+    fn test_seen_since() {
+        let mut collection = graph_collection();
+        collection.add_follower_fetch(1_u64, [2_u64], date("2025-02-01"));
+        let graph = FollowGraph::seen_since(&collection, date("2025-02-01"));
+        assert_eq!(graph.followers(1_u64), [Fid::from(2_u64)]);
+        assert!(graph.followers(2_u64).is_empty());
+    }
+
+    #[test]
+    fn test_follower_spam_score_distribution() {
+        let mut collection = graph_collection();
+        collection.add_user_value_iter([2_u64, 3, 4].into_iter().zip([0, 0, 2]).map(
+            |(fid, score)| {
+                Fidded::from((
+                    DatedSpamUpdate::from(date("2025-01-01"), SpamScore::try_from(score).unwrap()),
+                    Fid::from(fid),
+                ))
+            },
+        ));
+        let graph = FollowGraph::new(&collection);
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let distribution = graph
+            .follower_spam_score_distribution(1_u64, &set, date("2025-01-01"))
+            .unwrap();
+        assert!((distribution.spam() - 2.0 / 3.0).abs() < 1e-6);
+        assert!(graph
+            .follower_spam_score_distribution(1_u64, &set, date("2024-12-31"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_merge_combines_edges() {
+        let mut collection = graph_collection();
+        let mut other = UserCollectionWithNativeUserValue::default();
+        other.add_follower_fetch(1_u64, [2_u64], date("2025-03-01"));
+        collection.merge(&other, crate::CollisionPolicy::KeepFirst);
+        let edges = collection.user(1_u64).unwrap().follower_edges();
+        assert_eq!(edges.len(), 3);
+        assert_eq!(edges[0].last_seen(), date("2025-03-01"));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::core::UserValueContainer;
+use crate::dated::Dated;
+use crate::native_user_value::AnyNativeUserValue;
+use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
+use crate::spam_score::DatedSpamUpdate;
+use crate::Fidded;
+use crate::NativeUserValue;
+use crate::SpamScore;
+use crate::UserCollectionWithNativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// The fid of Warpcast, the provider of the spam labels.
+pub const WARPCAST_PROVIDER: u64 = 9152;
+
+/// A label stream of the [labels](https://github.com/farcasterxyz/labels) datasets, which is the
+/// fid of the provider of the labels and the label type.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LabelStream {
+    provider: u64,
+    label_type: String,
+}
+
+impl LabelStream {
+    pub fn new(provider: u64, label_type: impl Into<String>) -> Self {
+        Self {
+            provider,
+            label_type: label_type.into(),
+        }
+    }
+
+    /// The Warpcast spam labels. Labels of this stream are stored as spam updates.
+    pub fn warpcast_spam() -> Self {
+        Self::new(WARPCAST_PROVIDER, "spam")
+    }
+
+    pub fn provider(&self) -> u64 {
+        self.provider
+    }
+
+    pub fn label_type(&self) -> &str {
+        &self.label_type
+    }
+
+    pub fn is_warpcast_spam(&self) -> bool {
+        *self == Self::warpcast_spam()
+    }
+}
+
+/// A label of a [`LabelStream`] other than the Warpcast spam stream. Labels are stored as
+/// [`Dated<Label>`] where the date is the date of the label.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Label {
+    stream: LabelStream,
+    value: u64,
+}
+
+impl Label {
+    pub fn new(stream: LabelStream, value: u64) -> Self {
+        Self { stream, value }
+    }
+
+    pub fn stream(&self) -> &LabelStream {
+        &self.stream
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+impl NativeUserValueSeal for Dated<Label> {}
+
+impl NativeUserValue for Dated<Label> {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedLabel(self.clone())
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedLabel(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedLabel(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedLabel(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_labels
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_labels
+    }
+}
+
+impl UserStoreWithNativeUserValue {
+    /// The label values of the stream, sorted by date. The values of the Warpcast spam stream are
+    /// the scores of the spam updates.
+    pub fn labels_of_stream(&self, stream: &LabelStream) -> Vec<Dated<u64>> {
+        if stream.is_warpcast_spam() {
+            self.user_values_of_kind::<DatedSpamUpdate>()
+                .iter()
+                .map(|x| x.map_ref(|update| update.score() as u64))
+                .collect()
+        } else {
+            self.user_values_of_kind::<Dated<Label>>()
+                .iter()
+                .filter(|x| x.stream() == stream)
+                .map(|x| x.map_ref(Label::value))
+                .collect()
+        }
+    }
+
+    /// The label streams that the user has labels of.
+    pub fn label_streams(&self) -> BTreeSet<LabelStream> {
+        let mut streams: BTreeSet<LabelStream> = self
+            .user_values_of_kind::<Dated<Label>>()
+            .iter()
+            .map(|x| x.stream().clone())
+            .collect();
+        if self.has::<DatedSpamUpdate>() {
+            streams.insert(LabelStream::warpcast_spam());
+        }
+        streams
+    }
+}
+
+impl UserCollectionWithNativeUserValue {
+    /// The label streams that some user in the collection has labels of.
+    pub fn label_streams(&self) -> BTreeSet<LabelStream> {
+        self.iter().flat_map(|user| user.label_streams()).collect()
+    }
+
+    /// A new collection with the labels of the stream as spam updates, which lets the spam
+    /// analytics such as [`SetWithSpamEntries`](crate::SetWithSpamEntries) run on any label stream
+    /// with the values 0, 1 and 2. Labels with other values are left out.
+    pub fn label_stream_as_spam_updates(&self, stream: &LabelStream) -> Self {
+        let mut collection = Self::default();
+        collection.add_user_value_iter(self.iter().flat_map(|user| {
+            user.labels_of_stream(stream)
+                .into_iter()
+                .filter_map(move |label| {
+                    let score = SpamScore::try_from(label.into_inner() as usize).ok()?;
+                    let update: DatedSpamUpdate = label.map_ref(|_| score).map_into();
+                    Some(Fidded::from((update, user.fid())))
+                })
+        }));
+        collection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_utils::date;
+    use crate::Fid;
+    use crate::SetWithSpamEntries;
+    use crate::SpamDataParseError;
+    use crate::UnprocessedUserLine;
+
+    fn label(fid: u64, stream: &LabelStream, value: u64, date_str: &str) -> Fidded<Dated<Label>> {
+        Fidded::from((
+            Dated::from(date(date_str), Label::new(stream.clone(), value)),
+            Fid::from(fid),
+        ))
+    }
+
+    #[test]
+    fn test_spam_analytics_on_other_label_stream() {
+        let quality = LabelStream::new(1, "quality");
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter([
+            label(1, &quality, 2, "2025-01-01"),
+            label(2, &quality, 0, "2025-01-01"),
+            label(3, &quality, 7, "2025-01-01"),
+        ]);
+        collection.add_user_value_iter([Fidded::from((
+            DatedSpamUpdate::from(date("2025-01-01"), SpamScore::One),
+            Fid::from(1_u64),
+        ))]);
+
+        assert_eq!(
+            collection.label_streams(),
+            BTreeSet::from([quality.clone(), LabelStream::warpcast_spam()])
+        );
+
+        let projected = collection.label_stream_as_spam_updates(&quality);
+        let set = SetWithSpamEntries::new(&projected).unwrap();
+        assert_eq!(set.user_count(), 2);
+        assert_eq!(
+            set.current_spam_score_count().into_inner(),
+            [1, 0, 1].into()
+        );
+
+        let spam = collection.label_stream_as_spam_updates(&LabelStream::warpcast_spam());
+        assert_eq!(SetWithSpamEntries::new(&spam).unwrap().user_count(), 1);
+    }
+
+    #[test]
+    fn test_label_timestamps_are_kept() {
+        let quality = LabelStream::new(1, "quality");
+        let time = date("2025-01-01").and_hms_opt(12, 0, 0).unwrap().and_utc();
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter([Fidded::from((
+            Dated::<Label>::from_timestamp(time, Label::new(quality.clone(), 1)),
+            Fid::from(1_u64),
+        ))]);
+        collection.add_user_value_iter([Fidded::from((
+            DatedSpamUpdate::from_timestamp(time, SpamScore::One),
+            Fid::from(1_u64),
+        ))]);
+        let user = collection.user(Fid::from(1_u64)).unwrap();
+        assert_eq!(user.labels_of_stream(&quality)[0].timestamp(), Some(time));
+        assert_eq!(
+            user.labels_of_stream(&LabelStream::warpcast_spam())[0].timestamp(),
+            Some(time)
+        );
+
+        let projected = collection.label_stream_as_spam_updates(&quality);
+        let user = projected.user(Fid::from(1_u64)).unwrap();
+        assert_eq!(
+            user.user_values_of_kind::<DatedSpamUpdate>()[0].timestamp(),
+            Some(time)
+        );
+    }
+
+    #[test]
+    fn test_lines_of_other_streams_are_labels() {
+        let line: UnprocessedUserLine = serde_json::from_str(
+            r#"{"provider": 1, "type": {"target": "user", "fid": 5}, "label_type": "quality", "label_value": 7, "timestamp": 1737596669}"#,
+        )
+        .unwrap();
+        assert!(!line.is_warpcast_spam());
+        assert!(matches!(
+            Fidded::<DatedSpamUpdate>::try_from(line.clone()),
+            Err(SpamDataParseError::NotSpamLabel { provider: 1, .. })
+        ));
+
+        let label = Fidded::<Dated<Label>>::try_from(line).unwrap();
+        assert_eq!(label.fid(), Fid::from(5_u64));
+        let label = label.unfid();
+        assert_eq!(label.stream(), &LabelStream::new(1, "quality"));
+        assert_eq!(label.value(), 7);
+        assert_eq!(label.date(), date("2025-01-23"));
+    }
+}
@@ -9,19 +9,33 @@
 //! using the User, UserCollection and Subset struct.
 mod analyze_spam_entry;
 pub use analyze_spam_entry::SetWithSpamEntries;
+mod cast;
 mod cast_type;
+pub mod cohort;
+mod collection_diff;
 mod core;
+mod custom_user_value;
 mod dated;
 pub mod fetch;
 pub mod fid_score_shift;
+mod fid_set;
 mod fidded;
+pub mod filter;
 mod follow_count;
+pub mod follow_graph;
 mod is_user;
+pub mod label;
 mod native_user_value;
+mod profile;
+mod reaction;
 mod set_with_cast_data;
+mod set_with_follow_count_data;
+mod set_with_reaction_data;
 pub mod spam_score;
 pub mod subset;
+pub mod survival;
 mod time_utils;
+pub mod transition_model;
 mod try_from_user;
 mod try_from_user_set;
 mod unprocessed_user_line;
@@ -31,26 +45,68 @@ mod user_serde;
 mod user_set;
 pub mod user_store_with_native_user_value;
 mod user_with_cast_data;
+mod user_with_follow_count_data;
+mod user_with_reaction_data;
 mod user_with_spam_data;
 mod utils;
 pub use crate::unprocessed_user_line::SpamDataParseError;
+pub use cast::Cast;
+pub use cast::CastId;
+pub use cast::CastParent;
+pub use cast::CastRemove;
+pub use cast::Embed;
 pub use cast_type::CastType;
 pub use cast_type::InvalidCastInputError;
+pub use cohort::CohortPeriod;
+pub use cohort::CohortTable;
+pub use cohort::Cohorts;
+pub use collection_diff::CollectionDiff;
+pub use collection_diff::UserDiff;
 pub use core::AnyUserValue;
 pub use core::CollectionError;
 pub use core::Collidable;
+pub use core::CollisionPolicy;
+pub use core::CollisionReport;
 pub use core::Fid;
 pub use core::HasTag;
+pub use core::Source;
+pub use core::SourceId;
+pub use core::SourceLocation;
+pub use core::Sourced;
+pub use core::UserCollection;
 pub use core::UserError;
 pub use core::UserStore;
+pub use core::UserValue;
+pub use core::UserValueCollision;
+pub use core::UserValueContainer;
+pub use core::UserValueStorage;
+pub use custom_user_value::CustomUserValue;
+pub use custom_user_value::CustomValue;
+pub use custom_user_value::CustomValueError;
+pub use dated::Dated;
 #[doc(inline)]
 pub use fid_score_shift::FidScoreShift;
 pub use fidded::Fidded;
+pub use filter::UserFilter;
 pub use follow_count::FollowCount;
+pub use follow_graph::FollowGraph;
+pub use follow_graph::FollowerEdge;
 pub use is_user::IsUser;
+pub use label::Label;
+pub use label::LabelStream;
 pub use native_user_value::AnyNativeUserValue;
+pub use native_user_value::AnyNativeUserValueRef;
 pub use native_user_value::NativeUserValue;
+pub use native_user_value::NativeUserValueStorage;
+pub use profile::Profile;
+pub use reaction::Reaction;
+pub use reaction::ReactionType;
 pub use set_with_cast_data::SetWithCastData;
+pub use set_with_follow_count_data::FollowerCountSummary;
+pub use set_with_follow_count_data::SetWithFollowCountData;
+pub use set_with_follow_count_data::SetWithFollowCountDataError;
+pub use set_with_reaction_data::SetWithReactionData;
+pub use set_with_reaction_data::SetWithReactionDataError;
 pub use spam_score::DatedSpamScoreCount;
 pub use spam_score::SpamRecord;
 pub use spam_score::SpamScore;
@@ -58,6 +114,10 @@ pub use spam_score::SpamScoreCount;
 pub use spam_score::SpamScoreDistribution;
 #[doc(inline)]
 pub use subset::UsersSubset;
+pub use survival::SurvivalCurve;
+pub use survival::SurvivalCurves;
+pub use transition_model::TransitionMatrix;
+pub use transition_model::TransitionModel;
 pub use try_from_user::TryFromUser;
 pub use try_from_user_set::TryFromUserSet;
 pub use unprocessed_user_line::UnprocessedUserLine;
@@ -66,4 +126,8 @@ pub use user_collection::UserCollectionWithNativeUserValue;
 pub use user_set::UserSet;
 pub use user_store_with_native_user_value::UserStoreWithNativeUserValue;
 pub use user_with_cast_data::UserWithCastData;
+pub use user_with_follow_count_data::NoFollowCountDataError;
+pub use user_with_follow_count_data::UserWithFollowCountData;
+pub use user_with_reaction_data::NoReactionDataError;
+pub use user_with_reaction_data::UserWithReactionData;
 pub use user_with_spam_data::UserWithSpamData;
@@ -1,8 +1,19 @@
+use crate::cast::Cast;
+use crate::cast::CastRemove;
 use crate::cast_type::CastType;
 use crate::core::AnyUserValue;
+use crate::core::SourceId;
 use crate::core::UserValue;
+use crate::core::UserValueContainer;
+use crate::core::UserValueStorage;
+use crate::custom_user_value::CustomUserValue;
 use crate::dated::Dated;
 use crate::follow_count::FollowCount;
+use crate::follow_graph::insert_by_follower;
+use crate::follow_graph::FollowerEdge;
+use crate::label::Label;
+use crate::profile::Profile;
+use crate::reaction::Reaction;
 use crate::spam_score::{DatedSpamUpdate, SpamScore, SpamUpdate};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fmt::Debug;
@@ -18,6 +29,10 @@ pub trait NativeUserValue:
     fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self>;
 
     fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self>;
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self>;
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self>;
 }
 
 impl<T> UserValue<AnyNativeUserValue> for T
@@ -36,6 +51,12 @@ where
     fn from_any_ref(list: &AnyNativeUserValue) -> Option<&Self> {
         T::from_any_user_value_ref(list)
     }
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        T::stored(storage)
+    }
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        T::stored_mut(storage)
+    }
 }
 
 pub(crate) trait NativeUserValueSeal {}
@@ -47,7 +68,52 @@ pub enum AnyNativeUserValue {
     SpamUpdate(SpamUpdate),
     SpamScore(SpamScore),
     DatedCastType(Dated<CastType>),
-    FollowCount(FollowCount),
+    DatedCast(Dated<Cast>),
+    DatedCastRemove(Dated<CastRemove>),
+    DatedFollowCount(Dated<FollowCount>),
+    DatedProfile(Dated<Profile>),
+    FollowerEdge(FollowerEdge),
+    DatedReaction(Dated<Reaction>),
+    DatedLabel(Dated<Label>),
+    Custom(CustomUserValue),
+}
+
+/// A borrowed [`AnyNativeUserValue`], see
+/// [`UserStore::all_user_values`](crate::UserStore::all_user_values).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AnyNativeUserValueRef<'a> {
+    DatedSpamUpdate(&'a DatedSpamUpdate),
+    SpamUpdate(&'a SpamUpdate),
+    SpamScore(&'a SpamScore),
+    DatedCastType(&'a Dated<CastType>),
+    DatedCast(&'a Dated<Cast>),
+    DatedCastRemove(&'a Dated<CastRemove>),
+    DatedFollowCount(&'a Dated<FollowCount>),
+    DatedProfile(&'a Dated<Profile>),
+    FollowerEdge(&'a FollowerEdge),
+    DatedReaction(&'a Dated<Reaction>),
+    DatedLabel(&'a Dated<Label>),
+    Custom(&'a CustomUserValue),
+}
+
+impl From<AnyNativeUserValueRef<'_>> for AnyNativeUserValue {
+    fn from(value: AnyNativeUserValueRef<'_>) -> Self {
+        match value {
+            AnyNativeUserValueRef::DatedSpamUpdate(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::SpamUpdate(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::SpamScore(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedCastType(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedCast(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedCastRemove(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedFollowCount(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedProfile(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::FollowerEdge(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedReaction(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::DatedLabel(x) => x.as_any_user_value(),
+            AnyNativeUserValueRef::Custom(x) => x.as_any_user_value(),
+        }
+    }
 }
 
 impl AnyNativeUserValue {
@@ -61,6 +127,9 @@ impl AnyNativeUserValue {
 }
 
 impl AnyUserValue for AnyNativeUserValue {
+    type Storage = NativeUserValueStorage;
+    type Ref<'a> = AnyNativeUserValueRef<'a>;
+
     fn specify_ref<S: UserValue<AnyNativeUserValue>>(&self) -> Option<&S> {
         S::from_any_ref(self)
     }
@@ -69,3 +138,97 @@ impl AnyUserValue for AnyNativeUserValue {
         S::from_any(self)
     }
 }
+
+/// The storage of a [`UserStoreWithNativeUserValue`](crate::UserStoreWithNativeUserValue). There is
+/// one container for each kind of [`NativeUserValue`]. Dated values are kept sorted by date and
+/// timestamp, values at the same time are kept in insertion order. Follower edges are kept sorted
+/// by follower. Other values are kept in insertion order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NativeUserValueStorage {
+    pub(crate) dated_spam_updates: UserValueContainer<DatedSpamUpdate>,
+    pub(crate) spam_updates: UserValueContainer<SpamUpdate>,
+    pub(crate) spam_scores: UserValueContainer<SpamScore>,
+    pub(crate) dated_cast_types: UserValueContainer<Dated<CastType>>,
+    pub(crate) dated_casts: UserValueContainer<Dated<Cast>>,
+    pub(crate) dated_cast_removes: UserValueContainer<Dated<CastRemove>>,
+    pub(crate) dated_follow_counts: UserValueContainer<Dated<FollowCount>>,
+    pub(crate) dated_profiles: UserValueContainer<Dated<Profile>>,
+    pub(crate) follower_edges: UserValueContainer<FollowerEdge>,
+    pub(crate) dated_reactions: UserValueContainer<Dated<Reaction>>,
+    pub(crate) dated_labels: UserValueContainer<Dated<Label>>,
+    pub(crate) custom_values: UserValueContainer<CustomUserValue>,
+}
+
+impl UserValueStorage<AnyNativeUserValue> for NativeUserValueStorage {
+    fn insert(&mut self, value: AnyNativeUserValue, source: Option<SourceId>) {
+        match value {
+            AnyNativeUserValue::DatedSpamUpdate(x) => {
+                insert_by_date(&mut self.dated_spam_updates, x, source)
+            }
+            AnyNativeUserValue::SpamUpdate(x) => self.spam_updates.push(x, source),
+            AnyNativeUserValue::SpamScore(x) => self.spam_scores.push(x, source),
+            AnyNativeUserValue::DatedCastType(x) => {
+                insert_by_date(&mut self.dated_cast_types, x, source)
+            }
+            AnyNativeUserValue::DatedCast(x) => insert_by_date(&mut self.dated_casts, x, source),
+            AnyNativeUserValue::DatedCastRemove(x) => {
+                insert_by_date(&mut self.dated_cast_removes, x, source)
+            }
+            AnyNativeUserValue::DatedFollowCount(x) => {
+                insert_by_date(&mut self.dated_follow_counts, x, source)
+            }
+            AnyNativeUserValue::DatedProfile(x) => {
+                insert_by_date(&mut self.dated_profiles, x, source)
+            }
+            AnyNativeUserValue::FollowerEdge(x) => {
+                insert_by_follower(&mut self.follower_edges, x, source)
+            }
+            AnyNativeUserValue::DatedReaction(x) => {
+                insert_by_date(&mut self.dated_reactions, x, source)
+            }
+            AnyNativeUserValue::DatedLabel(x) => insert_by_date(&mut self.dated_labels, x, source),
+            AnyNativeUserValue::Custom(x) => self.custom_values.push(x, source),
+        }
+    }
+
+    fn any_value_refs(
+        &self,
+    ) -> impl Iterator<Item = (AnyNativeUserValueRef<'_>, Option<SourceId>)> + '_ {
+        use AnyNativeUserValueRef as Ref;
+        value_refs(&self.dated_spam_updates, Ref::DatedSpamUpdate)
+            .chain(value_refs(&self.spam_updates, Ref::SpamUpdate))
+            .chain(value_refs(&self.spam_scores, Ref::SpamScore))
+            .chain(value_refs(&self.dated_cast_types, Ref::DatedCastType))
+            .chain(value_refs(&self.dated_casts, Ref::DatedCast))
+            .chain(value_refs(&self.dated_cast_removes, Ref::DatedCastRemove))
+            .chain(value_refs(&self.dated_follow_counts, Ref::DatedFollowCount))
+            .chain(value_refs(&self.dated_profiles, Ref::DatedProfile))
+            .chain(value_refs(&self.follower_edges, Ref::FollowerEdge))
+            .chain(value_refs(&self.dated_reactions, Ref::DatedReaction))
+            .chain(value_refs(&self.dated_labels, Ref::DatedLabel))
+            .chain(value_refs(&self.custom_values, Ref::Custom))
+    }
+}
+
+fn value_refs<'a, T>(
+    container: &'a UserValueContainer<T>,
+    variant: fn(&'a T) -> AnyNativeUserValueRef<'a>,
+) -> impl Iterator<Item = (AnyNativeUserValueRef<'a>, Option<SourceId>)> + 'a {
+    container
+        .iter()
+        .map(move |(value, source)| (variant(value), source))
+}
+
+/// Insert after every value with the same or an earlier time, see [`Dated::date_time`]. This keeps
+/// the container sorted by date and by timestamp within a date, and values at the same time in
+/// insertion order.
+fn insert_by_date<T>(
+    container: &mut UserValueContainer<Dated<T>>,
+    value: Dated<T>,
+    source: Option<SourceId>,
+) {
+    let index = container
+        .values()
+        .partition_point(|x| x.date_time() <= value.date_time());
+    container.insert(index, value, source);
+}
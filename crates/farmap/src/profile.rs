@@ -0,0 +1,181 @@
+use crate::core::UserValueContainer;
+use crate::dated::Dated;
+use crate::native_user_value::AnyNativeUserValue;
+use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
+use crate::NativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The profile of a farcaster account. Every field is optional since no single source returns all
+/// of them. Profiles are stored as [`Dated<Profile>`] where the date is the date the profile was
+/// fetched or last changed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Profile {
+    username: Option<String>,
+    display_name: Option<String>,
+    bio: Option<String>,
+    pfp_url: Option<String>,
+    custody_address: Option<String>,
+    registered_at: Option<NaiveDateTime>,
+}
+
+impl Profile {
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn with_display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    pub fn with_bio(mut self, bio: impl Into<String>) -> Self {
+        self.bio = Some(bio.into());
+        self
+    }
+
+    pub fn with_pfp_url(mut self, pfp_url: impl Into<String>) -> Self {
+        self.pfp_url = Some(pfp_url.into());
+        self
+    }
+
+    pub fn with_custody_address(mut self, custody_address: impl Into<String>) -> Self {
+        self.custody_address = Some(custody_address.into());
+        self
+    }
+
+    pub fn with_registered_at(mut self, registered_at: NaiveDateTime) -> Self {
+        self.registered_at = Some(registered_at);
+        self
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    pub fn bio(&self) -> Option<&str> {
+        self.bio.as_deref()
+    }
+
+    pub fn pfp_url(&self) -> Option<&str> {
+        self.pfp_url.as_deref()
+    }
+
+    pub fn custody_address(&self) -> Option<&str> {
+        self.custody_address.as_deref()
+    }
+
+    pub fn registered_at(&self) -> Option<NaiveDateTime> {
+        self.registered_at
+    }
+
+    /// Fill the fields that are missing in this profile with the fields of the other profile.
+    pub fn or(self, other: Profile) -> Self {
+        Self {
+            username: self.username.or(other.username),
+            display_name: self.display_name.or(other.display_name),
+            bio: self.bio.or(other.bio),
+            pfp_url: self.pfp_url.or(other.pfp_url),
+            custody_address: self.custody_address.or(other.custody_address),
+            registered_at: self.registered_at.or(other.registered_at),
+        }
+    }
+}
+
+impl NativeUserValueSeal for Dated<Profile> {}
+
+impl NativeUserValue for Dated<Profile> {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedProfile(self.clone())
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedProfile(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedProfile(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedProfile(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_profiles
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_profiles
+    }
+}
+
+impl UserStoreWithNativeUserValue {
+    /// The profile of the user, built from the stored profiles. Each field is taken from the latest
+    /// profile that has the field. The date is the date of the latest profile.
+    pub fn profile(&self) -> Option<Dated<Profile>> {
+        let profiles = self.user_values_of_kind::<Dated<Profile>>();
+        let date = profiles.last()?.date();
+        let profile = profiles
+            .iter()
+            .rev()
+            .fold(Profile::default(), |profile, x| {
+                profile.or(x.as_inner().clone())
+            });
+        Some(Dated::from(date, profile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_utils::date;
+
+    #[test]
+    fn test_profile_prefers_latest_fields() {
+        let mut user = UserStoreWithNativeUserValue::new(1_u64);
+        assert!(user.profile().is_none());
+        user.add_user_value(Dated::<Profile>::from(
+            date("2025-02-01"),
+            Profile::default().with_display_name("New Name"),
+        ));
+        user.add_user_value(Dated::<Profile>::from(
+            date("2025-01-01"),
+            Profile::default()
+                .with_username("alice")
+                .with_display_name("Old Name"),
+        ));
+        let profile = user.profile().unwrap();
+        assert_eq!(profile.date(), date("2025-02-01"));
+        assert_eq!(profile.username(), Some("alice"));
+        assert_eq!(profile.display_name(), Some("New Name"));
+        assert_eq!(profile.bio(), None);
+    }
+
+    #[test]
+    fn test_dated_profile_round_trip() {
+        let profile = Dated::<Profile>::from(
+            date("2025-01-01"),
+            Profile::default()
+                .with_username("alice")
+                .with_registered_at(date("2023-11-07").and_hms_opt(5, 31, 56).unwrap()),
+        );
+        let json = serde_json::to_string(&profile.as_any_user_value()).unwrap();
+        let deserialized: AnyNativeUserValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.specify::<Dated<Profile>>(), Some(profile));
+    }
+}
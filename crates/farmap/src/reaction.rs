@@ -0,0 +1,99 @@
+use crate::core::UserValueContainer;
+use crate::dated::Dated;
+use crate::native_user_value::AnyNativeUserValue;
+use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
+use crate::Fid;
+use crate::NativeUserValue;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ReactionType {
+    Like,
+    Recast,
+}
+
+/// A like or a recast by the user that stores the value. The target is the cast that the user
+/// reacted to, when the hub provides it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Reaction {
+    reaction_type: ReactionType,
+    target_fid: Option<Fid>,
+    target_cast: Option<String>,
+}
+
+impl Reaction {
+    pub fn new(reaction_type: ReactionType) -> Self {
+        Self {
+            reaction_type,
+            target_fid: None,
+            target_cast: None,
+        }
+    }
+
+    /// Set the target to the cast with the hash by the fid.
+    pub fn with_target(
+        mut self,
+        target_fid: impl Into<Fid>,
+        target_cast: impl Into<String>,
+    ) -> Self {
+        self.target_fid = Some(target_fid.into());
+        self.target_cast = Some(target_cast.into());
+        self
+    }
+
+    pub fn reaction_type(&self) -> ReactionType {
+        self.reaction_type
+    }
+
+    /// The fid of the author of the target cast.
+    pub fn target_fid(&self) -> Option<Fid> {
+        self.target_fid
+    }
+
+    /// The hash of the target cast.
+    pub fn target_cast(&self) -> Option<&str> {
+        self.target_cast.as_deref()
+    }
+}
+
+impl From<ReactionType> for Reaction {
+    fn from(value: ReactionType) -> Self {
+        Self::new(value)
+    }
+}
+
+impl NativeUserValueSeal for Dated<Reaction> {}
+
+impl NativeUserValue for Dated<Reaction> {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedReaction(self.clone())
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::DatedReaction(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedReaction(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::DatedReaction(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_reactions
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_reactions
+    }
+}
@@ -1,15 +1,12 @@
-use crate::cast_type::CastType;
-use crate::dated::Dated;
 use crate::try_from_user::TryFromUser;
 use crate::try_from_user_set::TryFromUserSet;
-use crate::Fid;
 use crate::UserCollectionWithNativeUserValue;
 use crate::UserStoreWithNativeUserValue;
 use crate::UserWithCastData;
 use crate::{UserSet, UsersSubset};
 use thiserror::Error;
 
-/// A set of users that contain at least one [`CastType`].
+/// A set of users that contain at least one [`Cast`](crate::Cast) or [`CastType`](crate::CastType).
 ///
 /// You can typically create this struct via [`TryFrom<UserCollection>`] or [`TryFromUserSet`].
 /// Creation of the set is fallible since the set is not allowed to be empty.
@@ -18,13 +15,37 @@ pub struct SetWithCastData<'a> {
 }
 
 impl<'a> SetWithCastData<'a> {
+    fn from_subset(set: UsersSubset<'a>) -> Option<Self> {
+        (set.user_count() != 0).then_some(Self { set })
+    }
+
+    /// The users that are in either set. Like the set operations of [`UsersSubset`], this assumes
+    /// that both sets are sets of the same collection.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            set: self.set.union(&other.set),
+        }
+    }
+
+    /// The users that are in both sets. Returns None if no user is in both sets.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Self::from_subset(self.set.intersection(&other.set))
+    }
+
+    /// The users that are in this set but not in the other set. Returns None if every user is in
+    /// the other set.
+    pub fn difference(&self, other: &Self) -> Option<Self> {
+        Self::from_subset(self.set.difference(&other.set))
+    }
+
+    /// The users that are in exactly one of the sets. Returns None if the sets have the same users.
+    pub fn symmetric_difference(&self, other: &Self) -> Option<Self> {
+        Self::from_subset(self.set.symmetric_difference(&other.set))
+    }
+
     /// The total casts averaged over the users in the set.
     pub fn average_total_casts(&self) -> f64 {
-        let sum: usize = self
-            .set
-            .iter()
-            .map(|x| x.user_values_of_kind::<Dated<CastType>>().len())
-            .sum();
+        let sum: usize = self.set.iter().map(|x| x.cast_count()).sum();
         sum as f64 / self.set.user_count() as f64
     }
 }
@@ -33,7 +54,7 @@ impl<'a> IntoIterator for SetWithCastData<'a> {
     type Item = UserWithCastData<'a>;
 
     type IntoIter = std::iter::Map<
-        std::collections::hash_map::IntoValues<Fid, &'a UserStoreWithNativeUserValue>,
+        crate::subset::IntoIter<'a>,
         fn(&'a UserStoreWithNativeUserValue) -> UserWithCastData<'a>,
     >;
 
@@ -79,7 +100,7 @@ impl<'a, T: UserSet<'a>> TryFromUserSet<'a, T> for SetWithCastData<'a> {
 impl<'a> TryFrom<&'a UserCollectionWithNativeUserValue> for SetWithCastData<'a> {
     type Error = SetWithCastDataError;
     fn try_from(collection: &'a UserCollectionWithNativeUserValue) -> Result<Self, Self::Error> {
-        let set = UsersSubset::from_filter(collection, |user| user.has::<Dated<CastType>>());
+        let set = UsersSubset::from_filter(collection, |user| user.has_cast_data());
         if set.user_count() != 0 {
             Ok(Self { set })
         } else {
@@ -101,7 +122,10 @@ pub enum SetWithCastDataError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cast_type::CastType;
+    use crate::dated::Dated;
     use crate::user_collection::UserCollectionWithNativeUserValue;
+    use crate::Fid;
     use chrono::NaiveDate;
 
     fn same_date_cast_iter(count: usize, date: NaiveDate) -> impl Iterator<Item = Dated<CastType>> {
@@ -109,11 +133,10 @@ mod tests {
         vec![dated_cast_type].into_iter().cycle().take(count)
     }
 
-    #[allow(unused)]
     #[track_caller]
     fn create_valid_cast_user_set(
         collection: &UserCollectionWithNativeUserValue,
-    ) -> SetWithCastData {
+    ) -> SetWithCastData<'_> {
         collection.try_into().unwrap()
     }
 
@@ -127,6 +150,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_set_operations() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let collection = crate::user_collection::tests::new_collection_from_user_value_iter(
+            same_date_cast_iter(4, date),
+        );
+        let set = create_valid_cast_user_set(&collection);
+        let low = SetWithCastData::from_subset(UsersSubset::from_fid_range(
+            &collection,
+            ..=Fid::from(2_u64),
+        ))
+        .unwrap();
+        let fids = |set: &SetWithCastData| -> Vec<u64> {
+            set.set.iter().map(|user| user.fid().into()).collect()
+        };
+
+        assert_eq!(fids(&low.union(&set)), [1, 2, 3, 4]);
+        assert_eq!(fids(&low.intersection(&set).unwrap()), [1, 2]);
+        assert_eq!(fids(&set.difference(&low).unwrap()), [3, 4]);
+        assert!(low.difference(&set).is_none());
+        assert_eq!(fids(&set.symmetric_difference(&low).unwrap()), [3, 4]);
+    }
+
     mod test_try_from_collection {
         use super::*;
         use crate::user_collection::tests::{dummy_data, new_collection_from_user_value_iter};
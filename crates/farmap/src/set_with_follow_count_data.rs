@@ -0,0 +1,260 @@
+use crate::follow_count::FollowCount;
+use crate::is_user::IsUser;
+use crate::try_from_user::TryFromUser;
+use crate::try_from_user_set::TryFromUserSet;
+use crate::Dated;
+use crate::Fid;
+use crate::SetWithSpamEntries;
+use crate::SpamScore;
+use crate::UserCollectionWithNativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use crate::UserWithFollowCountData;
+use crate::{UserSet, UsersSubset};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// A set of users that contain at least one [`FollowCount`].
+///
+/// You can typically create this struct via [`TryFrom<UserCollection>`] or [`TryFromUserSet`].
+/// Creation of the set is fallible since the set is not allowed to be empty.
+pub struct SetWithFollowCountData<'a> {
+    set: UsersSubset<'a>,
+}
+
+/// Summary statistics of the follower counts of a group of users.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FollowerCountSummary {
+    user_count: usize,
+    mean: f64,
+    median: u64,
+    min: u64,
+    max: u64,
+}
+
+impl FollowerCountSummary {
+    /// Summarize the follower counts. Returns None if there are no counts.
+    pub fn new(mut counts: Vec<u64>) -> Option<Self> {
+        if counts.is_empty() {
+            return None;
+        }
+        counts.sort_unstable();
+        Some(Self {
+            user_count: counts.len(),
+            mean: counts.iter().sum::<u64>() as f64 / counts.len() as f64,
+            median: counts[counts.len() / 2],
+            min: counts[0],
+            max: counts[counts.len() - 1],
+        })
+    }
+
+    pub fn user_count(&self) -> usize {
+        self.user_count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// The upper median of the follower counts.
+    pub fn median(&self) -> u64 {
+        self.median
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+}
+
+impl<'a> SetWithFollowCountData<'a> {
+    /// The follower growth of each user in the set from the start date up to and including the end
+    /// date. See [`UserWithFollowCountData::follower_growth`]. Users without a follower count in the
+    /// period are left out.
+    pub fn follower_growth(&self, start: NaiveDate, end: NaiveDate) -> BTreeMap<Fid, i64> {
+        self.users()
+            .filter_map(|user| {
+                user.follower_growth(start, end)
+                    .map(|growth| (user.fid(), growth))
+            })
+            .collect()
+    }
+
+    /// The latest follower counts of the users in the set grouped by the current spam score of the
+    /// user in the spam set. Users that are not in the spam set are left out.
+    pub fn follower_counts_by_spam_score(
+        &self,
+        spam_set: &SetWithSpamEntries,
+    ) -> HashMap<SpamScore, FollowerCountSummary> {
+        let mut counts: HashMap<SpamScore, Vec<u64>> = HashMap::new();
+        for user in self.users() {
+            let (Some(score), Some(count)) = (
+                spam_set.current_spam_score_of(user.fid()),
+                user.latest_follower_count(),
+            ) else {
+                continue;
+            };
+            counts.entry(score).or_default().push(count);
+        }
+        counts
+            .into_iter()
+            .filter_map(|(score, counts)| FollowerCountSummary::new(counts).map(|x| (score, x)))
+            .collect()
+    }
+
+    fn users(&self) -> impl Iterator<Item = UserWithFollowCountData<'a>> + '_ {
+        self.set
+            .iter()
+            .map(|user| UserWithFollowCountData::try_from(user).unwrap())
+    }
+}
+
+impl<'a> IntoIterator for SetWithFollowCountData<'a> {
+    type Item = UserWithFollowCountData<'a>;
+
+    type IntoIter = std::iter::Map<
+        crate::subset::IntoIter<'a>,
+        fn(&'a UserStoreWithNativeUserValue) -> UserWithFollowCountData<'a>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.set
+            .into_iter()
+            .map(|user| UserWithFollowCountData::try_from(user).unwrap())
+    }
+}
+
+#[allow(refining_impl_trait)]
+impl<'a> UserSet<'a> for SetWithFollowCountData<'a> {
+    fn user_count(&self) -> usize {
+        self.set.user_count()
+    }
+
+    fn user(&'a self, fid: usize) -> Option<UserWithFollowCountData<'a>> {
+        self.set
+            .user(fid)
+            .map(|user| UserWithFollowCountData::try_from(user).unwrap())
+    }
+}
+
+impl<'a, T: UserSet<'a>> TryFromUserSet<'a, T> for SetWithFollowCountData<'a> {
+    type Error = SetWithFollowCountDataError;
+    fn try_from_set(value: T) -> Result<Self, Self::Error> {
+        let results: Vec<UserWithFollowCountData> = value
+            .into_iter()
+            .flat_map(<UserWithFollowCountData as TryFromUser<_>>::try_from_user)
+            .collect();
+        if results.is_empty() {
+            Err(SetWithFollowCountDataError::EmptySetError)
+        } else {
+            let mut set = UsersSubset::default();
+            for user in results {
+                set.add_user(user);
+            }
+            Ok(Self { set })
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a UserCollectionWithNativeUserValue> for SetWithFollowCountData<'a> {
+    type Error = SetWithFollowCountDataError;
+    fn try_from(collection: &'a UserCollectionWithNativeUserValue) -> Result<Self, Self::Error> {
+        let set = UsersSubset::from_filter(collection, |user| user.has::<Dated<FollowCount>>());
+        if set.user_count() != 0 {
+            Ok(Self { set })
+        } else {
+            Err(SetWithFollowCountDataError::EmptySetError)
+        }
+    }
+}
+
+/// This error indicates that the user tried to create a set that would be empty, which is not
+/// allowed.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[non_exhaustive]
+pub enum SetWithFollowCountDataError {
+    #[error("Tried to create a set that would be empty")]
+    EmptySetError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam_score::DatedSpamUpdate;
+    use crate::time_utils::date;
+    use crate::user_collection::tests::dummy_data;
+    use crate::Fidded;
+
+    fn follower_count(fid: u64, followers: u64, date_str: &str) -> Fidded<Dated<FollowCount>> {
+        let count = FollowCount::default().with_followers(followers);
+        Fidded::from((Dated::from(date(date_str), count), Fid::from(fid)))
+    }
+
+    fn follow_count_collection() -> UserCollectionWithNativeUserValue {
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter([
+            follower_count(1, 20, "2025-02-01"),
+            follower_count(1, 10, "2025-01-01"),
+            follower_count(1, 15, "2025-01-15"),
+            follower_count(2, 100, "2025-01-10"),
+            follower_count(3, 50, "2025-03-01"),
+        ]);
+        collection
+    }
+
+    #[test]
+    fn test_err_on_try_from_with_no_follow_count_data() {
+        assert_eq!(
+            SetWithFollowCountData::try_from(&dummy_data()).err(),
+            Some(SetWithFollowCountDataError::EmptySetError)
+        );
+    }
+
+    #[test]
+    fn test_follower_growth() {
+        let collection = follow_count_collection();
+        let set = SetWithFollowCountData::try_from(&collection).unwrap();
+        let growth = set.follower_growth(date("2025-01-01"), date("2025-01-31"));
+        assert_eq!(
+            growth,
+            BTreeMap::from([(Fid::from(1_u64), 5), (Fid::from(2_u64), 0)])
+        );
+        let user = set.user(1).unwrap();
+        assert_eq!(user.follower_count_at_date(date("2025-01-20")), Some(15));
+        assert_eq!(user.latest_follower_count(), Some(20));
+    }
+
+    #[test]
+    fn test_follower_counts_by_spam_score() {
+        let mut collection = follow_count_collection();
+        collection.add_user_value_iter([
+            Fidded::from((
+                DatedSpamUpdate::from(date("2025-01-01"), SpamScore::Zero),
+                Fid::from(1_u64),
+            )),
+            Fidded::from((
+                DatedSpamUpdate::from(date("2025-01-01"), SpamScore::Two),
+                Fid::from(2_u64),
+            )),
+            Fidded::from((
+                DatedSpamUpdate::from(date("2025-01-01"), SpamScore::Two),
+                Fid::from(3_u64),
+            )),
+        ]);
+        let spam_set = SetWithSpamEntries::new(&collection).unwrap();
+        let set = SetWithFollowCountData::try_from(&collection).unwrap();
+        let summaries = set.follower_counts_by_spam_score(&spam_set);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[&SpamScore::Zero].median(), 20);
+        let nonspam = summaries[&SpamScore::Two];
+        assert_eq!(nonspam.user_count(), 2);
+        assert_eq!(nonspam.mean(), 75.0);
+        assert_eq!((nonspam.min(), nonspam.max()), (50, 100));
+    }
+}
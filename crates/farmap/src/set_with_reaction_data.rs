@@ -0,0 +1,207 @@
+use crate::dated::Dated;
+use crate::reaction::Reaction;
+use crate::reaction::ReactionType;
+use crate::try_from_user::TryFromUser;
+use crate::try_from_user_set::TryFromUserSet;
+use crate::Fid;
+use crate::UserCollectionWithNativeUserValue;
+use crate::UserStoreWithNativeUserValue;
+use crate::UserWithReactionData;
+use crate::{UserSet, UsersSubset};
+use chrono::NaiveDate;
+use thiserror::Error;
+
+/// A set of users that contain at least one [`Reaction`].
+///
+/// You can typically create this struct via [`TryFrom<UserCollection>`] or [`TryFromUserSet`].
+/// Creation of the set is fallible since the set is not allowed to be empty.
+pub struct SetWithReactionData<'a> {
+    set: UsersSubset<'a>,
+}
+
+impl<'a> SetWithReactionData<'a> {
+    /// The total reactions averaged over the users in the set.
+    pub fn average_total_reactions(&self) -> f64 {
+        let sum: usize = self
+            .set
+            .iter()
+            .map(|x| x.user_values_of_kind::<Dated<Reaction>>().len())
+            .sum();
+        sum as f64 / self.set.user_count() as f64
+    }
+
+    /// The number of reactions of the type over all the users in the set.
+    pub fn reaction_count(&self, reaction_type: ReactionType) -> usize {
+        self.users()
+            .map(|user| user.reactions_of_type(reaction_type).count())
+            .sum()
+    }
+
+    /// The number of reactions per date over all the users in the set, from the start date up to
+    /// and including the end date.
+    pub fn daily_reaction_counts(&self, start: NaiveDate, end: NaiveDate) -> Vec<Dated<usize>> {
+        start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .map(|date| {
+                let count: usize = self
+                    .users()
+                    .map(|user| user.reaction_count_between(date, date))
+                    .sum();
+                Dated::from(date, count)
+            })
+            .collect()
+    }
+
+    /// The number of reactions from users in the set to casts by the target fid.
+    pub fn reactions_to_fid(&self, target_fid: impl Into<Fid>) -> usize {
+        let target_fid = target_fid.into();
+        self.users()
+            .flat_map(|user| user.reactions())
+            .filter(|reaction| reaction.target_fid() == Some(target_fid))
+            .count()
+    }
+
+    fn users(&self) -> impl Iterator<Item = UserWithReactionData<'a>> + '_ {
+        self.set
+            .iter()
+            .map(|user| UserWithReactionData::try_from(user).unwrap())
+    }
+}
+
+impl<'a> IntoIterator for SetWithReactionData<'a> {
+    type Item = UserWithReactionData<'a>;
+
+    type IntoIter = std::iter::Map<
+        crate::subset::IntoIter<'a>,
+        fn(&'a UserStoreWithNativeUserValue) -> UserWithReactionData<'a>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.set
+            .into_iter()
+            .map(|user| UserWithReactionData::try_from(user).unwrap())
+    }
+}
+
+#[allow(refining_impl_trait)]
+impl<'a> UserSet<'a> for SetWithReactionData<'a> {
+    fn user_count(&self) -> usize {
+        self.set.user_count()
+    }
+
+    fn user(&'a self, fid: usize) -> Option<UserWithReactionData<'a>> {
+        self.set
+            .user(fid)
+            .map(|user| UserWithReactionData::try_from(user).unwrap())
+    }
+}
+
+impl<'a, T: UserSet<'a>> TryFromUserSet<'a, T> for SetWithReactionData<'a> {
+    type Error = SetWithReactionDataError;
+    fn try_from_set(value: T) -> Result<Self, Self::Error> {
+        let results: Vec<UserWithReactionData> = value
+            .into_iter()
+            .flat_map(<UserWithReactionData as TryFromUser<_>>::try_from_user)
+            .collect();
+        if results.is_empty() {
+            Err(SetWithReactionDataError::EmptySetError)
+        } else {
+            let mut set = UsersSubset::default();
+            for user in results {
+                set.add_user(user);
+            }
+            Ok(Self { set })
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a UserCollectionWithNativeUserValue> for SetWithReactionData<'a> {
+    type Error = SetWithReactionDataError;
+    fn try_from(collection: &'a UserCollectionWithNativeUserValue) -> Result<Self, Self::Error> {
+        let set = UsersSubset::from_filter(collection, |user| user.has::<Dated<Reaction>>());
+        if set.user_count() != 0 {
+            Ok(Self { set })
+        } else {
+            Err(SetWithReactionDataError::EmptySetError)
+        }
+    }
+}
+
+/// This error indicates that the user tried to create a set that would be empty, which is not
+/// allowed.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[non_exhaustive]
+pub enum SetWithReactionDataError {
+    #[error("Tried to create a set that would be empty")]
+    EmptySetError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_utils::date;
+    use crate::user_collection::tests::dummy_data;
+    use crate::Fidded;
+
+    fn reaction(fid: u64, reaction: Reaction, date_str: &str) -> Fidded<Dated<Reaction>> {
+        Fidded::from((Dated::from(date(date_str), reaction), Fid::from(fid)))
+    }
+
+    fn reaction_collection() -> UserCollectionWithNativeUserValue {
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter([
+            reaction(
+                1,
+                Reaction::new(ReactionType::Like).with_target(3_u64, "0xa"),
+                "2025-01-02",
+            ),
+            reaction(1, Reaction::new(ReactionType::Recast), "2025-01-01"),
+            reaction(
+                2,
+                Reaction::new(ReactionType::Like).with_target(3_u64, "0xb"),
+                "2025-01-02",
+            ),
+        ]);
+        collection
+    }
+
+    #[test]
+    fn test_err_on_try_from_with_no_reaction_data() {
+        assert_eq!(
+            SetWithReactionData::try_from(&dummy_data()).err(),
+            Some(SetWithReactionDataError::EmptySetError)
+        );
+    }
+
+    #[test]
+    fn test_reaction_counts() {
+        let collection = reaction_collection();
+        let set = SetWithReactionData::try_from(&collection).unwrap();
+        assert_eq!(set.user_count(), 2);
+        assert_eq!(set.average_total_reactions(), 1.5);
+        assert_eq!(set.reaction_count(ReactionType::Like), 2);
+        assert_eq!(set.reaction_count(ReactionType::Recast), 1);
+        assert_eq!(set.reactions_to_fid(3_u64), 2);
+        assert_eq!(
+            set.daily_reaction_counts(date("2025-01-01"), date("2025-01-03"))
+                .into_iter()
+                .map(|x| x.into_inner())
+                .collect::<Vec<_>>(),
+            [1, 2, 0]
+        );
+    }
+
+    #[test]
+    fn test_reactions_are_sorted_and_round_trip() {
+        let collection = reaction_collection();
+        let set = SetWithReactionData::try_from(&collection).unwrap();
+        let user = set.user(1).unwrap();
+        assert_eq!(user.reactions()[0].reaction_type(), ReactionType::Recast);
+        assert_eq!(user.latest_reaction().target_cast(), Some("0xa"));
+
+        let json = serde_json::to_string(&collection).unwrap();
+        let deserialized: UserCollectionWithNativeUserValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, collection);
+    }
+}
@@ -1,11 +1,16 @@
 #![allow(refining_impl_trait)]
+use crate::core::SourceId;
+use crate::core::UserValueContainer;
 use crate::dated::Dated;
 use crate::native_user_value::AnyNativeUserValue;
 use crate::native_user_value::NativeUserValueSeal;
+use crate::native_user_value::NativeUserValueStorage;
 use crate::utils::distribution_from_counts;
 use crate::Collidable;
 use crate::NativeUserValue;
+use chrono::DateTime;
 use chrono::NaiveDate;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -87,6 +92,16 @@ pub struct SpamScoreDistribution {
 }
 
 impl SpamScoreDistribution {
+    /// The distribution of the shares of spam, maybe spam and non spam, in that order. The shares
+    /// are expected to sum to one.
+    pub(crate) fn from_shares(shares: [f32; 3]) -> Self {
+        Self {
+            spam: shares[0],
+            maybe: shares[1],
+            nonspam: shares[2],
+        }
+    }
+
     pub fn spam(&self) -> f32 {
         self.spam
     }
@@ -100,24 +115,52 @@ impl SpamScoreDistribution {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
-pub struct CommitHash(u32);
+/// The full SHA-1 hash of a git commit. The hash is serialized as a 40 character hex string.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct CommitHash([u8; 20]);
 
 impl TryFrom<String> for CommitHash {
     type Error = InvalidHashError;
 
     fn try_from(full_commit_value: String) -> Result<Self, Self::Error> {
-        if full_commit_value.len() != 40 {
-            return Err(InvalidHashError(full_commit_value));
+        CommitHash::try_from(full_commit_value.as_str())
+    }
+}
+
+impl TryFrom<&str> for CommitHash {
+    type Error = InvalidHashError;
+
+    fn try_from(full_commit_value: &str) -> Result<Self, Self::Error> {
+        let invalid = || InvalidHashError(full_commit_value.to_string());
+        if full_commit_value.len() != 40 || !full_commit_value.is_ascii() {
+            return Err(invalid());
         };
 
-        let shortened_commit = full_commit_value.chars().take(4).collect::<String>();
-        let result = u32::from_str_radix(&shortened_commit, 16)
-            .map_err(|_| InvalidHashError(full_commit_value))?;
+        let mut result = [0; 20];
+        for (index, byte) in result.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&full_commit_value[2 * index..2 * index + 2], 16)
+                .map_err(|_| invalid())?;
+        }
         Ok(CommitHash(result))
     }
 }
 
+impl std::fmt::Display for CommitHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<CommitHash> for String {
+    fn from(value: CommitHash) -> Self {
+        value.to_string()
+    }
+}
+
 #[derive(Error, Debug)]
 #[error("invalid hash: {0}")]
 pub struct InvalidHashError(String);
@@ -178,6 +221,19 @@ impl SpamUpdate {
             Self::WithoutSourceCommit(x) => *x,
         }
     }
+
+    /// The same update with the commit it was imported from.
+    pub fn with_source_commit(self, commit: CommitHash) -> Self {
+        Self::WithSourceCommit((self.score(), commit))
+    }
+
+    /// The commit the update was imported from, if it is known.
+    pub fn source_commit(&self) -> Option<CommitHash> {
+        match self {
+            Self::WithSourceCommit(x) => Some(x.1),
+            Self::WithoutSourceCommit(_) => None,
+        }
+    }
 }
 
 impl From<SpamScore> for SpamUpdate {
@@ -188,11 +244,11 @@ impl From<SpamScore> for SpamUpdate {
 
 impl NativeUserValue for SpamUpdate {
     fn as_any_user_value(&self) -> AnyNativeUserValue {
-        AnyNativeUserValue::SpamScore(self.score())
+        AnyNativeUserValue::SpamUpdate(*self)
     }
 
     fn into_any_user_value(self) -> AnyNativeUserValue {
-        AnyNativeUserValue::SpamScore(self.score())
+        AnyNativeUserValue::SpamUpdate(self)
     }
 
     fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
@@ -208,10 +264,52 @@ impl NativeUserValue for SpamUpdate {
             _ => None,
         }
     }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.spam_updates
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.spam_updates
+    }
 }
 
 impl NativeUserValueSeal for SpamUpdate {}
 
+impl NativeUserValue for SpamScore {
+    fn as_any_user_value(&self) -> AnyNativeUserValue {
+        AnyNativeUserValue::SpamScore(*self)
+    }
+
+    fn into_any_user_value(self) -> AnyNativeUserValue {
+        AnyNativeUserValue::SpamScore(self)
+    }
+
+    fn from_any_user_value(any_user_value: AnyNativeUserValue) -> Option<Self> {
+        match any_user_value {
+            AnyNativeUserValue::SpamScore(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_any_user_value_ref(any_user_value: &AnyNativeUserValue) -> Option<&Self> {
+        match any_user_value {
+            AnyNativeUserValue::SpamScore(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.spam_scores
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.spam_scores
+    }
+}
+
+impl NativeUserValueSeal for SpamScore {}
+
 impl NativeUserValue for DatedSpamUpdate {
     fn as_any_user_value(&self) -> AnyNativeUserValue {
         AnyNativeUserValue::DatedSpamUpdate(*self)
@@ -234,6 +332,14 @@ impl NativeUserValue for DatedSpamUpdate {
             _ => None,
         }
     }
+
+    fn stored(storage: &NativeUserValueStorage) -> &UserValueContainer<Self> {
+        &storage.dated_spam_updates
+    }
+
+    fn stored_mut(storage: &mut NativeUserValueStorage) -> &mut UserValueContainer<Self> {
+        &mut storage.dated_spam_updates
+    }
 }
 
 impl From<(SpamScore, NaiveDate)> for DatedSpamUpdate {
@@ -247,10 +353,137 @@ impl NativeUserValueSeal for DatedSpamUpdate {}
 
 impl Collidable for DatedSpamUpdate {
     fn is_collision(&self, other: &Self) -> bool {
-        self.date() == other.date() && self.score() != other.score()
+        self.is_same_time(other) && self.score() != other.score()
     }
 }
 
+/// Run-length compact a spam history. The result is sorted by time and only keeps the updates where
+/// the score changed, along with the latest update. Updates at the same time stay in insertion
+/// order, so the last of them is still the one that counts. Consecutive exact duplicates are
+/// removed, and of several such duplicates the first one is kept along with its source. The score
+/// at any date, as well as the earliest and latest update, are the same for the compacted history
+/// as for the original history.
+pub(crate) fn compact_sourced_spam_updates(
+    updates: impl IntoIterator<Item = (DatedSpamUpdate, Option<SourceId>)>,
+) -> Vec<(DatedSpamUpdate, Option<SourceId>)> {
+    let mut updates: Vec<_> = updates.into_iter().collect();
+    updates.sort_by_key(|(x, _)| x.date_time());
+    updates.dedup_by(|(x, _), (y, _)| x.date_time() == y.date_time() && x.score() == y.score());
+
+    let last_index = updates.len().saturating_sub(1);
+    let mut previous_score: Option<SpamScore> = None;
+    let mut result = Vec::new();
+    for (index, (update, source)) in updates.into_iter().enumerate() {
+        if previous_score != Some(update.score()) || index == last_index {
+            result.push((update, source));
+        }
+        previous_score = Some(update.score());
+    }
+    result
+}
+
+/// The score of the latest update at or before the date in a spam history that is sorted by date.
+/// When several updates share that date, the last of them is used. The lookup is a binary search.
+pub(crate) fn spam_score_at_date(
+    updates: &[DatedSpamUpdate],
+    date: NaiveDate,
+) -> Option<SpamScore> {
+    let index = updates.partition_point(|x| x.date() <= date);
+    index.checked_sub(1).map(|index| updates[index].score())
+}
+
+/// The score of the latest update at or before the time in a spam history that is sorted by time.
+/// Updates without a timestamp are taken to be at the start of their date.
+pub(crate) fn spam_score_at_time(
+    updates: &[DatedSpamUpdate],
+    time: DateTime<Utc>,
+) -> Option<SpamScore> {
+    let index = updates.partition_point(|x| x.date_time() <= time);
+    index.checked_sub(1).map(|index| updates[index].score())
+}
+
+/// The spam score count at each date, computed in a single sweep over the spam histories. Each
+/// history must be sorted by date and the dates must be sorted in ascending order. Every count is
+/// the same as the count from [`spam_score_at_date`] for each history at that date.
+///
+/// Each update adds one to the count of its score for the dates from its own date until the date of
+/// the next update. The counts are collected as differences and summed up at the end.
+#[cfg_attr(feature = "rayon", allow(dead_code))]
+pub(crate) fn spam_score_counts_at_dates<'a>(
+    histories: impl IntoIterator<Item = &'a [DatedSpamUpdate]>,
+    dates: &[NaiveDate],
+) -> Vec<DatedSpamScoreCount> {
+    debug_assert!(dates.is_sorted());
+    let mut differences = vec![[0_i64; 3]; dates.len() + 1];
+    for updates in histories {
+        add_spam_score_differences(&mut differences, updates, dates);
+    }
+    spam_score_counts_from_differences(differences, dates)
+}
+
+/// Like [`spam_score_counts_at_dates`], but the histories are swept in parallel. The counts are
+/// sums of integers, so they are the same as the counts of the sequential sweep.
+#[cfg(feature = "rayon")]
+pub(crate) fn par_spam_score_counts_at_dates<'a>(
+    histories: impl rayon::iter::ParallelIterator<Item = &'a [DatedSpamUpdate]>,
+    dates: &[NaiveDate],
+) -> Vec<DatedSpamScoreCount> {
+    use rayon::iter::ParallelIterator;
+    debug_assert!(dates.is_sorted());
+    let zero = || vec![[0_i64; 3]; dates.len() + 1];
+    let differences = histories
+        .fold(zero, |mut differences, updates| {
+            add_spam_score_differences(&mut differences, updates, dates);
+            differences
+        })
+        .reduce(zero, |mut differences, other| {
+            for (difference, other) in differences.iter_mut().zip(other) {
+                for (x, y) in difference.iter_mut().zip(other) {
+                    *x += y;
+                }
+            }
+            differences
+        });
+    spam_score_counts_from_differences(differences, dates)
+}
+
+/// Add the score of the history at each date as a difference at the first date with the score and
+/// at the first date after it.
+fn add_spam_score_differences(
+    differences: &mut [[i64; 3]],
+    updates: &[DatedSpamUpdate],
+    dates: &[NaiveDate],
+) {
+    for (index, update) in updates.iter().enumerate() {
+        let start = dates.partition_point(|x| *x < update.date());
+        let end = updates.get(index + 1).map_or(dates.len(), |next| {
+            dates.partition_point(|x| *x < next.date())
+        });
+        if start < end {
+            differences[start][update.score() as usize] += 1;
+            differences[end][update.score() as usize] -= 1;
+        }
+    }
+}
+
+fn spam_score_counts_from_differences(
+    differences: Vec<[i64; 3]>,
+    dates: &[NaiveDate],
+) -> Vec<DatedSpamScoreCount> {
+    let mut current = [0_i64; 3];
+    dates
+        .iter()
+        .zip(differences)
+        .map(|(date, difference)| {
+            for (count, difference) in current.iter_mut().zip(difference) {
+                *count += difference;
+            }
+            let count: [u64; 3] = current.map(|x| x as u64);
+            DatedSpamScoreCount::from(*date, count)
+        })
+        .collect()
+}
+
 #[derive(Error, Debug)]
 #[error("trying to create a SpamEntries from an empty struct")]
 pub struct EmptyEntriesError;
@@ -273,6 +506,248 @@ pub mod tests {
         assert!(SpamScore::try_from(100).is_err());
     }
 
+    #[test]
+    fn test_commit_hashes_keep_the_full_sha() {
+        let first = "5d1c2d9f0e7a4b3c8d6e1f2a3b4c5d6e7f8a9b0c";
+        let second = "5d1c000000000000000000000000000000000000";
+        let hash = CommitHash::try_from(first).unwrap();
+        assert_ne!(hash, CommitHash::try_from(second).unwrap());
+        assert_eq!(hash.to_string(), first);
+
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{first}\""));
+        assert_eq!(serde_json::from_str::<CommitHash>(&json).unwrap(), hash);
+        assert!(CommitHash::try_from("5d1c").is_err());
+        assert!(CommitHash::try_from("z".repeat(40)).is_err());
+    }
+
+    mod spam_score_at_date {
+        use super::*;
+        use crate::time_utils::date;
+
+        fn history() -> Vec<DatedSpamUpdate> {
+            vec![
+                DatedSpamUpdate::from(date("2025-01-01"), SpamScore::Zero),
+                DatedSpamUpdate::from(date("2025-01-03"), SpamScore::One),
+                DatedSpamUpdate::from(date("2025-01-03"), SpamScore::Two),
+                DatedSpamUpdate::from(date("2025-01-05"), SpamScore::One),
+            ]
+        }
+
+        #[test]
+        fn test_lookups() {
+            let history = history();
+            assert_eq!(spam_score_at_date(&history, date("2024-12-31")), None);
+            assert_eq!(
+                spam_score_at_date(&history, date("2025-01-01")),
+                Some(SpamScore::Zero)
+            );
+            assert_eq!(
+                spam_score_at_date(&history, date("2025-01-04")),
+                Some(SpamScore::Two)
+            );
+            assert_eq!(
+                spam_score_at_date(&history, date("2030-01-01")),
+                Some(SpamScore::One)
+            );
+        }
+
+        #[test]
+        fn test_counts_at_dates() {
+            let history = history();
+            let other = vec![DatedSpamUpdate::from(date("2025-01-02"), SpamScore::Zero)];
+            let dates = [
+                date("2024-12-31"),
+                date("2025-01-01"),
+                date("2025-01-03"),
+                date("2025-01-06"),
+            ];
+            let counts: Vec<[u64; 3]> =
+                spam_score_counts_at_dates([history.as_slice(), other.as_slice()], &dates)
+                    .into_iter()
+                    .map(|x| x.into_inner().into())
+                    .collect();
+            assert_eq!(counts, [[0, 0, 0], [1, 0, 0], [1, 0, 1], [1, 1, 0]]);
+        }
+    }
+
+    mod spam_score_at_time {
+        use super::*;
+        use crate::time_utils::date;
+        use crate::CollisionPolicy;
+        use crate::Fid;
+        use crate::Fidded;
+        use crate::UserCollectionWithNativeUserValue;
+
+        fn update_at(hour: u32, score: SpamScore) -> DatedSpamUpdate {
+            let time = date("2025-01-01")
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+                .and_utc();
+            DatedSpamUpdate::from_timestamp(time, score)
+        }
+
+        #[test]
+        fn test_same_day_updates_at_different_times_are_not_collisions() {
+            let morning = update_at(8, SpamScore::One);
+            let evening = update_at(20, SpamScore::Two);
+            assert!(!morning.is_collision(&evening));
+            assert!(morning.is_collision(&update_at(8, SpamScore::Two)));
+
+            let date_only = DatedSpamUpdate::from(date("2025-01-01"), SpamScore::Zero);
+            assert!(date_only.is_collision(&evening));
+        }
+
+        #[test]
+        fn test_same_day_updates_are_ordered_by_time() {
+            let mut collection = UserCollectionWithNativeUserValue::default();
+            let report = collection.try_add_user_value_iter(
+                [
+                    Fidded::from((update_at(20, SpamScore::Two), Fid::from(1_u64))),
+                    Fidded::from((update_at(8, SpamScore::One), Fid::from(1_u64))),
+                ],
+                CollisionPolicy::Reject,
+            );
+            assert!(report.is_empty());
+            let user = collection.user(1_u64).unwrap();
+            let updates = user.user_values_of_kind::<DatedSpamUpdate>();
+            assert_eq!(
+                updates,
+                [update_at(8, SpamScore::One), update_at(20, SpamScore::Two)]
+            );
+
+            let at_hour = |hour| {
+                date("2025-01-01")
+                    .and_hms_opt(hour, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            };
+            assert_eq!(spam_score_at_time(updates, at_hour(7)), None);
+            assert_eq!(
+                spam_score_at_time(updates, at_hour(12)),
+                Some(SpamScore::One)
+            );
+            assert_eq!(
+                spam_score_at_time(updates, at_hour(20)),
+                Some(SpamScore::Two)
+            );
+            assert_eq!(
+                spam_score_at_date(updates, date("2025-01-01")),
+                Some(SpamScore::Two)
+            );
+        }
+
+        #[test]
+        fn test_updates_without_timestamp_still_deserialize() {
+            let update = DatedSpamUpdate::from(date("2025-01-01"), SpamScore::One);
+            let json = serde_json::to_string(&update).unwrap();
+            assert!(!json.contains("timestamp"));
+            let deserialized = serde_json::from_str::<DatedSpamUpdate>(&json).unwrap();
+            assert_eq!(deserialized.timestamp(), None);
+            assert_eq!(deserialized, update);
+
+            let timestamped = update_at(8, SpamScore::One);
+            let json = serde_json::to_string(&timestamped).unwrap();
+            assert_eq!(
+                serde_json::from_str::<DatedSpamUpdate>(&json).unwrap(),
+                timestamped
+            );
+        }
+    }
+
+    mod compact_spam_updates {
+        use super::*;
+        use crate::core::Source;
+        use crate::time_utils::date;
+
+        fn compact_spam_updates(
+            updates: impl IntoIterator<Item = DatedSpamUpdate>,
+        ) -> Vec<DatedSpamUpdate> {
+            compact_sourced_spam_updates(updates.into_iter().map(|x| (x, None)))
+                .into_iter()
+                .map(|(update, _)| update)
+                .collect()
+        }
+
+        fn history(scores: &[(usize, &str)]) -> Vec<DatedSpamUpdate> {
+            scores
+                .iter()
+                .map(|(score, date_str)| {
+                    DatedSpamUpdate::from(date(date_str), SpamScore::try_from(*score).unwrap())
+                })
+                .collect()
+        }
+
+        #[track_caller]
+        fn check_compaction(input: &[(usize, &str)], expected: &[(usize, &str)]) {
+            assert_eq!(compact_spam_updates(history(input)), history(expected));
+        }
+
+        #[test]
+        fn test_empty() {
+            check_compaction(&[], &[]);
+        }
+
+        #[test]
+        fn test_single_update() {
+            check_compaction(&[(1, "2025-01-01")], &[(1, "2025-01-01")]);
+        }
+
+        #[test]
+        fn test_unchanged_scores_keep_first_and_latest() {
+            check_compaction(
+                &[(1, "2025-01-01"), (1, "2025-01-02"), (1, "2025-01-03")],
+                &[(1, "2025-01-01"), (1, "2025-01-03")],
+            );
+        }
+
+        #[test]
+        fn test_unsorted_with_duplicates() {
+            check_compaction(
+                &[
+                    (2, "2025-01-05"),
+                    (1, "2025-01-01"),
+                    (1, "2025-01-02"),
+                    (1, "2025-01-01"),
+                    (2, "2025-01-03"),
+                    (2, "2025-01-04"),
+                ],
+                &[(1, "2025-01-01"), (2, "2025-01-03"), (2, "2025-01-05")],
+            );
+        }
+
+        #[test]
+        fn test_conflicting_updates_keep_insertion_order() {
+            let input = history(&[(2, "2025-01-01"), (0, "2025-01-01"), (1, "2025-01-02")]);
+            let compacted = compact_spam_updates(input.clone());
+            assert_eq!(compacted, input);
+            assert_eq!(
+                spam_score_at_date(&compacted, date("2025-01-01")),
+                Some(SpamScore::Zero)
+            );
+        }
+
+        #[test]
+        fn test_sources_are_kept() {
+            let first = Source::new("first").id();
+            let second = Source::new("second").id();
+            let update = |date_str| DatedSpamUpdate::from(date(date_str), SpamScore::One);
+            let compacted = compact_sourced_spam_updates([
+                (update("2025-01-02"), Some(second)),
+                (update("2025-01-01"), Some(first)),
+                (update("2025-01-01"), Some(second)),
+                (update("2025-01-03"), None),
+            ]);
+            assert_eq!(
+                compacted,
+                [
+                    (update("2025-01-01"), Some(first)),
+                    (update("2025-01-03"), None)
+                ]
+            );
+        }
+    }
+
     #[test]
     pub fn basic_spam_score_count_test() {
         let count = basic_spam_score_count();
@@ -1,102 +1,420 @@
+use crate::core::AnyUserValue;
+use crate::core::UserCollection;
+use crate::core::UserStore;
+use crate::fid_set::word_contains;
+use crate::fid_set::word_range;
+use crate::fid_set::FidSet;
 use crate::is_user::IsUser;
-use crate::user_collection::UserCollectionWithNativeUserValue;
+use crate::AnyNativeUserValue;
 use crate::Fid;
-use crate::UserStoreWithNativeUserValue;
+use itertools::Either;
+use itertools::EitherOrBoth;
+use itertools::Itertools;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::ops::RangeBounds;
+use std::sync::Arc;
 
-#[derive(Clone, Debug, Default, PartialEq)]
-pub struct UsersSubset<'a> {
-    map: HashMap<Fid, &'a UserStoreWithNativeUserValue>,
+/// A subset of the users in a [`UserCollection`].
+///
+/// The type parameter T is the [`AnyUserValue`] of the collection the subset is created from. It
+/// defaults to [`AnyNativeUserValue`].
+///
+/// The users are ordered by fid. Iteration is always in ascending fid order.
+///
+/// A subset that is created from a collection refers to the collection and stores its users as a
+/// sparse bitmap over their fids. The users are found by walking the users of the collection in the
+/// range of each word of the bitmap, and set operations on two subsets of the same collection are
+/// done word by word on the bitmaps, so neither looks up or allocates per user. Subsets that are
+/// built from maps or from users of different collections keep their own map of users instead.
+pub struct UsersSubset<'a, T: AnyUserValue = AnyNativeUserValue> {
+    users: Users<'a, T>,
+    members: FidSet,
 }
 
-impl<'a> UsersSubset<'a> {
-    pub fn from_filter<F>(users: &'a UserCollectionWithNativeUserValue, filter: F) -> Self
+/// The users that the fids of a [`UsersSubset`] refer to.
+enum Users<'a, T: AnyUserValue> {
+    Collection(&'a UserCollection<T>),
+    Detached(Arc<BTreeMap<Fid, &'a UserStore<T>>>),
+}
+
+impl<'a, T: AnyUserValue> Users<'a, T> {
+    fn get(&self, fid: Fid) -> Option<&'a UserStore<T>> {
+        match self {
+            Self::Collection(collection) => collection.data().get(&fid),
+            Self::Detached(map) => map.get(&fid).copied(),
+        }
+    }
+
+    /// The users of the fids in the word, found by walking the users in the range of the word
+    /// instead of looking up every fid.
+    fn word_users(&self, word: (u64, u64)) -> impl Iterator<Item = &'a UserStore<T>> + '_ {
+        let users = match *self {
+            Self::Collection(collection) => Either::Left(
+                collection
+                    .data()
+                    .range(word_range(word))
+                    .map(|(fid, user)| (*fid, user)),
+            ),
+            Self::Detached(ref map) => {
+                Either::Right(map.range(word_range(word)).map(|(fid, user)| (*fid, *user)))
+            }
+        };
+        users
+            .filter(move |(fid, _)| word_contains(word, *fid))
+            .map(|(_, user)| user)
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Collection(x), Self::Collection(y)) => std::ptr::eq(*x, *y),
+            (Self::Detached(x), Self::Detached(y)) => Arc::ptr_eq(x, y),
+            _ => false,
+        }
+    }
+}
+
+impl<T: AnyUserValue> Clone for Users<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Collection(collection) => Self::Collection(collection),
+            Self::Detached(map) => Self::Detached(Arc::clone(map)),
+        }
+    }
+}
+
+impl<T: AnyUserValue> Clone for UsersSubset<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            users: self.users.clone(),
+            members: self.members.clone(),
+        }
+    }
+}
+
+impl<T: AnyUserValue> Default for UsersSubset<'_, T> {
+    fn default() -> Self {
+        Self {
+            users: Users::Detached(Arc::default()),
+            members: FidSet::new(),
+        }
+    }
+}
+
+impl<T: AnyUserValue> std::fmt::Debug for UsersSubset<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UsersSubset")
+            .field("fids", &self.members)
+            .finish()
+    }
+}
+
+/// Two subsets are equal if they have the same fids and the users with those fids are equal.
+impl<T: AnyUserValue> PartialEq for UsersSubset<'_, T>
+where
+    UserStore<T>: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.members == other.members
+            && (self.users.is_same(&other.users)
+                || self
+                    .iter()
+                    .zip(other.iter())
+                    .all(|(x, y)| std::ptr::eq(x, y) || x == y))
+    }
+}
+
+impl<'a, T: AnyUserValue> UsersSubset<'a, T> {
+    fn from_collection(users: &'a UserCollection<T>, members: FidSet) -> Self {
+        Self {
+            users: Users::Collection(users),
+            members,
+        }
+    }
+
+    fn from_map(map: BTreeMap<Fid, &'a UserStore<T>>) -> Self {
+        Self {
+            members: map.keys().copied().collect(),
+            users: Users::Detached(Arc::new(map)),
+        }
+    }
+
+    fn get(&self, fid: Fid) -> &'a UserStore<T> {
+        self.users
+            .get(fid)
+            .expect("the fids of a subset refer to its users")
+    }
+
+    pub fn from_filter<F>(users: &'a UserCollection<T>, filter: F) -> Self
     where
-        F: Fn(&UserStoreWithNativeUserValue) -> bool,
+        F: Fn(&UserStore<T>) -> bool,
     {
-        let filtered_map: HashMap<Fid, &'a UserStoreWithNativeUserValue> = users
+        let members = users
             .iter()
             .filter(|user| filter(user))
-            .map(|user| (user.fid(), user))
+            .map(|user| user.fid())
             .collect();
+        Self::from_collection(users, members)
+    }
+
+    /// Like [`Self::from_filter`], but the filter is applied to the users in parallel, which
+    /// requires the filter to be [`Sync`].
+    #[cfg(feature = "rayon")]
+    pub fn par_from_filter<F>(users: &'a UserCollection<T>, filter: F) -> Self
+    where
+        F: Fn(&UserStore<T>) -> bool + Sync,
+        UserStore<T>: Sync,
+    {
+        use rayon::prelude::*;
+        let members = users
+            .data()
+            .par_iter()
+            .filter(|(_, user)| filter(user))
+            .fold(FidSet::new, |mut members, (fid, _)| {
+                members.insert(*fid);
+                members
+            })
+            .reduce(FidSet::new, |mut members, other| {
+                members.union_with(&other);
+                members
+            });
+        Self::from_collection(users, members)
+    }
 
-        Self { map: filtered_map }
+    /// Create a subset of the users with a fid in the range.
+    pub fn from_fid_range(users: &'a UserCollection<T>, range: impl RangeBounds<Fid>) -> Self {
+        let members = users.range(range).map(|user| user.fid()).collect();
+        Self::from_collection(users, members)
     }
 
     /// apply filter to existing subset and mutate subset.
     pub fn filter<F>(&mut self, filter: F)
     where
-        F: Fn(&UserStoreWithNativeUserValue) -> bool,
+        F: Fn(&UserStore<T>) -> bool,
     {
-        self.map = self
-            .map
-            .values()
+        self.members = self
+            .iter()
             .filter(|user| filter(user))
-            .map(|user| (user.fid(), *user))
-            .collect::<HashMap<Fid, &UserStoreWithNativeUserValue>>();
+            .map(|user| user.fid())
+            .collect();
     }
 
     /// return a new struct with filter applied
     pub fn filtered<F>(&self, filter: F) -> Self
     where
-        F: Fn(&UserStoreWithNativeUserValue) -> bool,
+        F: Fn(&UserStore<T>) -> bool,
     {
-        let mut new = self.clone();
-        new.filter(filter);
-        new
+        let mut set = self.clone();
+        set.filter(filter);
+        set
     }
 
-    pub fn into_map(self) -> HashMap<Fid, &'a UserStoreWithNativeUserValue> {
-        self.map
+    /// return a new struct with only the users with a fid in the range.
+    pub fn fid_range(&self, range: impl RangeBounds<Fid>) -> Self {
+        let mut set = self.clone();
+        set.members.retain_range(range);
+        set
     }
 
-    pub fn drop_fid(&mut self, fid: impl Into<Fid>) -> Option<&UserStoreWithNativeUserValue> {
-        let fid = fid.into();
-        self.map.get(&fid).map(|v| &**v)
+    pub fn into_map(self) -> BTreeMap<Fid, &'a UserStore<T>> {
+        self.iter().map(|user| (user.fid(), user)).collect()
     }
 
-    pub fn add_user(&mut self, user: impl IsUser<'a>) {
-        self.map.insert(user.fid(), user.user());
+    /// Remove the user with the fid from the subset. Returns the user if it was in the subset.
+    pub fn drop_fid(&mut self, fid: impl Into<Fid>) -> Option<&'a UserStore<T>> {
+        let fid = fid.into();
+        self.members.remove(fid).then(|| self.get(fid))
     }
 
     pub fn user_count(&self) -> usize {
-        self.map.len()
+        self.members.len()
     }
 
-    pub fn user(&self, fid: impl Into<Fid>) -> Option<&UserStoreWithNativeUserValue> {
+    pub fn contains(&self, fid: impl Into<Fid>) -> bool {
+        self.members.contains(fid.into())
+    }
+
+    pub fn user(&self, fid: impl Into<Fid>) -> Option<&'a UserStore<T>> {
         let fid = fid.into();
-        self.map.get(&fid).copied()
+        self.members.contains(fid).then(|| self.get(fid))
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &UserStoreWithNativeUserValue> {
-        self.map.values().copied()
+    /// All users in ascending fid order.
+    pub fn iter(&self) -> impl Iterator<Item = &'a UserStore<T>> + '_ {
+        self.range(..)
+    }
+
+    /// The users that are in either subset.
+    ///
+    /// The set operations assume that both subsets are subsets of the same collection. Subsets of
+    /// the same collection are combined on their bitmaps. Other subsets are merged in fid order,
+    /// which takes linear time in the size of the subsets.
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, FidSet::union_with, |_| true)
+    }
+
+    /// The users that are in both subsets.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, FidSet::intersect_with, |x| x.is_both())
+    }
+
+    /// The users that are in this subset but not in the other subset.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, FidSet::difference_with, |x| {
+            matches!(x, EitherOrBoth::Left(_))
+        })
+    }
+
+    /// The users that are in exactly one of the subsets.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.combine(other, FidSet::symmetric_difference_with, |x| !x.is_both())
+    }
+
+    /// Combine the bitmaps with the operation if the subsets refer to the same users, and merge
+    /// the users with the filter otherwise. An empty subset can be combined with any subset.
+    fn combine<F>(&self, other: &Self, operation: fn(&mut FidSet, &FidSet), keep: F) -> Self
+    where
+        F: Fn(&EitherOrBoth<(Fid, &'a UserStore<T>)>) -> bool,
+    {
+        let users = if self.users.is_same(&other.users) || other.members.is_empty() {
+            &self.users
+        } else if self.members.is_empty() {
+            &other.users
+        } else {
+            return self.merge(other, keep);
+        };
+        let mut members = self.members.clone();
+        operation(&mut members, &other.members);
+        Self {
+            users: users.clone(),
+            members,
+        }
+    }
+
+    fn merge<F>(&self, other: &Self, keep: F) -> Self
+    where
+        F: Fn(&EitherOrBoth<(Fid, &'a UserStore<T>)>) -> bool,
+    {
+        let map = self
+            .iter()
+            .map(|user| (user.fid(), user))
+            .merge_join_by(
+                other.iter().map(|user| (user.fid(), user)),
+                |(x, _), (y, _)| x.cmp(y),
+            )
+            .filter(keep)
+            .map(|x| {
+                x.reduce(|left, right| {
+                    debug_assert!(
+                        std::ptr::eq(left.1, right.1),
+                        "set operation on subsets of different collections"
+                    );
+                    left
+                })
+            })
+            .collect();
+        Self::from_map(map)
+    }
+
+    /// All users as a parallel iterator.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_iter(
+        &self,
+    ) -> impl rayon::iter::ParallelIterator<Item = &'a UserStore<T>> + '_
+    where
+        UserStore<T>: Sync,
+    {
+        use rayon::prelude::*;
+        self.members
+            .par_words()
+            .flat_map_iter(|word| self.users.word_users(word))
+    }
+
+    /// The users with a fid in the range, in ascending fid order.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<Fid>,
+    ) -> impl Iterator<Item = &'a UserStore<T>> + '_ {
+        self.members
+            .words(range)
+            .flat_map(|word| self.users.word_users(word))
+    }
+}
+
+impl<'a> UsersSubset<'a> {
+    /// Add the user to the subset. A user of the collection the subset refers to only sets its
+    /// bit, while any other user makes the subset keep its own map of users.
+    pub fn add_user(&mut self, user: impl IsUser<'a>) {
+        let fid = user.fid();
+        let user = user.user();
+        let is_known = self
+            .users
+            .get(fid)
+            .is_some_and(|known| std::ptr::eq(known, user));
+        if !is_known {
+            if let Users::Collection(_) = self.users {
+                let map = self.iter().map(|user| (user.fid(), user)).collect();
+                self.users = Users::Detached(Arc::new(map));
+            }
+            if let Users::Detached(map) = &mut self.users {
+                Arc::make_mut(map).insert(fid, user);
+            }
+        }
+        self.members.insert(fid);
     }
 }
 
-impl<'a> IntoIterator for UsersSubset<'a> {
-    type Item = &'a UserStoreWithNativeUserValue;
-    type IntoIter = std::collections::hash_map::IntoValues<Fid, &'a UserStoreWithNativeUserValue>;
+/// An iterator over the users of a [`UsersSubset`] in ascending fid order.
+pub struct IntoIter<'a, T: AnyUserValue = AnyNativeUserValue> {
+    users: Users<'a, T>,
+    words: std::vec::IntoIter<(u64, u64)>,
+    word_users: VecDeque<&'a UserStore<T>>,
+}
+
+impl<'a, T: AnyUserValue> Iterator for IntoIter<'a, T> {
+    type Item = &'a UserStore<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(user) = self.word_users.pop_front() {
+                return Some(user);
+            }
+            let word = self.words.next()?;
+            self.word_users.extend(self.users.word_users(word));
+        }
+    }
+}
+
+impl<'a, T: AnyUserValue> IntoIterator for UsersSubset<'a, T> {
+    type Item = &'a UserStore<T>;
+    type IntoIter = IntoIter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.map.into_values()
+        IntoIter {
+            users: self.users,
+            words: self.members.into_words(),
+            word_users: VecDeque::new(),
+        }
     }
 }
 
-impl<'a> From<HashMap<Fid, &'a UserStoreWithNativeUserValue>> for UsersSubset<'a> {
-    fn from(value: HashMap<Fid, &'a UserStoreWithNativeUserValue>) -> Self {
-        Self { map: value }
+impl<'a, T: AnyUserValue> From<BTreeMap<Fid, &'a UserStore<T>>> for UsersSubset<'a, T> {
+    fn from(value: BTreeMap<Fid, &'a UserStore<T>>) -> Self {
+        Self::from_map(value)
     }
 }
 
-impl<'a> From<&'a UserCollectionWithNativeUserValue> for UsersSubset<'a> {
-    fn from(users: &'a UserCollectionWithNativeUserValue) -> Self {
-        let map: HashMap<Fid, &UserStoreWithNativeUserValue> = users
-            .data()
-            .iter()
-            .map(|(key, value)| (*key, value))
-            .collect();
+impl<'a, T: AnyUserValue> From<HashMap<Fid, &'a UserStore<T>>> for UsersSubset<'a, T> {
+    fn from(value: HashMap<Fid, &'a UserStore<T>>) -> Self {
+        Self::from_map(value.into_iter().collect())
+    }
+}
 
-        Self { map }
+impl<'a, T: AnyUserValue> From<&'a UserCollection<T>> for UsersSubset<'a, T> {
+    fn from(users: &'a UserCollection<T>) -> Self {
+        let members = users.data().keys().copied().collect();
+        Self::from_collection(users, members)
     }
 }
 
@@ -105,6 +423,8 @@ mod tests {
 
     use super::*;
     use crate::user_collection::tests::dummy_data;
+    use crate::UserCollectionWithNativeUserValue;
+    use crate::UserStoreWithNativeUserValue;
 
     #[test]
     fn empty_set() {
@@ -113,7 +433,7 @@ mod tests {
         assert_eq!(set.user_count(), 0);
     }
 
-    pub fn create_set(collection: &UserCollectionWithNativeUserValue) -> UsersSubset {
+    pub fn create_set(collection: &UserCollectionWithNativeUserValue) -> UsersSubset<'_> {
         UsersSubset::from(collection)
     }
 
@@ -161,6 +481,190 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_subset_of_external_user_values() {
+        use crate::core::tests::{external_collection, Note};
+
+        let collection = external_collection();
+        let set = UsersSubset::from_filter(&collection, |user| !user.has::<Note>());
+        assert_eq!(set.user_count(), 2);
+        assert!(set.user(2_u64).is_none());
+        assert_eq!(
+            set.filtered(|user| user.fid() == Fid::from(1_u64))
+                .user_count(),
+            1
+        );
+    }
+
+    mod test_fid_range {
+        use super::*;
+        use crate::core::tests::external_collection;
+
+        fn fids<T: AnyUserValue>(set: &UsersSubset<T>) -> Vec<u64> {
+            set.iter().map(|user| user.fid().into()).collect()
+        }
+
+        #[test]
+        fn test_iteration_is_in_ascending_fid_order() {
+            let collection = external_collection();
+            assert_eq!(fids(&UsersSubset::from(&collection)), [1, 2, 3]);
+        }
+
+        #[test]
+        fn test_fid_range() {
+            let collection = external_collection();
+            let set = UsersSubset::from(&collection);
+            assert_eq!(fids(&set.fid_range(Fid::from(2_u64)..)), [2, 3]);
+            assert_eq!(fids(&set.fid_range(..Fid::from(2_u64))), [1]);
+            assert_eq!(
+                fids(&UsersSubset::from_fid_range(
+                    &collection,
+                    Fid::from(2_u64)..=Fid::from(3_u64)
+                )),
+                [2, 3]
+            );
+            assert_eq!(set.range(Fid::from(4_u64)..).count(), 0);
+        }
+
+        #[test]
+        fn test_sparse_fids() {
+            use crate::user_collection::tests::add_user::check_add_user;
+            use crate::user_store_with_native_user_value::tests::create_new_user;
+
+            let mut collection = UserCollectionWithNativeUserValue::default();
+            for fid in [1, 64, 1 << 40, u64::MAX] {
+                check_add_user(&mut collection, create_new_user(fid));
+            }
+            let set = UsersSubset::from(&collection);
+            assert_eq!(fids(&set), [1, 64, 1 << 40, u64::MAX]);
+            assert_eq!(
+                fids(&set.fid_range(Fid::from(2_u64)..)),
+                [64, 1 << 40, u64::MAX]
+            );
+
+            let filtered = set.filtered(|user| u64::from(user.fid()) > 1);
+            assert_eq!(
+                filtered
+                    .into_iter()
+                    .map(|user| u64::from(user.fid()))
+                    .collect::<Vec<_>>(),
+                [64, 1 << 40, u64::MAX]
+            );
+
+            let map: BTreeMap<Fid, &UserStoreWithNativeUserValue> =
+                collection.iter().map(|user| (user.fid(), user)).collect();
+            let detached = UsersSubset::from(map);
+            assert_eq!(
+                detached
+                    .into_iter()
+                    .map(|user| u64::from(user.fid()))
+                    .collect::<Vec<_>>(),
+                [1, 64, 1 << 40, u64::MAX]
+            );
+        }
+    }
+
+    mod test_set_operations {
+        use super::*;
+        use crate::core::tests::{external_collection, Note};
+
+        fn fids<T: AnyUserValue>(set: &UsersSubset<T>) -> Vec<u64> {
+            set.iter().map(|user| user.fid().into()).collect()
+        }
+
+        #[test]
+        fn test_set_operations() {
+            let collection = external_collection();
+            let low = UsersSubset::from_fid_range(&collection, ..=Fid::from(2_u64));
+            let with_note = UsersSubset::from_filter(&collection, |user| user.has::<Note>());
+            let high = UsersSubset::from_fid_range(&collection, Fid::from(3_u64)..);
+
+            assert_eq!(fids(&low.union(&high)), [1, 2, 3]);
+            assert_eq!(fids(&low.intersection(&with_note)), [2]);
+            assert_eq!(fids(&low.intersection(&high)), Vec::<u64>::new());
+            assert_eq!(fids(&low.difference(&with_note)), [1]);
+            assert_eq!(fids(&with_note.difference(&low)), Vec::<u64>::new());
+            assert_eq!(
+                fids(&low.symmetric_difference(&with_note.union(&high))),
+                [1, 3]
+            );
+            assert_eq!(low.union(&UsersSubset::default()), low);
+        }
+
+        #[test]
+        fn test_set_operations_on_subsets_with_different_users() {
+            let collection = external_collection();
+            let all = UsersSubset::from(&collection);
+            let detached = UsersSubset::from(all.fid_range(Fid::from(2_u64)..).into_map());
+
+            assert_eq!(fids(&detached), [2, 3]);
+            assert_eq!(fids(&all.difference(&detached)), [1]);
+            assert_eq!(fids(&detached.intersection(&all)), [2, 3]);
+            assert_eq!(detached.union(&all), all);
+        }
+    }
+
+    mod test_membership {
+        use super::*;
+        use crate::spam_score::DatedSpamUpdate;
+        use crate::time_utils::date;
+        use crate::user_collection::tests::new_collection_from_user_value_iter;
+        use crate::SpamScore;
+        use crate::UserWithSpamData;
+
+        #[test]
+        fn test_drop_fid_removes_the_user() {
+            let users = dummy_data();
+            let mut set = create_set(&users);
+            assert_eq!(
+                set.drop_fid(1_u64).map(|user| user.fid()),
+                Some(1_u64.into())
+            );
+            assert!(set.drop_fid(1_u64).is_none());
+            assert!(!set.contains(1_u64));
+            assert_eq!(set.user_count(), 1);
+        }
+
+        #[test]
+        fn test_add_user_from_the_collection_and_from_elsewhere() {
+            let users = dummy_data();
+            let other = new_collection_from_user_value_iter(
+                [SpamScore::One, SpamScore::Two, SpamScore::Zero]
+                    .map(|score| DatedSpamUpdate::from(date("2025-01-01"), score)),
+            );
+            let with_spam_data = |user| UserWithSpamData::try_from(user).unwrap();
+            let mut set = UsersSubset::from_fid_range(&users, ..Fid::from(2_u64));
+            set.add_user(with_spam_data(users.user(2_u64).unwrap()));
+            assert_eq!(set, create_set(&users));
+
+            set.add_user(with_spam_data(other.user(3_u64).unwrap()));
+            assert_eq!(set.user_count(), 3);
+            assert!(std::ptr::eq(
+                set.user(3_u64).unwrap(),
+                other.user(3_u64).unwrap()
+            ));
+            assert!(std::ptr::eq(
+                set.user(1_u64).unwrap(),
+                users.user(1_u64).unwrap()
+            ));
+        }
+
+        #[test]
+        fn test_filtered_keeps_the_original() {
+            let users = dummy_data();
+            let set = create_set(&users);
+            let filtered = set.filtered(|user| user.fid() == Fid::from(2_u64));
+            assert_eq!(set.user_count(), 2);
+            assert_eq!(
+                filtered
+                    .into_iter()
+                    .map(|user| user.fid())
+                    .collect::<Vec<_>>(),
+                [Fid::from(2_u64)]
+            );
+        }
+    }
+
     mod test_filter {
         use super::*;
 
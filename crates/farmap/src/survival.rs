@@ -0,0 +1,361 @@
+//! Survival analysis of spam scores.
+//!
+//! A spell is the time that a user keeps a spam score, from the update that gave the user the
+//! score to the first update with another score. Spells that have not ended at the censoring date
+//! are censored. [`SurvivalCurves`] estimates a Kaplan-Meier curve of the spells of each score,
+//! which gives the share of users that still have the score a number of days after they got it.
+use crate::SetWithSpamEntries;
+use crate::SpamScore;
+use crate::UserWithSpamData;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The spells of each user that are included in the curves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Spells {
+    /// Only the spell of the first spam score of each user, which ends at the first change.
+    #[default]
+    First,
+    /// Every spell of each user.
+    All,
+}
+
+/// The time that a user kept a spam score.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Spell {
+    score: SpamScore,
+    days: u64,
+    changed: bool,
+}
+
+/// The spells of the user that start at or before the censoring date. Consecutive updates with the
+/// same score are part of the same spell.
+fn user_spells(user: &UserWithSpamData, spells: Spells, censoring_date: NaiveDate) -> Vec<Spell> {
+    let updates = user.dated_spam_updates();
+    let mut result = Vec::new();
+    let mut start = 0;
+    while let Some(first) = updates.get(start).filter(|x| x.date() <= censoring_date) {
+        let change = updates[start..]
+            .iter()
+            .position(|x| x.score() != first.score())
+            .map(|offset| start + offset)
+            .filter(|index| updates[*index].date() <= censoring_date);
+        let end_date = change.map_or(censoring_date, |index| updates[index].date());
+        result.push(Spell {
+            score: first.score(),
+            days: (end_date - first.date()).num_days().unsigned_abs(),
+            changed: change.is_some(),
+        });
+
+        match change {
+            Some(index) if spells == Spells::All => start = index,
+            _ => break,
+        }
+    }
+    result
+}
+
+/// Kaplan-Meier curves of how long users keep each spam score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurvivalCurves {
+    spells: Spells,
+    censoring_date: NaiveDate,
+    curves: Vec<SurvivalCurve>,
+}
+
+impl SurvivalCurves {
+    /// The curves of the users in the set, censored at the latest spam score date of the set.
+    pub fn new(set: &SetWithSpamEntries, spells: Spells) -> Self {
+        Self::with_censoring_date(set, spells, set.latest_spam_score_date())
+    }
+
+    /// The curves of the users in the set, censored at the date. Changes after the date are not
+    /// counted and spells that start after the date are left out.
+    pub fn with_censoring_date(
+        set: &SetWithSpamEntries,
+        spells: Spells,
+        censoring_date: NaiveDate,
+    ) -> Self {
+        let mut spells_by_score: [Vec<Spell>; 3] = Default::default();
+        for user in set.subset().iter() {
+            let user = UserWithSpamData::try_from(user)
+                .expect("SetWithSpamEntries should only contain users that have spam data");
+            for spell in user_spells(&user, spells, censoring_date) {
+                spells_by_score[spell.score as usize].push(spell);
+            }
+        }
+
+        let curves = spells_by_score
+            .into_iter()
+            .filter(|spells| !spells.is_empty())
+            .map(|spells| SurvivalCurve::from_spells(spells[0].score, spells))
+            .collect();
+
+        Self {
+            spells,
+            censoring_date,
+            curves,
+        }
+    }
+
+    pub fn spells(&self) -> Spells {
+        self.spells
+    }
+
+    pub fn censoring_date(&self) -> NaiveDate {
+        self.censoring_date
+    }
+
+    /// The curve of the spells of the score. None if no spell has the score.
+    pub fn curve(&self, score: SpamScore) -> Option<&SurvivalCurve> {
+        self.curves.iter().find(|curve| curve.score == score)
+    }
+
+    /// The curves in the order spam, maybe spam and non spam, leaving out scores without spells.
+    pub fn curves(&self) -> &[SurvivalCurve] {
+        &self.curves
+    }
+}
+
+/// A Kaplan-Meier estimate of the share of spells of a score that have not changed a number of
+/// days after they started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurvivalCurve {
+    score: SpamScore,
+    spell_count: u64,
+    change_count: u64,
+    censored_count: u64,
+    median_days: Option<u64>,
+    points: Vec<SurvivalPoint>,
+}
+
+impl SurvivalCurve {
+    fn from_spells(score: SpamScore, spells: Vec<Spell>) -> Self {
+        let mut by_days: BTreeMap<u64, (u64, u64)> = BTreeMap::new();
+        for spell in &spells {
+            let (changes, censored) = by_days.entry(spell.days).or_default();
+            if spell.changed {
+                *changes += 1;
+            } else {
+                *censored += 1;
+            }
+        }
+
+        let spell_count = spells.len() as u64;
+        let mut at_risk = spell_count;
+        let mut survival = 1.0;
+        let points: Vec<SurvivalPoint> = by_days
+            .into_iter()
+            .map(|(days, (changes, censored))| {
+                survival *= 1.0 - changes as f64 / at_risk as f64;
+                let point = SurvivalPoint {
+                    days,
+                    at_risk,
+                    changes,
+                    censored,
+                    survival,
+                };
+                at_risk -= changes + censored;
+                point
+            })
+            .collect();
+
+        let change_count = spells.iter().filter(|spell| spell.changed).count() as u64;
+        Self {
+            score,
+            spell_count,
+            change_count,
+            censored_count: spell_count - change_count,
+            median_days: points
+                .iter()
+                .find(|point| point.survival <= 0.5)
+                .map(|point| point.days),
+            points,
+        }
+    }
+
+    pub fn score(&self) -> SpamScore {
+        self.score
+    }
+
+    pub fn spell_count(&self) -> u64 {
+        self.spell_count
+    }
+
+    /// The number of spells that ended with a change of score.
+    pub fn change_count(&self) -> u64 {
+        self.change_count
+    }
+
+    /// The number of spells that had not changed at the censoring date.
+    pub fn censored_count(&self) -> u64 {
+        self.censored_count
+    }
+
+    /// The number of days until half of the spells have changed. None if the curve never drops to
+    /// one half.
+    pub fn median_days(&self) -> Option<u64> {
+        self.median_days
+    }
+
+    /// The points of the curve at every number of days at which a spell changed or was censored,
+    /// in ascending order.
+    pub fn points(&self) -> &[SurvivalPoint] {
+        &self.points
+    }
+
+    /// The estimated share of spells that have not changed the number of days after they started.
+    pub fn survival_at(&self, days: u64) -> f64 {
+        self.points
+            .iter()
+            .take_while(|point| point.days <= days)
+            .last()
+            .map_or(1.0, |point| point.survival)
+    }
+
+    /// The estimated probability that a spell changes within the number of days.
+    pub fn change_within(&self, days: u64) -> f64 {
+        1.0 - self.survival_at(days)
+    }
+}
+
+/// A step of a [`SurvivalCurve`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SurvivalPoint {
+    days: u64,
+    at_risk: u64,
+    changes: u64,
+    censored: u64,
+    survival: f64,
+}
+
+impl SurvivalPoint {
+    /// The number of days since the start of the spells.
+    pub fn days(&self) -> u64 {
+        self.days
+    }
+
+    /// The number of spells that had neither changed nor been censored before the days.
+    pub fn at_risk(&self) -> u64 {
+        self.at_risk
+    }
+
+    /// The number of spells that changed after exactly the days.
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    /// The number of spells that were censored after exactly the days.
+    pub fn censored(&self) -> u64 {
+        self.censored
+    }
+
+    /// The estimated share of spells that have not changed after the days.
+    pub fn survival(&self) -> f64 {
+        self.survival
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam_score::DatedSpamUpdate;
+    use crate::time_utils::date;
+    use crate::Fid;
+    use crate::Fidded;
+    use crate::UserCollectionWithNativeUserValue;
+
+    fn collection() -> UserCollectionWithNativeUserValue {
+        let updates = [
+            (1_u64, "2025-01-01", SpamScore::One),
+            (1, "2025-01-11", SpamScore::Two),
+            (2, "2025-01-01", SpamScore::One),
+            (2, "2025-01-05", SpamScore::One),
+            (2, "2025-01-21", SpamScore::Zero),
+            (3, "2025-01-01", SpamScore::One),
+            (4, "2025-01-16", SpamScore::One),
+            (4, "2025-01-26", SpamScore::Zero),
+            (5, "2025-01-01", SpamScore::Two),
+            (5, "2025-01-11", SpamScore::One),
+            (5, "2025-01-31", SpamScore::Two),
+        ];
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter(updates.map(|(fid, day, score)| {
+            Fidded::from((DatedSpamUpdate::from(date(day), score), Fid::from(fid)))
+        }));
+        collection
+    }
+
+    #[test]
+    fn test_first_spells() {
+        let collection = collection();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let curves = SurvivalCurves::new(&set, Spells::First);
+        assert_eq!(curves.censoring_date(), date("2025-01-31"));
+
+        // maybe spam spells: 10 days changed, 20 days changed, 30 days censored, 10 days changed.
+        let maybe = curves.curve(SpamScore::One).unwrap();
+        assert_eq!(maybe.spell_count(), 4);
+        assert_eq!(maybe.change_count(), 3);
+        assert_eq!(maybe.censored_count(), 1);
+        let steps: Vec<(u64, u64, f64)> = maybe
+            .points()
+            .iter()
+            .map(|point| (point.days(), point.at_risk(), point.survival()))
+            .collect();
+        assert_eq!(steps, [(10, 4, 0.5), (20, 2, 0.25), (30, 1, 0.25)]);
+        assert_eq!(maybe.median_days(), Some(10));
+        assert_eq!(maybe.survival_at(9), 1.0);
+        assert_eq!(maybe.change_within(15), 0.5);
+
+        let non_spam = curves.curve(SpamScore::Two).unwrap();
+        assert_eq!(non_spam.spell_count(), 1);
+        assert!(curves.curve(SpamScore::Zero).is_none());
+    }
+
+    #[test]
+    fn test_all_spells() {
+        let collection = collection();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let curves = SurvivalCurves::new(&set, Spells::All);
+
+        let maybe = curves.curve(SpamScore::One).unwrap();
+        assert_eq!(maybe.spell_count(), 5);
+        assert_eq!(maybe.change_count(), 4);
+        assert_eq!(maybe.points()[1].days(), 20);
+        assert_eq!(maybe.points()[1].changes(), 2);
+
+        // the spam spells of fid 2 and 4 have not changed, and the last non spam spell of fid 5
+        // starts at the censoring date.
+        let spam = curves.curve(SpamScore::Zero).unwrap();
+        assert_eq!(spam.spell_count(), 2);
+        assert_eq!(spam.censored_count(), 2);
+        assert_eq!(spam.median_days(), None);
+        let non_spam = curves.curve(SpamScore::Two).unwrap();
+        assert_eq!(non_spam.spell_count(), 3);
+        assert_eq!(non_spam.points()[0].days(), 0);
+        assert_eq!(non_spam.points()[0].censored(), 1);
+    }
+
+    #[test]
+    fn test_censoring_date() {
+        let collection = collection();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let curves = SurvivalCurves::with_censoring_date(&set, Spells::All, date("2025-01-15"));
+
+        // fid 4 starts after the censoring date and fid 2 changes after it.
+        let maybe = curves.curve(SpamScore::One).unwrap();
+        assert_eq!(maybe.spell_count(), 4);
+        assert_eq!(maybe.change_count(), 1);
+        assert_eq!(maybe.points().last().unwrap().days(), 14);
+
+        let json = serde_json::to_string(&curves).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SurvivalCurves>(&json).unwrap(),
+            curves
+        );
+    }
+}
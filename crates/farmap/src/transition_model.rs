@@ -0,0 +1,351 @@
+//! A Markov model of how users move between spam scores.
+//!
+//! [`TransitionModel`] counts the moves between spam scores of the users in a
+//! [`SetWithSpamEntries`] over every period of its history. The pooled [`TransitionMatrix`] of the
+//! model projects a [`SpamScoreDistribution`] forward period by period and gives the stationary
+//! distribution that the scores settle at if the transition probabilities stay the same.
+use crate::cohort::CohortPeriod;
+use crate::spam_score::DatedSpamScoreDistribution;
+use crate::Dated;
+use crate::SetWithSpamEntries;
+use crate::SpamScore;
+use crate::SpamScoreDistribution;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The largest difference between two iterations at which the stationary distribution is taken
+/// to have converged.
+const STATIONARY_TOLERANCE: f64 = 1e-12;
+
+/// The maximum number of iterations when computing the stationary distribution.
+const STATIONARY_MAX_ITERATIONS: usize = 100_000;
+
+/// The probabilities of moving from one spam score to another in one period, estimated from the
+/// number of users that made each move.
+///
+/// Rows are the spam score at the start of the period and columns the spam score at the end of the
+/// period. If no user had a spam score at the start of a period, the users with that score are
+/// assumed to keep it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionMatrix {
+    counts: [[u64; 3]; 3],
+    probabilities: [[f64; 3]; 3],
+}
+
+impl TransitionMatrix {
+    /// Estimate the probabilities from the counts of moves, where `counts[from][to]` is the number
+    /// of users that moved from the score `from` to the score `to`.
+    pub fn from_counts(counts: [[u64; 3]; 3]) -> Self {
+        let mut probabilities = [[0.0; 3]; 3];
+        for (from, row) in counts.iter().enumerate() {
+            let total: u64 = row.iter().sum();
+            if total == 0 {
+                probabilities[from][from] = 1.0;
+            } else {
+                for (to, count) in row.iter().enumerate() {
+                    probabilities[from][to] = *count as f64 / total as f64;
+                }
+            }
+        }
+        Self {
+            counts,
+            probabilities,
+        }
+    }
+
+    /// The number of users that moved from one score to the other.
+    pub fn count(&self, from: SpamScore, to: SpamScore) -> u64 {
+        self.counts[from as usize][to as usize]
+    }
+
+    /// The number of users that had the score at the start of a period.
+    pub fn observed(&self, from: SpamScore) -> u64 {
+        self.counts[from as usize].iter().sum()
+    }
+
+    /// The probability of moving from one score to the other in one period.
+    pub fn probability(&self, from: SpamScore, to: SpamScore) -> f64 {
+        self.probabilities[from as usize][to as usize]
+    }
+
+    /// The distribution one period after the distribution.
+    pub fn apply(&self, distribution: SpamScoreDistribution) -> SpamScoreDistribution {
+        let shares: [f32; 3] = distribution.into();
+        let shares = self.step(shares.map(f64::from));
+        SpamScoreDistribution::from_shares(shares.map(|share| share as f32))
+    }
+
+    /// The distribution that does not change when the matrix is applied to it.
+    ///
+    /// The distribution is found by iterating the lazy chain, which stays put with probability one
+    /// half and otherwise follows the matrix. It has the same stationary distributions as the
+    /// matrix but also converges for matrices that cycle between scores. If the matrix has more
+    /// than one stationary distribution, for example when neither spam nor non spam users ever
+    /// change score, the result is the one that an even distribution converges to.
+    pub fn stationary_distribution(&self) -> SpamScoreDistribution {
+        let mut shares = [1.0 / 3.0; 3];
+        for _ in 0..STATIONARY_MAX_ITERATIONS {
+            let step = self.step(shares);
+            let next: [f64; 3] = std::array::from_fn(|index| (shares[index] + step[index]) / 2.0);
+            let difference = next
+                .iter()
+                .zip(shares)
+                .map(|(x, y)| (x - y).abs())
+                .fold(0.0, f64::max);
+            shares = next;
+            if difference < STATIONARY_TOLERANCE {
+                break;
+            }
+        }
+        SpamScoreDistribution::from_shares(shares.map(|share| share as f32))
+    }
+
+    fn step(&self, shares: [f64; 3]) -> [f64; 3] {
+        std::array::from_fn(|to| {
+            shares
+                .iter()
+                .zip(&self.probabilities)
+                .map(|(share, row)| share * row[to])
+                .sum()
+        })
+    }
+}
+
+/// The transitions between spam scores in every period of the history of a set of users.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionModel {
+    period: CohortPeriod,
+    matrix: TransitionMatrix,
+    windows: Vec<Dated<TransitionMatrix>>,
+    latest_spam_score_date: NaiveDate,
+}
+
+impl TransitionModel {
+    /// Estimate the model from the spam scores of the users at the start of every period between
+    /// the earliest and the latest spam score date in the set. The period that contains the latest
+    /// date is left out since it is not complete. Returns None if the set does not span a complete
+    /// period.
+    pub fn new(set: &SetWithSpamEntries, period: CohortPeriod) -> Option<Self> {
+        let latest_spam_score_date = set.latest_spam_score_date();
+        let mut dates = vec![period.start_of(set.earliest_spam_score_date())];
+        loop {
+            let next = period.next_start(*dates.last().expect("dates is not empty"));
+            if next > latest_spam_score_date {
+                break;
+            }
+            dates.push(next);
+        }
+        if dates.len() < 2 {
+            return None;
+        }
+
+        let counts = set.spam_score_transition_counts(&dates);
+        let mut pooled = [[0; 3]; 3];
+        for window in &counts {
+            for (pooled, count) in pooled.iter_mut().flatten().zip(window.iter().flatten()) {
+                *pooled += count;
+            }
+        }
+
+        let windows = dates
+            .into_iter()
+            .zip(counts)
+            .map(|(date, counts)| Dated::from(date, TransitionMatrix::from_counts(counts)))
+            .collect();
+
+        Some(Self {
+            period,
+            matrix: TransitionMatrix::from_counts(pooled),
+            windows,
+            latest_spam_score_date,
+        })
+    }
+
+    pub fn period(&self) -> CohortPeriod {
+        self.period
+    }
+
+    /// The transitions pooled over every period.
+    pub fn matrix(&self) -> &TransitionMatrix {
+        &self.matrix
+    }
+
+    /// The transitions of each period, dated by the start of the period.
+    pub fn windows(&self) -> &[Dated<TransitionMatrix>] {
+        &self.windows
+    }
+
+    /// The probability of moving from one score to the other in each period, dated by the start of
+    /// the period. The probability is None in periods where no user had the score at the start.
+    pub fn drift(&self, from: SpamScore, to: SpamScore) -> Vec<Dated<Option<f64>>> {
+        self.windows
+            .iter()
+            .map(|window| {
+                let matrix = window.as_inner();
+                let probability = (matrix.observed(from) > 0).then(|| matrix.probability(from, to));
+                Dated::from(window.date(), probability)
+            })
+            .collect()
+    }
+
+    /// Project the distribution a number of periods ahead with the pooled transitions. The
+    /// distribution is taken to be the distribution at the latest spam score date of the set, and
+    /// the projected distributions are dated by the start of each following period.
+    pub fn project(&self, distribution: SpamScoreDistribution, periods: usize) -> Projection {
+        let mut date = self.latest_spam_score_date;
+        let mut distribution = distribution;
+        let distributions = (0..periods)
+            .map(|_| {
+                date = self.period.next_start(date);
+                distribution = self.matrix.apply(distribution);
+                Dated::from(date, distribution)
+            })
+            .collect();
+
+        Projection {
+            distributions,
+            stationary: self.matrix.stationary_distribution(),
+        }
+    }
+}
+
+/// The projected spam score distributions of a [`TransitionModel`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Projection {
+    distributions: Vec<DatedSpamScoreDistribution>,
+    stationary: SpamScoreDistribution,
+}
+
+impl Projection {
+    /// The projected distribution at the start of each period.
+    pub fn distributions(&self) -> &[DatedSpamScoreDistribution] {
+        &self.distributions
+    }
+
+    /// The distribution that the projection converges to.
+    pub fn stationary(&self) -> SpamScoreDistribution {
+        self.stationary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam_score::DatedSpamUpdate;
+    use crate::time_utils::date;
+    use crate::Fid;
+    use crate::Fidded;
+    use crate::UserCollectionWithNativeUserValue;
+
+    #[track_caller]
+    fn assert_shares(distribution: SpamScoreDistribution, expected: [f32; 3]) {
+        let shares: [f32; 3] = distribution.into();
+        for (share, expected) in shares.iter().zip(expected) {
+            assert!(
+                (share - expected).abs() < 1e-4,
+                "{shares:?} is not {expected:?}"
+            );
+        }
+    }
+
+    fn collection() -> UserCollectionWithNativeUserValue {
+        let updates = [
+            (1_u64, "2025-01-01", SpamScore::Two),
+            (1, "2025-02-10", SpamScore::Zero),
+            (2, "2025-01-05", SpamScore::Two),
+            (3, "2025-01-01", SpamScore::One),
+            (3, "2025-03-02", SpamScore::Two),
+            (4, "2025-01-01", SpamScore::Zero),
+            (4, "2025-04-01", SpamScore::Zero),
+        ];
+        let mut collection = UserCollectionWithNativeUserValue::default();
+        collection.add_user_value_iter(updates.map(|(fid, day, score)| {
+            Fidded::from((DatedSpamUpdate::from(date(day), score), Fid::from(fid)))
+        }));
+        collection
+    }
+
+    #[test]
+    fn test_monthly_transitions() {
+        let collection = collection();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let model = TransitionModel::new(&set, CohortPeriod::Month).unwrap();
+
+        assert_eq!(model.windows().len(), 3);
+        let matrix = model.matrix();
+        assert_eq!(matrix.count(SpamScore::Zero, SpamScore::Zero), 4);
+        assert_eq!(matrix.count(SpamScore::Two, SpamScore::Zero), 1);
+        assert_eq!(matrix.observed(SpamScore::Two), 4);
+        assert_eq!(matrix.probability(SpamScore::Two, SpamScore::Two), 0.75);
+        assert_eq!(
+            matrix.probability(SpamScore::One, SpamScore::Two),
+            1.0 / 3.0
+        );
+
+        let drift = |from, to| -> Vec<Option<f64>> {
+            model
+                .drift(from, to)
+                .iter()
+                .map(|x| *x.as_inner())
+                .collect()
+        };
+        assert_eq!(
+            drift(SpamScore::Two, SpamScore::Zero),
+            [Some(0.0), Some(0.5), Some(0.0)]
+        );
+        assert_eq!(
+            drift(SpamScore::One, SpamScore::Two),
+            [Some(0.0), Some(0.0), Some(1.0)]
+        );
+        assert_eq!(
+            model.drift(SpamScore::Two, SpamScore::Two)[1].date(),
+            date("2025-02-01")
+        );
+    }
+
+    #[test]
+    fn test_projection() {
+        let collection = collection();
+        let set = SetWithSpamEntries::new(&collection).unwrap();
+        let model = TransitionModel::new(&set, CohortPeriod::Month).unwrap();
+        let projection = model.project(SpamScoreDistribution::from_shares([0.0, 0.5, 0.5]), 2);
+
+        let first = projection.distributions()[0];
+        assert_eq!(first.date(), date("2025-05-01"));
+        assert_shares(*first.as_inner(), [0.125, 1.0 / 3.0, 0.5 / 3.0 + 0.375]);
+        assert_eq!(projection.distributions()[1].date(), date("2025-06-01"));
+        // no user leaves the spam score, so every user ends up there.
+        assert_shares(projection.stationary(), [1.0, 0.0, 0.0]);
+
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TransitionModel>(&json).unwrap(),
+            model
+        );
+    }
+
+    #[test]
+    fn test_stationary_distribution() {
+        let even = TransitionMatrix::from_counts([[1, 1, 0], [0, 1, 1], [1, 0, 1]]);
+        assert_shares(even.stationary_distribution(), [1.0 / 3.0; 3]);
+
+        let cycle = TransitionMatrix::from_counts([[0, 1, 0], [0, 0, 1], [1, 0, 0]]);
+        assert_shares(cycle.stationary_distribution(), [1.0 / 3.0; 3]);
+
+        let skewed = TransitionMatrix::from_counts([[1, 1, 0], [1, 0, 1], [0, 0, 0]]);
+        assert_eq!(skewed.probability(SpamScore::Two, SpamScore::Two), 1.0);
+        assert_shares(skewed.stationary_distribution(), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_no_complete_period() {
+        let collection = crate::user_collection::tests::dummy_data();
+        let set = SetWithSpamEntries::new(&collection)
+            .unwrap()
+            .filtered(|user| user.fid() == Fid::from(2_u64))
+            .unwrap();
+        assert!(TransitionModel::new(&set, CohortPeriod::Month).is_none());
+        assert!(TransitionModel::new(&set, CohortPeriod::Week).is_none());
+    }
+}
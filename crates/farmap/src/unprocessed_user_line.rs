@@ -1,10 +1,16 @@
+use crate::label::WARPCAST_PROVIDER;
+use crate::spam_score::CommitHash;
 use crate::spam_score::DatedSpamUpdate;
 use crate::spam_score::SpamScoreError;
+use crate::Dated;
 use crate::Fid;
 use crate::Fidded;
+use crate::Label;
+use crate::LabelStream;
 use crate::SpamScore;
 use chrono::DateTime;
 use chrono::NaiveDate;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -26,6 +32,20 @@ impl UnprocessedUserLine {
         self.r#type.fid as usize
     }
 
+    pub fn label_type(&self) -> &str {
+        &self.label_type
+    }
+
+    /// The stream of the label, which is the provider and the label type.
+    pub fn label_stream(&self) -> LabelStream {
+        LabelStream::new(self.provider as u64, self.label_type.clone())
+    }
+
+    /// Returns true if the line is a Warpcast spam label.
+    pub fn is_warpcast_spam(&self) -> bool {
+        self.provider as u64 == WARPCAST_PROVIDER && self.label_type == "spam"
+    }
+
     pub fn label_value(&self) -> usize {
         self.label_value
     }
@@ -35,31 +55,70 @@ impl UnprocessedUserLine {
     }
 
     pub fn date(&self) -> Result<NaiveDate, SpamDataParseError> {
-        if let Some(date) = DateTime::from_timestamp(self.timestamp().try_into().unwrap(), 0) {
-            Ok(date.date_naive())
-        } else {
-            Err(SpamDataParseError::DateError {
+        Ok(self.date_time()?.date_naive())
+    }
+
+    /// The time of the label, which is the timestamp of the line in UTC.
+    pub fn date_time(&self) -> Result<DateTime<Utc>, SpamDataParseError> {
+        i64::try_from(self.timestamp())
+            .ok()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .ok_or(SpamDataParseError::DateError {
                 timestamp: self.timestamp(),
             })
-        }
+    }
+
+    /// Like [`Fidded::<DatedSpamUpdate>::try_from`], but the update records the commit that the line
+    /// was imported from.
+    pub fn try_into_spam_update_from_commit(
+        self,
+        commit: CommitHash,
+    ) -> Result<Fidded<DatedSpamUpdate>, SpamDataParseError> {
+        let fidded = Fidded::<DatedSpamUpdate>::try_from(self)?;
+        let fid = fidded.fid();
+        let update = fidded.unfid();
+        let update = match update.timestamp() {
+            Some(timestamp) => {
+                DatedSpamUpdate::from_timestamp(timestamp, update.with_source_commit(commit))
+            }
+            None => DatedSpamUpdate::from(update.date(), update.with_source_commit(commit)),
+        };
+        Ok(Fidded::from((update, fid)))
     }
 }
 
 impl TryFrom<UnprocessedUserLine> for Fidded<DatedSpamUpdate> {
     type Error = SpamDataParseError;
     fn try_from(value: UnprocessedUserLine) -> Result<Self, Self::Error> {
+        if !value.is_warpcast_spam() {
+            return Err(SpamDataParseError::NotSpamLabel {
+                provider: value.provider,
+                label_type: value.label_type,
+            });
+        }
         let fid = value.fid();
-        let date = value.date()?;
-        value.label_value();
+        let time = value.date_time()?;
         let spam_score = SpamScore::try_from(value.label_value())?;
 
-        let dated_spam_update = DatedSpamUpdate::from(date, spam_score);
+        let dated_spam_update = DatedSpamUpdate::from_timestamp(time, spam_score);
         let fid = Fid::from(fid);
         let fidded: Fidded<DatedSpamUpdate> = Fidded::from((dated_spam_update, fid));
         Ok(fidded)
     }
 }
 
+impl TryFrom<UnprocessedUserLine> for Fidded<Dated<Label>> {
+    type Error = SpamDataParseError;
+    fn try_from(value: UnprocessedUserLine) -> Result<Self, Self::Error> {
+        let time = value.date_time()?;
+        let label = Label::new(value.label_stream(), value.label_value() as u64);
+        Ok(Fidded::from((
+            Dated::from_timestamp(time, label),
+            Fid::from(value.fid()),
+        )))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 struct Type {
     fid: u64,
@@ -72,4 +131,6 @@ pub enum SpamDataParseError {
     SpamScoreError(#[from] SpamScoreError),
     #[error("Timestamp was {0}, which is invalid.", . timestamp)]
     DateError { timestamp: usize },
+    #[error("label of type {label_type} from provider {provider} is not a spam label")]
+    NotSpamLabel { provider: usize, label_type: String },
 }
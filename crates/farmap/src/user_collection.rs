@@ -1,107 +1,117 @@
-use crate::user_collection_serde::UserCollectionSerde;
-use crate::CollectionError;
+use crate::core::UserCollection;
+use crate::core::UserStore;
+use crate::spam_score::CommitHash;
+use crate::spam_score::DatedSpamUpdate;
+use crate::AnyNativeUserValue;
+use crate::AnyNativeUserValueRef;
+use crate::CollisionPolicy;
+use crate::CollisionReport;
 use crate::Fid;
 use crate::HasTag;
-use crate::NativeUserValue;
-use crate::UserStoreWithNativeUserValue;
-use serde::Deserialize;
-use serde::Serialize;
-use std::collections::hash_map::Entry::Vacant;
-use std::collections::HashMap;
-
-#[derive(Default, Debug, PartialEq, Clone, Serialize, Deserialize)]
-#[serde(from = "UserCollectionSerde")]
-#[serde(into = "UserCollectionSerde")]
-pub struct UserCollectionWithNativeUserValue {
-    map: HashMap<Fid, UserStoreWithNativeUserValue>,
-}
+use crate::SourceId;
+use std::collections::HashSet;
 
-impl UserCollectionWithNativeUserValue {
-    /// Returns a vec of all the collision errors, if there are any.
-    pub fn add_user_value_iter<S>(&mut self, values: impl IntoIterator<Item = impl HasTag<Fid, S>>)
-    where
-        S: NativeUserValue,
-    {
-        for value in values {
-            if let Some(user) = self.user_mut(value.tag()) {
-                user.add_user_value(value.untag().1);
-            } else {
-                let mut user = UserStoreWithNativeUserValue::new(value.tag());
-                user.add_user_value(value.untag().1);
-                self.add_user(user).expect("new user cannot collide");
-            }
-        }
-    }
+/// A [`UserCollection`] of users that store [`NativeUserValue`](crate::NativeUserValue)s.
+pub type UserCollectionWithNativeUserValue = UserCollection<AnyNativeUserValue>;
 
-    pub fn user_mut(&mut self, fid: impl Into<Fid>) -> Option<&mut UserStoreWithNativeUserValue> {
-        let fid: Fid = fid.into();
-        self.map.get_mut(&fid)
+impl UserCollectionWithNativeUserValue {
+    /// Run-length compact the spam history of every user in the collection. See
+    /// [`UserStoreWithNativeUserValue::compact_spam_updates`](crate::UserStoreWithNativeUserValue::compact_spam_updates).
+    /// Returns the number of removed updates.
+    pub fn compact_spam_updates(&mut self) -> usize {
+        self.iter_mut()
+            .map(|user| user.compact_spam_updates())
+            .sum()
     }
 
-    #[allow(unused)]
-    pub(crate) fn user_mut_unchecked(
+    /// Add spam updates and compact the spam history of every user that received an update.
+    /// Returns the number of updates that were not stored or were removed by the compaction.
+    pub fn add_compacted_spam_updates(
         &mut self,
-        fid: impl Into<Fid>,
-    ) -> &mut UserStoreWithNativeUserValue {
-        let fid: Fid = fid.into();
-        self.map
-            .get_mut(&fid)
-            .expect("fid {fid} should exist in collection")
-    }
-
-    pub fn user(&self, fid: impl Into<Fid>) -> Option<&UserStoreWithNativeUserValue> {
-        let fid: Fid = fid.into();
-        self.map.get(&fid)
-    }
-
-    pub fn user_count(&self) -> usize {
-        self.map.len()
-    }
-
-    /// Applies a filter to the user data. Use with caution since the data is removed from the
-    /// struct. For most situations it is preferred to create a subset of the data.
-    pub fn apply_filter<F>(&mut self, filter: F)
-    where
-        F: Fn(&UserStoreWithNativeUserValue) -> bool,
-    {
-        let old_map = std::mem::take(&mut self.map);
-        let new_map = old_map
-            .into_values()
-            .filter(|user| filter(user))
-            .map(|user| (user.fid(), user))
-            .collect::<HashMap<Fid, UserStoreWithNativeUserValue>>();
-        self.map = new_map;
-    }
-
-    pub fn iter(&self) -> impl Iterator<Item = &UserStoreWithNativeUserValue> {
-        self.map.values()
+        values: impl IntoIterator<Item: HasTag<Fid, DatedSpamUpdate>>,
+    ) -> usize {
+        let mut fids: HashSet<Fid> = HashSet::new();
+        self.add_user_value_iter(values.into_iter().inspect(|value| {
+            fids.insert(value.tag());
+        }));
+        fids.into_iter()
+            .flat_map(|fid| self.user_mut(fid).map(|user| user.compact_spam_updates()))
+            .sum()
     }
 
-    pub fn data(&self) -> &HashMap<Fid, UserStoreWithNativeUserValue> {
-        &self.map
+    /// Remove every spam update that was imported from the commit. Returns the number of removed
+    /// updates.
+    pub fn remove_spam_updates_from_commit(&mut self, commit: CommitHash) -> usize {
+        self.retain_user_values(|update: &DatedSpamUpdate| update.source_commit() != Some(commit))
     }
 
-    pub fn add_user(&mut self, user: UserStoreWithNativeUserValue) -> Result<(), CollectionError> {
-        let fid: Fid = user.fid();
-        if let Vacant(v) = self.map.entry(fid) {
-            v.insert(user);
-            Ok(())
-        } else {
-            Err(CollectionError::DuplicateUserError)
+    /// Merge another collection into this collection. Users that only exist in the other
+    /// collection are added. Values that are already stored for a user are skipped, follower edges
+    /// are merged with the stored edge of the same follower and the remaining values are added.
+    /// Spam updates are checked for collisions and the policy decides which update is stored, see
+    /// [`UserCollection::try_add_user_value_iter`].
+    pub fn merge(
+        &mut self,
+        other: &UserCollectionWithNativeUserValue,
+        policy: CollisionPolicy,
+    ) -> CollisionReport<DatedSpamUpdate> {
+        for (_, source) in other.sources() {
+            self.register_source(source.clone());
+        }
+        let mut spam_updates = Vec::new();
+        for other_user in other.iter() {
+            let fid = other_user.fid();
+            let missing: Vec<(AnyNativeUserValueRef, Option<SourceId>)> = match self.user(fid) {
+                Some(user) => {
+                    let existing: HashSet<AnyNativeUserValueRef> = user.all_user_values().collect();
+                    other_user
+                        .all_sourced_user_values()
+                        .filter(|(value, _)| !existing.contains(value))
+                        .collect()
+                }
+                None => {
+                    self.add_user(UserStore::new(fid))
+                        .expect("new user cannot collide");
+                    other_user.all_sourced_user_values().collect()
+                }
+            };
+            let user = self.user_mut_unchecked(fid);
+            for (value, source) in missing {
+                match value {
+                    AnyNativeUserValueRef::DatedSpamUpdate(x) => {
+                        spam_updates.push((fid, *x, source))
+                    }
+                    AnyNativeUserValueRef::FollowerEdge(x) => {
+                        user.merge_follower_edge(*x, source);
+                    }
+                    value => user.add_any_user_value(value.into(), source),
+                }
+            }
         }
+        self.try_add_user_values_with_sources(spam_updates, policy)
     }
-}
 
-impl From<HashMap<Fid, UserStoreWithNativeUserValue>> for UserCollectionWithNativeUserValue {
-    fn from(value: HashMap<Fid, UserStoreWithNativeUserValue>) -> Self {
-        Self { map: value }
+    /// A new collection with the users and values of both collections. See [`Self::merge`].
+    pub fn union(
+        &self,
+        other: &UserCollectionWithNativeUserValue,
+        policy: CollisionPolicy,
+    ) -> (
+        UserCollectionWithNativeUserValue,
+        CollisionReport<DatedSpamUpdate>,
+    ) {
+        let mut union = self.clone();
+        let report = union.merge(other, policy);
+        (union, report)
     }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::Fidded;
     use crate::NativeUserValue;
+    use crate::UserStoreWithNativeUserValue;
     use std::path::PathBuf;
 
     #[test]
@@ -158,6 +168,68 @@ pub mod tests {
         UserCollectionWithNativeUserValue::default()
     }
 
+    mod serde {
+        use super::*;
+        use crate::time_utils::date;
+        use crate::SpamScore;
+
+        #[test]
+        fn test_load_v1_data() {
+            let v1 = std::fs::read_to_string("data/dummy-data_db_v1.json").unwrap();
+            assert!(v1.starts_with(r#"{"version":1,"#));
+            let collection: UserCollectionWithNativeUserValue = serde_json::from_str(&v1).unwrap();
+
+            let scores = |fid: u64| -> Vec<(chrono::NaiveDate, SpamScore)> {
+                collection
+                    .user(fid)
+                    .unwrap()
+                    .user_values_of_kind::<DatedSpamUpdate>()
+                    .iter()
+                    .map(|x| (x.date(), x.score()))
+                    .collect()
+            };
+            assert_eq!(collection.user_count(), 2);
+            assert_eq!(
+                scores(1),
+                [
+                    (date("2024-01-01"), SpamScore::One),
+                    (date("2025-01-23"), SpamScore::Zero)
+                ]
+            );
+            assert_eq!(scores(2), [(date("2025-01-23"), SpamScore::Two)]);
+
+            // v1 data is saved in the latest format, which loads to the same collection.
+            let json = serde_json::to_string(&collection).unwrap();
+            assert!(!json.starts_with(r#"{"version":1,"#));
+            let deserialized: UserCollectionWithNativeUserValue =
+                serde_json::from_str(&json).unwrap();
+            assert_eq!(collection, deserialized);
+        }
+
+        #[test]
+        fn test_round_trip_of_typed_storage() {
+            let mut collection = empty_collection();
+            collection.add_user_value_iter([
+                TestUserValue {
+                    value: DatedSpamUpdate::from(date("2025-02-01"), SpamScore::Two),
+                    fid: 1_u64.into(),
+                },
+                TestUserValue {
+                    value: DatedSpamUpdate::from(date("2025-01-01"), SpamScore::Zero),
+                    fid: 1_u64.into(),
+                },
+            ]);
+            collection.add_user_value_iter([TestUserValue {
+                value: SpamScore::One,
+                fid: 2_u64.into(),
+            }]);
+            let json = serde_json::to_string(&collection).unwrap();
+            let deserialized: UserCollectionWithNativeUserValue =
+                serde_json::from_str(&json).unwrap();
+            assert_eq!(collection, deserialized);
+        }
+    }
+
     pub fn dummy_data() -> UserCollectionWithNativeUserValue {
         let db_path = PathBuf::from("data/dummy-data_db_v1.json");
         serde_json::from_str(&std::fs::read_to_string(db_path).unwrap()).unwrap()
@@ -201,4 +273,277 @@ pub mod tests {
             check_user_count(&collection, 2);
         }
     }
+
+    mod try_add_user_value_iter {
+        use super::*;
+        use crate::spam_score::DatedSpamUpdate;
+        use crate::time_utils::date;
+        use crate::CollisionPolicy;
+        use crate::CollisionReport;
+        use crate::Fidded;
+        use crate::SpamScore;
+
+        fn update(fid: u64, score: SpamScore, date_str: &str) -> Fidded<DatedSpamUpdate> {
+            Fidded::from((DatedSpamUpdate::from(date(date_str), score), Fid::from(fid)))
+        }
+
+        fn colliding_updates() -> Vec<Fidded<DatedSpamUpdate>> {
+            vec![
+                update(1, SpamScore::One, "2025-01-01"),
+                update(2, SpamScore::Two, "2025-01-01"),
+                update(1, SpamScore::One, "2025-01-02"),
+                update(1, SpamScore::Zero, "2025-01-01"),
+            ]
+        }
+
+        fn ingest(
+            policy: CollisionPolicy,
+        ) -> (
+            UserCollectionWithNativeUserValue,
+            CollisionReport<DatedSpamUpdate>,
+        ) {
+            let mut collection = empty_collection();
+            let report = collection.try_add_user_value_iter(colliding_updates(), policy);
+            (collection, report)
+        }
+
+        #[track_caller]
+        fn check_scores_at_first_date(
+            collection: &UserCollectionWithNativeUserValue,
+            expected: &[SpamScore],
+        ) {
+            let scores: Vec<SpamScore> = collection
+                .user(1_u64)
+                .unwrap()
+                .user_values_of_kind::<DatedSpamUpdate>()
+                .iter()
+                .filter(|x| x.date() == date("2025-01-01"))
+                .map(|x| x.score())
+                .collect();
+            assert_eq!(scores, expected);
+        }
+
+        #[test]
+        fn test_report_on_collision() {
+            let (collection, report) = ingest(CollisionPolicy::KeepFirst);
+            assert_eq!(collection.user_count(), 2);
+            assert_eq!(report.added_count(), 3);
+            assert_eq!(report.fids(), vec![Fid::from(1_u64)]);
+            let collision = report.collisions().first().unwrap();
+            assert_eq!(collision.date(), date("2025-01-01"));
+            assert_eq!(collision.existing().score(), SpamScore::One);
+            assert_eq!(collision.incoming().score(), SpamScore::Zero);
+        }
+
+        #[test]
+        fn test_keep_first() {
+            let (collection, _) = ingest(CollisionPolicy::KeepFirst);
+            check_scores_at_first_date(&collection, &[SpamScore::One]);
+        }
+
+        #[test]
+        fn test_keep_last() {
+            let (collection, _) = ingest(CollisionPolicy::KeepLast);
+            check_scores_at_first_date(&collection, &[SpamScore::Zero]);
+        }
+
+        #[test]
+        fn test_reject() {
+            let (collection, report) = ingest(CollisionPolicy::Reject);
+            check_scores_at_first_date(&collection, &[]);
+            assert_eq!(report.collisions().len(), 1);
+            assert_eq!(report.added_count(), 2);
+        }
+
+        #[test]
+        fn test_reject_sticks_for_the_batch() {
+            let mut collection = empty_collection();
+            let updates = [
+                update(1, SpamScore::One, "2025-01-01"),
+                update(1, SpamScore::Zero, "2025-01-01"),
+                update(1, SpamScore::Two, "2025-01-01"),
+            ];
+            let report = collection.try_add_user_value_iter(updates, CollisionPolicy::Reject);
+            assert!(collection.user(1_u64).is_none());
+            assert_eq!(report.collisions().len(), 3);
+            assert_eq!(report.added_count(), 0);
+        }
+
+        #[test]
+        fn test_no_report_on_duplicate() {
+            let mut collection = empty_collection();
+            let updates = [
+                update(1, SpamScore::One, "2025-01-01"),
+                update(1, SpamScore::One, "2025-01-01"),
+            ];
+            let report = collection.try_add_user_value_iter(updates, CollisionPolicy::Reject);
+            assert!(report.is_empty());
+        }
+    }
+
+    mod compact_spam_updates {
+        use super::*;
+        use crate::time_utils::date;
+        use crate::Fidded;
+        use crate::SpamScore;
+
+        fn daily_snapshots(fid: u64, days: u64) -> Vec<Fidded<DatedSpamUpdate>> {
+            (0..days)
+                .map(|n| {
+                    let date = date("2025-01-01")
+                        .checked_add_days(chrono::Days::new(n))
+                        .unwrap();
+                    Fidded::from((DatedSpamUpdate::from(date, SpamScore::Two), Fid::from(fid)))
+                })
+                .collect()
+        }
+
+        fn spam_update_count(collection: &UserCollectionWithNativeUserValue) -> usize {
+            collection
+                .iter()
+                .map(|user| user.user_values_of_kind::<DatedSpamUpdate>().len())
+                .sum()
+        }
+
+        #[test]
+        fn test_compact_existing_collection() {
+            let mut collection = empty_collection();
+            collection.add_user_value_iter(daily_snapshots(1, 10));
+            collection.add_user_value_iter(daily_snapshots(2, 1));
+            assert_eq!(collection.compact_spam_updates(), 8);
+            assert_eq!(spam_update_count(&collection), 3);
+        }
+
+        #[test]
+        fn test_compact_on_ingest() {
+            let mut collection = empty_collection();
+            assert_eq!(
+                collection.add_compacted_spam_updates(daily_snapshots(1, 10)),
+                8
+            );
+            assert_eq!(
+                collection.add_compacted_spam_updates(daily_snapshots(2, 5)),
+                3
+            );
+            assert_eq!(spam_update_count(&collection), 4);
+        }
+    }
+
+    mod merge {
+        use super::*;
+        use crate::time_utils::date;
+        use crate::Source;
+        use crate::SpamScore;
+
+        fn spam_updates(updates: &[(u64, usize, &str)]) -> UserCollectionWithNativeUserValue {
+            let mut collection = empty_collection();
+            collection.add_user_value_iter(updates.iter().map(|(fid, score, day)| {
+                Fidded::from((
+                    DatedSpamUpdate::from(date(day), SpamScore::try_from(*score).unwrap()),
+                    Fid::from(*fid),
+                ))
+            }));
+            collection
+        }
+
+        #[test]
+        fn test_merge_with_itself_is_unchanged() {
+            let mut collection = dummy_data();
+            let report = collection.merge(&dummy_data(), CollisionPolicy::KeepFirst);
+            assert!(report.is_empty());
+            assert_eq!(collection, dummy_data());
+        }
+
+        #[test]
+        fn test_merge_adds_users_and_values() {
+            let mut collection = spam_updates(&[(1, 0, "2025-01-01")]);
+            let other = spam_updates(&[(1, 1, "2025-01-02"), (2, 2, "2025-01-01")]);
+            let report = collection.merge(&other, CollisionPolicy::KeepFirst);
+            assert_eq!(report.added_count(), 2);
+            assert_eq!(collection.user_count(), 2);
+            assert_eq!(
+                collection
+                    .user(1_u64)
+                    .unwrap()
+                    .user_values_of_kind::<DatedSpamUpdate>()
+                    .len(),
+                2
+            );
+        }
+
+        #[test]
+        fn test_merge_reports_collisions() {
+            let collection = spam_updates(&[(1, 0, "2025-01-01")]);
+            let other = spam_updates(&[(1, 2, "2025-01-01")]);
+            let (union, report) = collection.union(&other, CollisionPolicy::KeepLast);
+            assert_eq!(report.collisions().len(), 1);
+            assert_eq!(union, other);
+            assert_eq!(collection.user_count(), 1);
+        }
+
+        #[test]
+        fn test_merge_keeps_sources() {
+            let mut collection = empty_collection();
+            let mut other = empty_collection();
+            let source = other.add_user_value_iter_from_source(
+                Source::new("labels").with_commit("abcd"),
+                [Fidded::from((
+                    DatedSpamUpdate::from(date("2025-01-01"), SpamScore::One),
+                    Fid::from(1_u64),
+                ))],
+            );
+            collection.merge(&other, CollisionPolicy::KeepFirst);
+            assert_eq!(collection.source(source), other.source(source));
+            assert_eq!(
+                collection
+                    .user_values_from_source::<DatedSpamUpdate>(source)
+                    .count(),
+                1
+            );
+        }
+    }
+
+    mod remove_spam_updates_from_commit {
+        use super::*;
+        use crate::spam_score::SpamUpdate;
+        use crate::time_utils::date;
+        use crate::SpamScore;
+
+        fn commit(hash: &str) -> CommitHash {
+            CommitHash::try_from(hash.repeat(10)).unwrap()
+        }
+
+        #[test]
+        fn test_remove_spam_updates_from_commit() {
+            let mut collection = empty_collection();
+            let update = |score: SpamScore, source: Option<CommitHash>| {
+                let update = match source {
+                    Some(hash) => SpamUpdate::WithSourceCommit((score, hash)),
+                    None => SpamUpdate::WithoutSourceCommit(score),
+                };
+                Fidded::from((
+                    DatedSpamUpdate::from(date("2025-01-01"), update),
+                    Fid::from(1_u64),
+                ))
+            };
+            collection.add_user_value_iter([
+                update(SpamScore::Zero, Some(commit("abcd"))),
+                update(SpamScore::One, Some(commit("1234"))),
+                update(SpamScore::Two, None),
+            ]);
+
+            assert_eq!(
+                collection.remove_spam_updates_from_commit(commit("abcd")),
+                1
+            );
+            let scores: Vec<SpamScore> = collection
+                .user(1_u64)
+                .unwrap()
+                .user_values_of_kind::<DatedSpamUpdate>()
+                .iter()
+                .map(|x| x.score())
+                .collect();
+            assert_eq!(scores, [SpamScore::One, SpamScore::Two]);
+        }
+    }
 }
@@ -1,18 +1,22 @@
+use crate::core::Source;
+use crate::core::SourceId;
 use crate::Fid;
 use crate::UserCollectionWithNativeUserValue;
 use crate::UserStoreWithNativeUserValue;
 use log::warn;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 
-const LATEST_VERSION: u64 = 1;
+const LATEST_VERSION: u64 = 2;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename(serialize = "UserCollection"))]
 pub struct UserCollectionSerde {
     #[serde(default)]
     version: u64,
-    map: HashMap<Fid, UserStoreWithNativeUserValue>,
+    map: BTreeMap<Fid, UserStoreWithNativeUserValue>,
+    #[serde(default)]
+    sources: BTreeMap<SourceId, Source>,
 }
 
 impl UserCollectionSerde {
@@ -26,20 +30,40 @@ impl From<UserCollectionSerde> for UserCollectionWithNativeUserValue {
         if !value.is_latest_version() {
             warn!("Data is not latest version. Please overwrite your database.");
         }
-        value.map.into()
+        let mut collection = UserCollectionWithNativeUserValue::from(value.map);
+        for source in value.sources.into_values() {
+            collection.register_source(source);
+        }
+        collection
     }
 }
 
-impl From<UserCollectionWithNativeUserValue> for UserCollectionSerde {
-    fn from(value: UserCollectionWithNativeUserValue) -> Self {
+impl From<&UserCollectionWithNativeUserValue> for UserCollectionSerde {
+    fn from(value: &UserCollectionWithNativeUserValue) -> Self {
         let data = value.data();
         Self {
             version: LATEST_VERSION,
             map: data.clone(),
+            sources: value
+                .sources()
+                .map(|(id, source)| (id, source.clone()))
+                .collect(),
         }
     }
 }
 
+impl Serialize for UserCollectionWithNativeUserValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        UserCollectionSerde::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UserCollectionWithNativeUserValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        UserCollectionSerde::deserialize(deserializer).map(Self::from)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -72,6 +96,43 @@ pub mod tests {
         }
     }
 
+    mod v2 {
+        use super::*;
+        use crate::spam_score::DatedSpamUpdate;
+        use crate::time_utils::date;
+        use crate::Fidded;
+        use crate::SpamScore;
+
+        #[test]
+        fn test_round_trip_keeps_sources() {
+            let mut collection = UserCollectionWithNativeUserValue::default();
+            let update = |fid: u64| {
+                Fidded::from((
+                    DatedSpamUpdate::from(date("2025-01-01"), SpamScore::One),
+                    Fid::from(fid),
+                ))
+            };
+            let source = collection.add_user_value_iter_from_source(
+                Source::new("labels").with_file_path("spam.jsonl"),
+                [update(1)],
+            );
+            collection.add_user_value_iter([update(2)]);
+
+            let raw = serde_json::to_string(&collection).unwrap();
+            let data: UserCollectionSerde = serde_json::from_str(&raw).unwrap();
+            assert_eq!(data.version, 2);
+            let deserialized = UserCollectionWithNativeUserValue::from(data);
+            assert_eq!(deserialized, collection);
+            assert_eq!(
+                deserialized
+                    .user_values_from_source::<DatedSpamUpdate>(source)
+                    .map(|(fid, _)| fid)
+                    .collect::<Vec<_>>(),
+                [Fid::from(1_u64)]
+            );
+        }
+    }
+
     mod v0 {
         use super::*;
         #[test]
@@ -1,9 +1,12 @@
+use crate::core::SourceId;
 use crate::AnyNativeUserValue;
 use crate::Fid;
 use crate::UserStoreWithNativeUserValue;
 use chrono::NaiveDateTime;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
 
 const LATEST_VERSION: u64 = 2;
 
@@ -12,19 +15,48 @@ pub struct UserSerde {
     #[serde(default)]
     version: u64,
     #[serde(default)]
-    #[serde(rename(serialize = "user_values"))]
-    user_values_v2: Vec<AnyNativeUserValue>,
-    #[serde(default)]
-    #[serde(skip_serializing)]
-    user_values: Vec<(AnyNativeUserValue, NaiveDateTime)>,
+    user_values: Vec<UserValueEntry>,
     fid: Fid,
 }
 
+/// Version 1 stored every user value with the time it was added. Later versions store the plain
+/// value, or the value along with the id of its source if the source is known. All are accepted
+/// when deserializing.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum UserValueEntry {
+    Sourced {
+        value: AnyNativeUserValue,
+        source: SourceId,
+    },
+    Plain(AnyNativeUserValue),
+    Timestamped(AnyNativeUserValue, NaiveDateTime),
+}
+
+impl UserValueEntry {
+    fn new(value: AnyNativeUserValue, source: Option<SourceId>) -> Self {
+        match source {
+            Some(source) => Self::Sourced { value, source },
+            None => Self::Plain(value),
+        }
+    }
+
+    fn into_value(self) -> (AnyNativeUserValue, Option<SourceId>) {
+        match self {
+            Self::Sourced { value, source } => (value, Some(source)),
+            Self::Plain(x) | Self::Timestamped(x, _) => (x, None),
+        }
+    }
+}
+
 impl From<UserSerde> for UserStoreWithNativeUserValue {
     fn from(value: UserSerde) -> Self {
-        let user_values = value.user_values.into_iter().map(|x| x.0).collect();
+        let user_values = value
+            .user_values
+            .into_iter()
+            .map(UserValueEntry::into_value);
 
-        UserStoreWithNativeUserValue::from_user_values(value.fid, user_values)
+        UserStoreWithNativeUserValue::from_generic_user_values(value.fid, user_values)
     }
 }
 
@@ -32,9 +64,23 @@ impl From<UserStoreWithNativeUserValue> for UserSerde {
     fn from(value: UserStoreWithNativeUserValue) -> Self {
         Self {
             version: LATEST_VERSION,
-            user_values_v2: value.all_user_values().cloned().collect(),
-            user_values: Vec::new(),
+            user_values: value
+                .all_sourced_user_values()
+                .map(|(value, source)| UserValueEntry::new(value.into(), source))
+                .collect(),
             fid: value.fid(),
         }
     }
 }
+
+impl Serialize for UserStoreWithNativeUserValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        UserSerde::from(self.clone()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UserStoreWithNativeUserValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        UserSerde::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -1,72 +1,25 @@
 use crate::core::UserStore;
-use crate::user_serde::UserSerde;
+use crate::spam_score::compact_sourced_spam_updates;
+use crate::spam_score::DatedSpamUpdate;
 use crate::AnyNativeUserValue;
-use crate::Collidable;
-use crate::Fid;
-use crate::NativeUserValue;
-use crate::UserError;
-use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Hash)]
-#[serde(from = "UserSerde")]
-#[serde(into = "UserSerde")]
-pub struct UserStoreWithNativeUserValue {
-    fid: Fid,
-    core: UserStore<AnyNativeUserValue>,
-}
+/// A [`UserStore`] that can hold every [`NativeUserValue`](crate::NativeUserValue).
+pub type UserStoreWithNativeUserValue = UserStore<AnyNativeUserValue>;
 
 impl UserStoreWithNativeUserValue {
-    /// Check if a User has at least one value of type T.
-    pub fn has<T: NativeUserValue>(&self) -> bool {
-        self.core.has::<T>()
-    }
-
-    /// Add a [`UserValue`] to a [`User`]. Returns an error in case of Collision. This method
-    /// relies on Ts implementation of Collidable to determine collisions.
-    pub fn try_add_user_value<T>(&mut self, value: T) -> Result<(), UserError>
-    where
-        T: NativeUserValue + Collidable,
-    {
-        self.core.try_add_user_value(value)
-    }
-
-    /// Insert a new [`UserValue`]. Returns Ok() on duplicate.
-    /// This method does not check for collisions.
-    pub fn add_user_value<T>(&mut self, value: T)
-    where
-        T: NativeUserValue,
-    {
-        self.core.add_user_value(value);
-    }
-
-    pub fn all_user_values(&self) -> impl Iterator<Item = &AnyNativeUserValue> {
-        self.core.all_user_values()
-    }
-
-    /// Get all the [`UserValue`]s of type T. If there are no such values, the method returns an
-    /// empty vec.
-    pub fn user_values_of_kind<T>(&self) -> Vec<&T>
-    where
-        T: NativeUserValue,
-    {
-        self.core.user_values_of_kind::<T>().collect()
-    }
-
-    pub fn new(fid: impl Into<Fid>) -> Self {
-        let fid = fid.into();
-        Self {
-            fid,
-            core: UserStore::<AnyNativeUserValue>::from(fid),
+    /// Run-length compact the spam history of the user. Only the [`DatedSpamUpdate`]s where the
+    /// spam score changed are kept, along with the latest update. Every
+    /// [`UserWithSpamData`](crate::UserWithSpamData) query gives the same answer before and after
+    /// compaction. Returns the number of removed updates.
+    pub fn compact_spam_updates(&mut self) -> usize {
+        let updates = self.take_sourced_user_values_where(|_: &DatedSpamUpdate, _| true);
+        let count = updates.len();
+        let compacted = compact_sourced_spam_updates(updates);
+        let removed = count - compacted.len();
+        for (update, source) in compacted {
+            self.add_user_value_with_source(update, source);
         }
-    }
-
-    pub fn fid(&self) -> Fid {
-        self.core.fid()
-    }
-
-    pub(crate) fn from_user_values(fid: Fid, values: Vec<AnyNativeUserValue>) -> Self {
-        let core = UserStore::<AnyNativeUserValue>::from_generic_user_values(fid, values);
-        Self { fid, core }
+        removed
     }
 }
 
@@ -74,6 +27,7 @@ impl UserStoreWithNativeUserValue {
 pub mod tests {
     use crate::spam_score::DatedSpamUpdate;
     use crate::Fid;
+    use crate::NativeUserValue;
 
     use super::*;
 
@@ -105,4 +59,47 @@ pub mod tests {
         let user = create_new_user(1);
         assert!(user.user_values_of_kind::<DatedSpamUpdate>().is_empty());
     }
+
+    #[test]
+    fn test_dated_spam_updates_are_sorted_by_date() {
+        use crate::spam_score::SpamScore;
+        use crate::time_utils::date;
+        let mut user = create_new_user(1);
+        for (score, day) in [
+            (SpamScore::Two, "2025-03-01"),
+            (SpamScore::Zero, "2025-01-01"),
+            (SpamScore::One, "2025-02-01"),
+            (SpamScore::Two, "2025-01-01"),
+        ] {
+            user.add_user_value(DatedSpamUpdate::from(date(day), score));
+        }
+        let stored: Vec<(SpamScore, _)> = user
+            .user_values_of_kind::<DatedSpamUpdate>()
+            .iter()
+            .map(|x| (x.score(), x.date()))
+            .collect();
+        assert_eq!(
+            stored,
+            [
+                (SpamScore::Zero, date("2025-01-01")),
+                (SpamScore::Two, date("2025-01-01")),
+                (SpamScore::One, date("2025-02-01")),
+                (SpamScore::Two, date("2025-03-01")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values_are_stored_per_kind() {
+        use crate::spam_score::{SpamScore, SpamUpdate};
+        let mut user = create_new_user(1);
+        user.add_user_value(SpamScore::One);
+        user.add_user_value(SpamUpdate::from(SpamScore::Two));
+        assert_eq!(user.user_values_of_kind::<SpamScore>(), [SpamScore::One]);
+        assert_eq!(
+            user.user_values_of_kind::<SpamUpdate>(),
+            [SpamUpdate::from(SpamScore::Two)]
+        );
+        assert_eq!(user.all_user_values().count(), 2);
+    }
 }
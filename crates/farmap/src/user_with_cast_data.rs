@@ -1,12 +1,10 @@
-use crate::dated::Dated;
 use crate::is_user::IsUser;
 use crate::try_from_user::TryFromUser;
-use crate::CastType;
 use crate::Fid;
 use crate::UserStoreWithNativeUserValue;
 use thiserror::Error;
 
-/// A reference to a [`User`] of lifetime a that contains at least one CastType.
+/// A reference to a [`User`] of lifetime a that contains at least one Cast or CastType.
 #[derive(Debug, Clone, PartialEq)]
 pub struct UserWithCastData<'a> {
     user: &'a UserStoreWithNativeUserValue,
@@ -25,7 +23,7 @@ impl<'a> IsUser<'a> for UserWithCastData<'a> {
 impl<'a> TryFrom<&'a UserStoreWithNativeUserValue> for UserWithCastData<'a> {
     type Error = NoCastDataError;
     fn try_from(value: &'a UserStoreWithNativeUserValue) -> Result<Self, Self::Error> {
-        if value.has::<Dated<CastType>>() {
+        if value.has_cast_data() {
             Ok(Self { user: value })
         } else {
             Err(NoCastDataError)
@@ -40,7 +38,7 @@ pub struct NoCastDataError;
 impl<'a, T: IsUser<'a>> TryFromUser<'a, T> for UserWithCastData<'a> {
     type Error = NoCastDataError;
     fn try_from_user(value: T) -> Result<Self, Self::Error> {
-        if value.user().has::<Dated<CastType>>() {
+        if value.user().has_cast_data() {
             Ok(UserWithCastData { user: value.user() })
         } else {
             Err(NoCastDataError)
@@ -0,0 +1,86 @@
+use crate::dated::Dated;
+use crate::follow_count::FollowCount;
+use crate::is_user::IsUser;
+use crate::try_from_user::TryFromUser;
+use crate::Fid;
+use crate::UserStoreWithNativeUserValue;
+use chrono::NaiveDate;
+use thiserror::Error;
+
+/// A reference to a [`User`] of lifetime a that contains at least one [`FollowCount`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserWithFollowCountData<'a> {
+    user: &'a UserStoreWithNativeUserValue,
+}
+
+impl<'a> UserWithFollowCountData<'a> {
+    /// All the follow counts of the user, sorted by date.
+    pub fn follow_counts(&self) -> &'a [Dated<FollowCount>] {
+        self.user.user_values_of_kind::<Dated<FollowCount>>()
+    }
+
+    /// The follower counts of the user, sorted by date. Follow counts without a follower count are
+    /// skipped.
+    pub fn follower_counts(&self) -> impl Iterator<Item = Dated<u64>> + 'a {
+        self.follow_counts().iter().filter_map(|x| {
+            x.followers()
+                .map(|followers| Dated::from(x.date(), followers))
+        })
+    }
+
+    /// The latest follower count on or before the date.
+    pub fn follower_count_at_date(&self, date: NaiveDate) -> Option<u64> {
+        self.follower_counts()
+            .take_while(|x| x.date() <= date)
+            .last()
+            .map(Dated::into_inner)
+    }
+
+    pub fn latest_follower_count(&self) -> Option<u64> {
+        self.follower_counts().last().map(Dated::into_inner)
+    }
+
+    /// The change in follower count from the first to the last follower count from the start date
+    /// up to and including the end date. Returns None if there is no follower count in the period.
+    pub fn follower_growth(&self, start: NaiveDate, end: NaiveDate) -> Option<i64> {
+        let mut counts = self
+            .follower_counts()
+            .filter(|x| x.date() >= start && x.date() <= end)
+            .map(Dated::into_inner);
+        let first = counts.next()?;
+        let last = counts.last().unwrap_or(first);
+        Some(last as i64 - first as i64)
+    }
+}
+
+impl<'a> IsUser<'a> for UserWithFollowCountData<'a> {
+    fn fid(&self) -> Fid {
+        self.user.fid()
+    }
+
+    fn user(&self) -> &'a UserStoreWithNativeUserValue {
+        self.user
+    }
+}
+
+impl<'a> TryFrom<&'a UserStoreWithNativeUserValue> for UserWithFollowCountData<'a> {
+    type Error = NoFollowCountDataError;
+    fn try_from(value: &'a UserStoreWithNativeUserValue) -> Result<Self, Self::Error> {
+        if value.has::<Dated<FollowCount>>() {
+            Ok(Self { user: value })
+        } else {
+            Err(NoFollowCountDataError)
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[error("no follow count data in user")]
+pub struct NoFollowCountDataError;
+
+impl<'a, T: IsUser<'a>> TryFromUser<'a, T> for UserWithFollowCountData<'a> {
+    type Error = NoFollowCountDataError;
+    fn try_from_user(value: T) -> Result<Self, Self::Error> {
+        UserWithFollowCountData::try_from(value.user())
+    }
+}
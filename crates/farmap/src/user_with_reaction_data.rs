@@ -0,0 +1,78 @@
+use crate::dated::Dated;
+use crate::is_user::IsUser;
+use crate::reaction::Reaction;
+use crate::reaction::ReactionType;
+use crate::try_from_user::TryFromUser;
+use crate::Fid;
+use crate::UserStoreWithNativeUserValue;
+use chrono::NaiveDate;
+use thiserror::Error;
+
+/// A reference to a [`User`] of lifetime a that contains at least one [`Reaction`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserWithReactionData<'a> {
+    user: &'a UserStoreWithNativeUserValue,
+}
+
+impl<'a> UserWithReactionData<'a> {
+    /// All the reactions of the user, sorted by date.
+    pub fn reactions(&self) -> &'a [Dated<Reaction>] {
+        self.user.user_values_of_kind::<Dated<Reaction>>()
+    }
+
+    /// The reactions of the type, sorted by date.
+    pub fn reactions_of_type(
+        &self,
+        reaction_type: ReactionType,
+    ) -> impl Iterator<Item = &'a Dated<Reaction>> {
+        self.reactions()
+            .iter()
+            .filter(move |x| x.reaction_type() == reaction_type)
+    }
+
+    /// The number of reactions from the start date up to and including the end date.
+    pub fn reaction_count_between(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        let reactions = self.reactions();
+        let from = reactions.partition_point(|x| x.date() < start);
+        let to = reactions.partition_point(|x| x.date() <= end);
+        to.saturating_sub(from)
+    }
+
+    pub fn latest_reaction(&self) -> &'a Dated<Reaction> {
+        self.reactions()
+            .last()
+            .expect("user with reaction data has a reaction")
+    }
+}
+
+impl<'a> IsUser<'a> for UserWithReactionData<'a> {
+    fn fid(&self) -> Fid {
+        self.user.fid()
+    }
+
+    fn user(&self) -> &'a UserStoreWithNativeUserValue {
+        self.user
+    }
+}
+
+impl<'a> TryFrom<&'a UserStoreWithNativeUserValue> for UserWithReactionData<'a> {
+    type Error = NoReactionDataError;
+    fn try_from(value: &'a UserStoreWithNativeUserValue) -> Result<Self, Self::Error> {
+        if value.has::<Dated<Reaction>>() {
+            Ok(Self { user: value })
+        } else {
+            Err(NoReactionDataError)
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[error("no reaction data in user")]
+pub struct NoReactionDataError;
+
+impl<'a, T: IsUser<'a>> TryFromUser<'a, T> for UserWithReactionData<'a> {
+    type Error = NoReactionDataError;
+    fn try_from_user(value: T) -> Result<Self, Self::Error> {
+        UserWithReactionData::try_from(value.user())
+    }
+}
@@ -1,11 +1,14 @@
 use crate::fetch::ConversionError;
 use crate::is_user::IsUser;
+use crate::spam_score::spam_score_at_date;
+use crate::spam_score::spam_score_at_time;
 use crate::spam_score::DatedSpamUpdate;
 use crate::Fid;
 use crate::SpamScore;
 use crate::UserStoreWithNativeUserValue;
+use chrono::DateTime;
 use chrono::NaiveDate;
-use itertools::Itertools;
+use chrono::Utc;
 
 /// A User guaranteed to have at least one [SpamUpdate](crate::spam_score::SpamUpdate).
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -22,19 +25,21 @@ impl<'a> TryFrom<&'a UserStoreWithNativeUserValue> for UserWithSpamData<'a> {
 }
 
 impl<'a> UserWithSpamData<'a> {
+    /// The spam score of the latest update at or before the date. Returns None if the date is
+    /// before the earliest update. This is a binary search over the date-sorted updates.
     pub fn spam_score_at_date(&self, date: NaiveDate) -> Option<SpamScore> {
-        self.dated_spam_updates()
-            .iter()
-            .filter(|x| x.date() <= date)
-            .max_by_key(|x| x.date())
-            .map(|update| update.score())
+        spam_score_at_date(self.dated_spam_updates(), date)
     }
 
-    pub fn dated_spam_updates(&self) -> Vec<&DatedSpamUpdate> {
-        self.user
-            .all_user_values()
-            .flat_map(|user_value| user_value.specify_ref::<DatedSpamUpdate>())
-            .collect_vec()
+    /// The spam score of the latest update at or before the time. Updates without a timestamp are
+    /// taken to be at the start of their date.
+    pub fn spam_score_at_time(&self, time: DateTime<Utc>) -> Option<SpamScore> {
+        spam_score_at_time(self.dated_spam_updates(), time)
+    }
+
+    /// All the spam updates of the user, sorted by date and timestamp.
+    pub fn dated_spam_updates(&self) -> &'a [DatedSpamUpdate] {
+        self.user.user_values_of_kind::<DatedSpamUpdate>()
     }
 
     pub fn user(&self) -> &'a UserStoreWithNativeUserValue {
@@ -46,31 +51,18 @@ impl<'a> UserWithSpamData<'a> {
     }
 
     pub fn earliest_spam_update(&self) -> DatedSpamUpdate {
-        **self
-            .dated_spam_updates()
-            .iter()
-            .min_by_key(|user| user.date())
-            .expect("cannot be empty")
+        *self.dated_spam_updates().first().expect("cannot be empty")
     }
 
     pub fn latest_spam_update(&self) -> DatedSpamUpdate {
-        **self
-            .dated_spam_updates()
-            .iter()
-            .max_by_key(|user| user.date())
-            .expect("cannot be empty")
+        *self.dated_spam_updates().last().expect("cannot be empty")
     }
 }
 
 fn optioned_user_to_user_with_spam_data_conversion(
     value: &UserStoreWithNativeUserValue,
-) -> Option<UserWithSpamData> {
-    if value
-        .all_user_values()
-        .flat_map(|user_value| user_value.specify_ref::<DatedSpamUpdate>())
-        .count()
-        != 0
-    {
+) -> Option<UserWithSpamData<'_>> {
+    if value.has::<DatedSpamUpdate>() {
         Some(UserWithSpamData { user: value })
     } else {
         None
@@ -117,7 +109,7 @@ pub mod tests {
         user
     }
 
-    pub fn valid_spam_user(user: &UserStoreWithNativeUserValue) -> UserWithSpamData {
+    pub fn valid_spam_user(user: &UserStoreWithNativeUserValue) -> UserWithSpamData<'_> {
         UserWithSpamData::try_from(user).unwrap()
     }
 
@@ -191,4 +183,64 @@ pub mod tests {
             check_earliest_spam_update_date(&user, "2025-01-23");
         }
     }
+
+    mod compaction {
+        use super::*;
+        use crate::time_utils::date;
+
+        fn redundant_history_user() -> UserStoreWithNativeUserValue {
+            let mut user = create_new_user(1);
+            for (score, date) in [
+                (1, "2024-01-01"),
+                (1, "2024-01-08"),
+                (1, "2024-01-15"),
+                (0, "2024-01-22"),
+                (0, "2024-01-29"),
+                (2, "2024-02-05"),
+                (2, "2024-02-12"),
+                (2, "2024-02-12"),
+            ] {
+                add_spam_score(&mut user, score, date);
+            }
+            user
+        }
+
+        #[test]
+        fn test_queries_are_unchanged_after_compaction() {
+            let user = redundant_history_user();
+            let mut compacted_user = user.clone();
+            assert_eq!(compacted_user.compact_spam_updates(), 4);
+
+            let spam_user = valid_spam_user(&user);
+            let compacted_spam_user = valid_spam_user(&compacted_user);
+            assert_eq!(compacted_spam_user.dated_spam_updates().len(), 4);
+
+            let mut date = date("2023-12-25");
+            while date <= NaiveDate::from_ymd_opt(2024, 3, 1).unwrap() {
+                assert_eq!(
+                    spam_user.spam_score_at_date(date),
+                    compacted_spam_user.spam_score_at_date(date)
+                );
+                date = date.checked_add_days(Days::new(1)).unwrap();
+            }
+
+            assert_eq!(
+                spam_user.earliest_spam_update(),
+                compacted_spam_user.earliest_spam_update()
+            );
+            assert_eq!(
+                spam_user.latest_spam_update(),
+                compacted_spam_user.latest_spam_update()
+            );
+        }
+
+        #[test]
+        fn test_compaction_is_idempotent() {
+            let mut user = redundant_history_user();
+            user.compact_spam_updates();
+            let compacted = user.clone();
+            assert_eq!(user.compact_spam_updates(), 0);
+            assert_eq!(user, compacted);
+        }
+    }
 }
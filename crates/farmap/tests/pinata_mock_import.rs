@@ -2,9 +2,13 @@ use chrono::NaiveDate;
 use chrono::NaiveDateTime;
 use chrono::NaiveTime;
 use farmap::fetch::pinata_parser::{
-    followers_from_pinata_response, number_of_casts_from_response, reaction_times_from_response,
+    cast_meta_from_pinata_response, followers_from_pinata_response, number_of_casts_from_response,
+    reaction_times_from_response,
 };
 use farmap::fetch::PinataFetcher;
+use farmap::CastType;
+use farmap::Embed;
+use farmap::ReactionType;
 use std::collections::HashSet;
 use std::fs::read_to_string;
 use url::Url;
@@ -29,6 +33,11 @@ async fn test_commit_call_against_mock_pinata_data() {
         .expect("Mock API call should not fail");
     println!("{:?}", response);
     assert_eq!(1, number_of_casts_from_response(response).await.unwrap());
+
+    // the cast types keep the timestamps of the casts.
+    let response = fetcher.casts_by_fid(11720).await.unwrap();
+    let cast_types = cast_meta_from_pinata_response(response).await.unwrap();
+    assert!(cast_types[0].unfid().timestamp().is_some());
 }
 
 #[tokio::test]
@@ -95,3 +104,167 @@ async fn test_likes_from_pinata_data() {
     ]);
     assert_eq!(result, expected_result);
 }
+
+#[tokio::test]
+async fn test_profile_from_pinata_data() {
+    let mut server = mockito::Server::new_async().await;
+    let mock_data = read_to_string("./test-data/pinata-mock/api-body-user-data.json")
+        .expect("api file should exist in data dir");
+    let _ = server
+        .mock("GET", "/v1/userDataByFid?fid=11720")
+        .with_body(mock_data)
+        .create_async()
+        .await;
+
+    let fetcher = PinataFetcher::default()
+        .with_base_url(Url::parse(&format!("{}/v1/", &server.url())).unwrap());
+    let profile = fetcher
+        .fetch_profile_for_fid(11720)
+        .await
+        .unwrap()
+        .expect("mock data has user data");
+    assert_eq!(profile.date(), NaiveDate::from_ymd_opt(2022, 1, 1).unwrap());
+    assert_eq!(profile.username(), Some("testuser"));
+    assert_eq!(profile.display_name(), Some("Test User"));
+}
+
+#[tokio::test]
+async fn test_reactions_from_pinata_data() {
+    let mut server = mockito::Server::new_async().await;
+    let mock_data = read_to_string("./test-data/pinata-mock/api-body-likes.json")
+        .expect("api file should exist in data dir");
+    let _ = server
+        .mock("GET", "/v1/reactionsByFid?reaction_type=Like&fid=11720")
+        .with_body(mock_data)
+        .create_async()
+        .await;
+    let _ = server
+        .mock("GET", "/v1/reactionsByFid?reaction_type=Recast&fid=11720")
+        .with_body(r#"{"messages": []}"#)
+        .create_async()
+        .await;
+
+    let fetcher = PinataFetcher::default()
+        .with_base_url(Url::parse(&format!("{}/v1/", &server.url())).unwrap());
+    let reactions = fetcher.fetch_reactions_for_fid(11720).await.unwrap();
+    assert_eq!(reactions.len(), 2);
+    assert!(reactions
+        .iter()
+        .all(|x| x.reaction_type() == ReactionType::Like && x.target_fid() == Some(1_u64.into())));
+    assert_eq!(
+        reactions[0].target_cast(),
+        Some("0xa3f2b89c45d7e912fa6850cb984e32bdb69cc523")
+    );
+}
+
+#[tokio::test]
+async fn test_casts_from_pinata_data() {
+    let mut server = mockito::Server::new_async().await;
+    let mock_data = read_to_string("./test-data/pinata-mock/api-body-casts.json")
+        .expect("api file should exist in data dir");
+    let _ = server
+        .mock("GET", "/v1/castsByFid?fid=11720")
+        .with_body(mock_data)
+        .create_async()
+        .await;
+
+    let fetcher = PinataFetcher::default()
+        .with_base_url(Url::parse(&format!("{}/v1/", &server.url())).unwrap());
+    let parsed = fetcher.fetch_casts_for_fid(11720).await.unwrap();
+    assert_eq!(parsed.casts.len(), 3);
+
+    let reply = parsed.casts[1].clone().unfid();
+    assert_eq!(reply.hash(), Some("0x23456"));
+    assert_eq!(reply.parent_cast().unwrap().hash(), "0xabcde");
+    assert_eq!(reply.mentions(), [3_u64.into(), 5_u64.into()]);
+    assert_eq!(reply.embeds().len(), 2);
+    assert!(matches!(&reply.embeds()[1], Embed::Cast(id) if id.hash() == "0x99999"));
+
+    let long_cast = parsed.casts[2].clone().unfid();
+    assert_eq!(long_cast.cast_type(), CastType::LONG_CAST);
+    assert_eq!(
+        long_cast.parent_url(),
+        Some("https://warpcast.com/~/channel/rust")
+    );
+
+    assert_eq!(parsed.removes.len(), 1);
+    assert_eq!(parsed.removes[0].clone().unfid().target_hash(), "0x12345");
+
+    assert_eq!(parsed.unsupported.len(), 1);
+    assert_eq!(
+        parsed.unsupported[0].cast_type.as_deref(),
+        Some("HUNDRED_K_CAST")
+    );
+}
+
+#[tokio::test]
+async fn test_malformed_cast_messages_are_unsupported() {
+    let mut server = mockito::Server::new_async().await;
+    let _ = server
+        .mock("GET", "/v1/castsByFid?fid=11720")
+        .with_body(
+            r#"{"messages": [
+                {"data": {"type": "MESSAGE_TYPE_CAST_ADD", "fid": 11720,
+                    "castAddBody": {"type": "CAST"}}, "hash": "0x1"},
+                {"data": {"type": "MESSAGE_TYPE_CAST_REMOVE", "timestamp": 71778354,
+                    "castRemoveBody": {"targetHash": "0x1"}}, "hash": "0x2"},
+                {"data": {"type": "MESSAGE_TYPE_CAST_REMOVE", "fid": 11720,
+                    "timestamp": 71778354, "castRemoveBody": {}}, "hash": "0x3"},
+                {"data": {"type": "MESSAGE_TYPE_CAST_REMOVE", "fid": 11720,
+                    "timestamp": 71778354, "castRemoveBody": {"targetHash": "0x1"}}, "hash": "0x4"}
+            ]}"#,
+        )
+        .create_async()
+        .await;
+
+    let fetcher = PinataFetcher::default()
+        .with_base_url(Url::parse(&format!("{}/v1/", &server.url())).unwrap());
+    let parsed = fetcher.fetch_casts_for_fid(11720).await.unwrap();
+    assert_eq!(parsed.removes.len(), 1);
+    let hashes: Vec<_> = parsed
+        .unsupported
+        .iter()
+        .map(|x| x.hash.as_deref().unwrap())
+        .collect();
+    assert_eq!(hashes, ["0x1", "0x2", "0x3"]);
+}
+
+#[tokio::test]
+async fn test_paginated_links_from_pinata_data() {
+    let mut server = mockito::Server::new_async().await;
+    let mock_data = read_to_string("./test-data/pinata-mock/api-body-link.json")
+        .expect("api file should exist in data dir");
+    let _ = server
+        .mock(
+            "GET",
+            "/v1/linksByTargetFid?link_type=follow&target_fid=11720",
+        )
+        .with_body(mock_data)
+        .create_async()
+        .await;
+    // the mock data has a next page token, so the followers on the next page are fetched too.
+    let _ = server
+        .mock(
+            "GET",
+            "/v1/linksByTargetFid?link_type=follow&target_fid=11720&pageToken=W251bGwsbnVsbF0%3D",
+        )
+        .with_body(r#"{"messages": [{"data": {"fid": 3}}], "nextPageToken": ""}"#)
+        .create_async()
+        .await;
+    let _ = server
+        .mock("GET", "/v1/linksByFid?link_type=follow&fid=11720")
+        .with_body(r#"{"messages": []}"#)
+        .create_async()
+        .await;
+
+    let fetcher = PinataFetcher::default()
+        .with_base_url(Url::parse(&format!("{}/v1/", &server.url())).unwrap());
+    let followers = fetcher.fetch_followers_for_fid(11720).await.unwrap();
+    assert_eq!(followers.len(), 3);
+    assert_eq!(followers.last(), Some(&3));
+    assert!(fetcher
+        .fetch_following_for_fid(11720)
+        .await
+        .unwrap()
+        .is_empty());
+}